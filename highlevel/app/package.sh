@@ -9,6 +9,8 @@ mkdir -p target/approot/bin
 mkdir -p target/approot/certs
 cp target/arm-v7-none-eabi/debug/sphere-app target/approot/bin/app
 cp app_manifest.json target/approot
+# files under resources/ can be opened with storage::open_in_image_package
+if [ -d resources ]; then cp -r resources/. target/approot; fi
 
 azsphere image-package pack-application --input target/approot --destination-file target/sphere-app.image --verbose
 azsphere device sideload delete --component-id 00f3df71-a397-4a5e-89cb-7dde6486888d --verbose
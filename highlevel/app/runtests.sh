@@ -1,2 +1,4 @@
 #!/bin/bash
-cargo test --target x86_64-unknown-linux-gnu
\ No newline at end of file
+cargo test --target x86_64-unknown-linux-gnu
+(cd ../sphere-lib && cargo test --target x86_64-unknown-linux-gnu --features mock)
+(cd ../sphere-rt && cargo test --target x86_64-unknown-linux-gnu --features mock,uart,iot)
@@ -6,9 +6,18 @@ extern crate sphere_rt as std;
 #[cfg(not(test))]
 use std::prelude::v1::*;
 
+use core::cell::Cell;
+use core::time::Duration;
+
+use log::{info, warn};
+
 extern crate sphere_lib;
+use sphere_lib::eventloop::EventLoop;
+use sphere_lib::logging;
 use sphere_lib::mt3620_gpio::*;
-use sphere_lib::util::sleep;
+use sphere_lib::signal::{Signal, SIGTERM};
+use sphere_lib::time::Timer;
+use sphere_lib::Error;
 
 const MT3620_GPIO8: i32 = 8;
 const MT3620_GPIO9: i32 = 9;
@@ -17,43 +26,48 @@ const MT3620_RDB_LED1_GREEN: i32 = MT3620_GPIO9;
 const MT3620_RDB_LED1_BLUE: i32 = MT3620_GPIO10;
 const MT3620_RDB_LED1_RED: i32 = MT3620_GPIO8;
 
-#[no_mangle]
-fn start() {
-    println!("start");
-
-    let red = GpioPort::open(MT3620_RDB_LED1_RED);
-    let green = GpioPort::open(MT3620_RDB_LED1_GREEN);
-    let blue = GpioPort::open(MT3620_RDB_LED1_BLUE);
-
-    loop {
-        red.set_low();
-        green.set_low();
-        blue.set_low();
-        sleep(1);
-
-        red.set_high();
-        green.set_high();
-        blue.set_high();
-        sleep(1);
-
-        red.set_high();
-        green.set_low();
-        blue.set_high();
-        sleep(1);
-
-        red.set_high();
-        green.set_low();
-        blue.set_low();
-        sleep(1);
-
-        red.set_low();
-        green.set_low();
-        blue.set_high();
-        sleep(1);
-
-        red.set_low();
-        green.set_high();
-        blue.set_high();
-        sleep(1);
+// levels of red, green and blue, the RDB LEDs are lit when low
+const COLORS: [(bool, bool, bool); 6] = [
+    (false, false, false),
+    (true, true, true),
+    (true, false, true),
+    (true, false, false),
+    (false, false, true),
+    (false, true, true),
+];
+
+#[cfg(not(test))]
+std::entry!(start);
+
+#[cfg_attr(test, allow(dead_code))]
+fn start() -> Result<(), Error> {
+    logging::Builder::new().init().unwrap();
+    info!("start {:?}", std::env::args().skip(1).collect::<Vec<_>>());
+
+    std::crash::set_build_id(env!("CARGO_PKG_VERSION"));
+    if let Ok(Some(crash)) = std::crash::last_crash() {
+        warn!("last run {}", crash);
+        std::crash::clear()?;
     }
+
+    let red = GpioPort::open(MT3620_RDB_LED1_RED)?;
+    let green = GpioPort::open(MT3620_RDB_LED1_GREEN)?;
+    let blue = GpioPort::open(MT3620_RDB_LED1_BLUE)?;
+
+    let color = Cell::new(0);
+    let event_loop = EventLoop::new()?;
+    event_loop.register_timer(Timer::periodic(Duration::from_secs(1))?, |_| {
+        let (r, g, b) = COLORS[color.get()];
+        red.set(r).unwrap();
+        green.set(g).unwrap();
+        blue.set(b).unwrap();
+        color.set((color.get() + 1) % COLORS.len());
+    })?;
+    // returning from `start` runs the shutdown hooks
+    event_loop.register_signal(Signal::catch(SIGTERM)?, |event_loop| {
+        info!("stopping");
+        event_loop.stop();
+    })?;
+
+    event_loop.run()
 }
@@ -3,6 +3,9 @@ use alloc::boxed::Box;
 use alloc::format;
 use core::cell::RefCell;
 use core::ptr;
+use core::time::Duration;
+
+use log::debug;
 
 extern crate sphere_sys;
 
@@ -25,21 +28,31 @@ use sphere_sys::IoTHubDeviceClient_LL_SetDeviceTwinCallback;
 use sphere_sys::IoTHubDeviceClient_LL_SetOption;
 use sphere_sys::IoTHubMessage_CreateFromString;
 use sphere_sys::IoTHubMessage_Destroy;
+use sphere_sys::IoTHub_Deinit;
 use sphere_sys::IoTHub_Init;
 use sphere_sys::AZURE_SPHERE_PROV_RESULT_AZURE_SPHERE_PROV_RESULT_DEVICEAUTH_NOT_READY;
 use sphere_sys::AZURE_SPHERE_PROV_RESULT_AZURE_SPHERE_PROV_RESULT_OK;
 use sphere_sys::AZURE_SPHERE_PROV_RETURN_VALUE;
 use sphere_sys::DEVICE_TWIN_UPDATE_STATE;
+use sphere_sys::IOTHUB_CLIENT_CONFIRMATION_RESULT;
 use sphere_sys::IOTHUB_CLIENT_CONNECTION_STATUS;
 use sphere_sys::IOTHUB_CLIENT_CONNECTION_STATUS_REASON;
 use sphere_sys::IOTHUB_CLIENT_EVENT_CONFIRMATION_CALLBACK;
+use sphere_sys::IOTHUB_CLIENT_RESULT_TAG;
+use sphere_sys::IOTHUB_CLIENT_RESULT_TAG_IOTHUB_CLIENT_ERROR;
 use sphere_sys::IOTHUB_CLIENT_RESULT_TAG_IOTHUB_CLIENT_OK;
 use sphere_sys::IOTHUB_DEVICE_CLIENT_LL_HANDLE;
 use sphere_sys::IOTHUB_MESSAGE_HANDLE;
 use sphere_sys::IOTHUB_SECURITY_TYPE_TAG_IOTHUB_SECURITY_TYPE_X509;
 
-use crate::util::usleep;
+use crate::eventloop::EventLoop;
+use crate::fd::RawFd;
+use crate::shutdown;
+use crate::time::{sleep, Timer};
+use crate::Error;
 
+/// The SDK calls back with a pointer to the `AzureProvisioning`, so it must
+/// not move once callbacks are set.
 pub struct AzureProvisioning<'s> {
     provisioning_result: RefCell<AZURE_SPHERE_PROV_RETURN_VALUE>,
     provisioning_handle: RefCell<IOTHUB_DEVICE_CLIENT_LL_HANDLE>,
@@ -47,19 +60,18 @@ pub struct AzureProvisioning<'s> {
     pub authenticated: RefCell<bool>,
     status_callback: RefCell<Option<Box<dyn StatusCallback + 's>>>,
     method_callback: RefCell<Option<Box<dyn DeviceMethodCallback + 's>>>,
+    twin_callback: RefCell<Option<Box<dyn DeviceTwinCallback + 's>>>,
 }
 
-pub trait StatusCallback = Fn(u32, u32, &AzureProvisioning) -> ();
+pub trait StatusCallback = Fn(u32, u32, &AzureProvisioning);
+
+pub trait DeviceTwinCallback = Fn(u32, &[u8]);
 
-pub trait DeviceTwinCallback = Fn(u32, &[u8]) -> ();
+pub trait ConfirmationCallback = FnOnce(u32);
 
 // method, payload -> result, result_payload - seems the result_payload needs to be a zero terminated string?
 pub trait DeviceMethodCallback = FnMut(&str, &[u8]) -> (i32, alloc::string::String);
 
-struct StatusContext<'s, 'x: 's> {
-    provisioning: &'s AzureProvisioning<'x>,
-}
-
 impl<'s> AzureProvisioning<'s> {
     pub fn set_connection_status_callback<F>(&self, f: F)
     where
@@ -77,67 +89,59 @@ impl<'s> AzureProvisioning<'s> {
                 reason: IOTHUB_CLIENT_CONNECTION_STATUS_REASON,
                 user_context_callback: *mut c_void,
             ) {
-                let ctx_ptr = user_context_callback as *mut StatusContext;
-                let ctx = &mut *ctx_ptr;
+                let provisioning = &*(user_context_callback as *const AzureProvisioning);
 
-                *ctx.provisioning.authenticated.borrow_mut() = result == 0;
+                *provisioning.authenticated.borrow_mut() = result == 0;
 
-                let callback = &*ctx.provisioning.status_callback.borrow();
+                let callback = &*provisioning.status_callback.borrow();
                 if let Some(cb) = callback {
-                    (cb)(result, reason, ctx.provisioning);
+                    (cb)(result, reason, provisioning);
                 }
             }
 
             let callback = connection_status_callback;
 
-            let ctx = StatusContext { provisioning: self };
-            // Trait object with a stable address
-            let ctx = Box::new(ctx); // as Box<dyn StatusCallback>;
-                                     // Raw pointer
-            let ctx = Box::into_raw(ctx);
-
             IoTHubDeviceClient_LL_SetConnectionStatusCallback(
                 *self.provisioning_handle.borrow(),
                 Some(callback),
-                ctx as *mut _,
+                self as *const _ as *mut _,
             );
         }
     }
 
-    // TODO make it work like the other two callbacks internally
     pub fn set_device_twin_callback<F>(&self, f: F)
     where
         F: DeviceTwinCallback,
         F: 's,
     {
+        *self.twin_callback.borrow_mut() = Some(Box::new(f));
+        self.set_device_twin_callback_internal();
+    }
+
+    fn set_device_twin_callback_internal(&self) {
         unsafe {
-            unsafe extern "C" fn device_twin_callback<F>(
+            unsafe extern "C" fn device_twin_callback(
                 update_state: DEVICE_TWIN_UPDATE_STATE,
                 payload: *const c_uchar,
                 size: usize,
                 user_context_callback: *mut c_void,
-            ) where
-                F: DeviceTwinCallback,
-            {
+            ) {
                 let pl = core::slice::from_raw_parts(payload, size);
 
-                let callback_ptr = user_context_callback as *mut F;
-                let callback = &mut *callback_ptr;
-                callback(update_state, &pl);
+                let provisioning = &*(user_context_callback as *const AzureProvisioning);
+
+                let callback = &*provisioning.twin_callback.borrow();
+                if let Some(cb) = callback {
+                    (cb)(update_state, pl);
+                }
             }
 
-            let callback = device_twin_callback::<F>;
-            // Trait object with a stable address
-            let func = Box::new(f) as Box<dyn DeviceTwinCallback>;
-            // Thin pointer
-            let func = Box::new(func);
-            // Raw pointer
-            let func = Box::into_raw(func);
+            let callback = device_twin_callback;
 
             IoTHubDeviceClient_LL_SetDeviceTwinCallback(
                 *self.provisioning_handle.borrow(),
                 Some(callback),
-                func as *mut _,
+                self as *const _ as *mut _,
             );
         }
     }
@@ -161,26 +165,28 @@ impl<'s> AzureProvisioning<'s> {
                 response_size: *mut usize,
                 user_context_callback: *mut c_void,
             ) -> c_int {
-                let method = core::str::from_utf8(core::slice::from_raw_parts(
+                let method = match core::str::from_utf8(core::slice::from_raw_parts(
                     method_name as *const _,
                     count_until_zero(method_name),
-                ))
-                .unwrap();
+                )) {
+                    Ok(method) => method,
+                    // no callback can handle it, don't panic across FFI
+                    Err(_) => return 400,
+                };
                 let pl = core::slice::from_raw_parts(payload, size);
 
-                let ctx_ptr = user_context_callback as *mut StatusContext;
-                let ctx = &mut *ctx_ptr;
+                let provisioning = &*(user_context_callback as *const AzureProvisioning);
 
                 // method, payload -> result, result_payload
-                let callback = &mut *ctx.provisioning.method_callback.borrow_mut();
+                let callback = &mut *provisioning.method_callback.borrow_mut();
                 if let Some(cb) = callback {
-                    let (result, result_payload) = (cb)(&method, &pl);
+                    let (result, result_payload) = (cb)(method, pl);
 
                     // The response payload content. This must be heap-allocated, 'free' will be called on this buffer by the Azure IoT Hub SDK.
                     // apparently the SDK wants a zero terminated string
                     let null_ending_payload = format!("{}\0", result_payload);
                     *response_size = null_ending_payload.len();
-                    let response_buffer_ptr = malloc(null_ending_payload.len() as u32);
+                    let response_buffer_ptr = malloc(null_ending_payload.len() as _);
                     core::ptr::copy(
                         null_ending_payload.as_ptr(),
                         response_buffer_ptr as *mut _,
@@ -196,16 +202,10 @@ impl<'s> AzureProvisioning<'s> {
 
             let callback = device_method_callback;
 
-            let ctx = StatusContext { provisioning: self };
-            // Trait object with a stable address
-            let ctx = Box::new(ctx); // as Box<dyn StatusCallback>;
-                                     // Raw pointer
-            let ctx = Box::into_raw(ctx);
-
             IoTHubDeviceClient_LL_SetDeviceMethodCallback(
                 *self.provisioning_handle.borrow(),
                 Some(callback),
-                ctx as *mut _,
+                self as *const _ as *mut _,
             );
         }
     }
@@ -214,7 +214,17 @@ impl<'s> AzureProvisioning<'s> {
         unsafe { IoTHubDeviceClient_LL_DoWork(*self.provisioning_handle.borrow()) };
     }
 
-    pub fn set_keep_alive_seconds(&self, seconds: u32) -> Result<&'static str, &'static str> {
+    /// Pumps `do_work` every `interval` from `event_loop` instead of by hand.
+    /// Unregister the returned timer to stop.
+    pub fn do_work_on(
+        &'s self,
+        event_loop: &EventLoop<'s>,
+        interval: Duration,
+    ) -> Result<RawFd, Error> {
+        event_loop.register_timer(Timer::periodic(interval)?, move |_| self.do_work())
+    }
+
+    pub fn set_keep_alive_seconds(&self, seconds: u32) -> Result<(), Error> {
         let keep_alive_option = b"keepalive\0";
 
         let res = unsafe {
@@ -225,54 +235,78 @@ impl<'s> AzureProvisioning<'s> {
             )
         };
 
-        if res == IOTHUB_CLIENT_RESULT_TAG_IOTHUB_CLIENT_OK {
-            Ok("ok")
-        } else {
-            Err("Unable to set keep alive timeout")
+        check(res)
+    }
+
+    pub fn send_telemetry(&self, payload: &str) -> Result<(), Error> {
+        self.send_event(payload, None, ptr::null_mut())
+    }
+
+    /// Like `send_telemetry`, `on_confirmation` gets the
+    /// `IOTHUB_CLIENT_CONFIRMATION_RESULT` once the message was delivered or
+    /// given up on. `do_work` must keep running until then.
+    pub fn send_telemetry_confirmed<F>(
+        &self,
+        payload: &str,
+        on_confirmation: F,
+    ) -> Result<(), Error>
+    where
+        F: ConfirmationCallback,
+        F: 's,
+    {
+        unsafe extern "C" fn confirmation_callback(
+            result: IOTHUB_CLIENT_CONFIRMATION_RESULT,
+            user_context_callback: *mut c_void,
+        ) {
+            let callback =
+                Box::from_raw(user_context_callback as *mut Box<dyn ConfirmationCallback>);
+            callback(result);
         }
+
+        // Thin pointer, owned by the SDK until it confirms
+        let callback = Box::new(Box::new(on_confirmation) as Box<dyn ConfirmationCallback + 's>);
+        let callback = Box::into_raw(callback);
+
+        let result = self.send_event(payload, Some(confirmation_callback), callback as *mut _);
+        if result.is_err() {
+            drop(unsafe { Box::from_raw(callback) });
+        }
+        result
     }
 
-    pub fn send_telemetry(&self, payload: &str) -> Result<&'static str, &'static str> {
+    fn send_event(
+        &self,
+        payload: &str,
+        callback: IOTHUB_CLIENT_EVENT_CONFIRMATION_CALLBACK,
+        context: *mut c_void,
+    ) -> Result<(), Error> {
         let null_ending_payload = format!("{}\0", payload);
 
-        let success = unsafe {
-            let res;
-
+        let result = unsafe {
             let message_handle: Box<IOTHUB_MESSAGE_HANDLE> = Box::new(
                 IoTHubMessage_CreateFromString(null_ending_payload.as_ptr() as *const i8),
             );
 
-            if ((*message_handle) as *const _) != ptr::null() {
-                let callback: IOTHUB_CLIENT_EVENT_CONFIRMATION_CALLBACK = None;
+            if !(*message_handle).is_null() {
                 let send_result = IoTHubDeviceClient_LL_SendEventAsync(
                     *self.provisioning_handle.borrow(),
                     *message_handle,
                     callback,
-                    ptr::null_mut(),
+                    context,
                 );
 
                 IoTHubMessage_Destroy(*message_handle);
 
-                if send_result == IOTHUB_CLIENT_RESULT_TAG_IOTHUB_CLIENT_OK {
-                    res = true;
-                } else {
-                    res = false;
-                }
+                send_result
             } else {
-                res = false;
+                IOTHUB_CLIENT_RESULT_TAG_IOTHUB_CLIENT_ERROR
             }
-
-            res
         };
 
-        if success {
-            Ok("Message sent")
-        } else {
-            Err("Message send failed")
-        }
+        check(result)
     }
 
-    pub fn reconnect(&self) {
+    pub fn reconnect(&self) -> Result<(), Error> {
         unsafe {
             IoTHubDeviceClient_LL_Destroy(*self.provisioning_handle.borrow());
             *self.authenticated.borrow_mut() = false;
@@ -281,7 +315,7 @@ impl<'s> AzureProvisioning<'s> {
             let null_ending_scope_id = format!("{}\0", self.scope_id);
             let mut handle = Box::<IOTHUB_DEVICE_CLIENT_LL_HANDLE>::new_uninit();
 
-            crate::logging::log("azureiot: destroyed old handle");
+            debug!("destroyed old handle");
 
             loop {
                 let result: AZURE_SPHERE_PROV_RETURN_VALUE = {
@@ -293,12 +327,12 @@ impl<'s> AzureProvisioning<'s> {
                     )
                 };
 
-                crate::logging::log(&format!("azureioit: got result {}", result.result));
+                debug!("provisioning result {}", result.result);
 
                 if result.result
                     == AZURE_SPHERE_PROV_RESULT_AZURE_SPHERE_PROV_RESULT_DEVICEAUTH_NOT_READY
                 {
-                    usleep(500);
+                    sleep(Duration::from_micros(500));
                     continue;
                 }
 
@@ -309,27 +343,28 @@ impl<'s> AzureProvisioning<'s> {
 
                     self.set_connection_status_callback_internal();
                     self.set_device_method_callback_internal();
+                    self.set_device_twin_callback_internal();
 
                     self.do_work();
 
                     *self.authenticated.borrow_mut() = true;
 
-                    break;
+                    break Ok(());
                 } else {
-                    break;
+                    break Err(provisioning_error(&result));
                 }
             }
         }
     }
 
     pub fn is_authenticated(&self) -> bool {
-        *self.authenticated.borrow() == true
+        *self.authenticated.borrow()
     }
 
     pub fn azure_create_device_auth_provisioning(
         scope_id: &'s str,
         wait_for_auth_ready: bool,
-    ) -> Result<AzureProvisioning<'s>, &'static str> {
+    ) -> Result<AzureProvisioning<'s>, Error> {
         let null_ending_scope_id = format!("{}\0", scope_id);
 
         let mut handle = Box::<IOTHUB_DEVICE_CLIENT_LL_HANDLE>::new_uninit();
@@ -348,7 +383,7 @@ impl<'s> AzureProvisioning<'s> {
                 && result.result
                     == AZURE_SPHERE_PROV_RESULT_AZURE_SPHERE_PROV_RESULT_DEVICEAUTH_NOT_READY
             {
-                usleep(500);
+                sleep(Duration::from_micros(500));
                 continue;
             }
 
@@ -358,13 +393,14 @@ impl<'s> AzureProvisioning<'s> {
                 break Ok(AzureProvisioning {
                     provisioning_result: RefCell::new(result),
                     provisioning_handle: RefCell::new(real_handle),
-                    scope_id: scope_id,
+                    scope_id,
                     authenticated: RefCell::new(true),
                     status_callback: RefCell::new(None),
                     method_callback: RefCell::new(None),
+                    twin_callback: RefCell::new(None),
                 });
             } else {
-                break Err("Provisioning failed");
+                break Err(provisioning_error(&result));
             }
         }
     }
@@ -373,7 +409,7 @@ impl<'s> AzureProvisioning<'s> {
     pub fn azure_create_from_device_auth(
         uri: &str,
         device_id: &str,
-    ) -> Result<AzureProvisioning<'s>, &'static str> {
+    ) -> Result<AzureProvisioning<'s>, Error> {
         let null_ending_uri = format!("{}\0", uri);
         let null_ending_device_id = format!("{}\0", device_id);
 
@@ -396,14 +432,15 @@ impl<'s> AzureProvisioning<'s> {
                 let device_id_option: u32 = 1;
                 let device_id_option_ptr = (&device_id_option) as *const u32;
 
-                if IoTHubDeviceClient_LL_SetOption(
+                let set_device_id = check(IoTHubDeviceClient_LL_SetOption(
                     result,
                     format!("{}\0", "SetDeviceId").as_ptr() as *const i8,
                     device_id_option_ptr as *const _,
-                ) != IOTHUB_CLIENT_RESULT_TAG_IOTHUB_CLIENT_OK
-                {
-                    return Err("Connect failed in SetDeviceId");
+                ));
+                if set_device_id.is_err() {
+                    IoTHubDeviceClient_LL_Destroy(result);
                 }
+                set_device_id?;
             };
 
             let real_handle = result;
@@ -421,16 +458,17 @@ impl<'s> AzureProvisioning<'s> {
                 authenticated: RefCell::new(true),
                 status_callback: RefCell::new(None),
                 method_callback: RefCell::new(None),
+                twin_callback: RefCell::new(None),
             })
         } else {
-            Err("Connect failed")
+            Err(Error::IotHub(IOTHUB_CLIENT_RESULT_TAG_IOTHUB_CLIENT_ERROR))
         }
     }
 
     // RECONNECT doesn't work in this case! Need to be added
     pub fn azure_create_from_connection_string(
         connection_string: &str,
-    ) -> Result<AzureProvisioning<'s>, &'static str> {
+    ) -> Result<AzureProvisioning<'s>, Error> {
         let null_ending_connection_string = format!("{}\0", connection_string);
 
         let result: IOTHUB_DEVICE_CLIENT_LL_HANDLE = unsafe {
@@ -457,29 +495,63 @@ impl<'s> AzureProvisioning<'s> {
                 authenticated: RefCell::new(true),
                 status_callback: RefCell::new(None),
                 method_callback: RefCell::new(None),
+                twin_callback: RefCell::new(None),
             })
         } else {
-            Err("Connect failed")
+            Err(Error::IotHub(IOTHUB_CLIENT_RESULT_TAG_IOTHUB_CLIENT_ERROR))
         }
     }
 
-    pub fn init() -> i32 {
-        unsafe { IoTHub_Init() }
+    /// Initializes the IoT Hub client library once per app, it is
+    /// deinitialized on `shutdown::run`.
+    pub fn init() -> Result<(), Error> {
+        match unsafe { IoTHub_Init() } {
+            0 => {
+                shutdown::register("azureiot", || unsafe { IoTHub_Deinit() }).forget();
+                Ok(())
+            }
+            _ => Err(Error::IotHub(IOTHUB_CLIENT_RESULT_TAG_IOTHUB_CLIENT_ERROR)),
+        }
     }
 
-    pub fn set_option(&self, option: &str, data: &str) -> u32 {
+    pub fn set_option(&self, option: &str, data: &str) -> Result<(), Error> {
         let null_ending_option_name = format!("{}\0", option);
         let null_ending_certs = format!("{}\0", data);
-        unsafe {
+        check(unsafe {
             IoTHubDeviceClient_LL_SetOption(
                 *self.provisioning_handle.borrow(),
                 null_ending_option_name.as_ptr() as *const _,
                 null_ending_certs.as_ptr() as *const _,
             )
+        })
+    }
+}
+
+impl<'s> Drop for AzureProvisioning<'s> {
+    fn drop(&mut self) {
+        let handle = *self.provisioning_handle.borrow();
+        if !handle.is_null() {
+            unsafe { IoTHubDeviceClient_LL_Destroy(handle) };
         }
     }
 }
 
+fn check(result: IOTHUB_CLIENT_RESULT_TAG) -> Result<(), Error> {
+    if result == IOTHUB_CLIENT_RESULT_TAG_IOTHUB_CLIENT_OK {
+        Ok(())
+    } else {
+        Err(Error::IotHub(result))
+    }
+}
+
+fn provisioning_error(result: &AZURE_SPHERE_PROV_RETURN_VALUE) -> Error {
+    Error::Provisioning {
+        result: result.result,
+        prov_device_error: result.prov_device_error,
+        iothub_client_error: result.iothub_client_error,
+    }
+}
+
 unsafe fn count_until_zero(ptr: *const i8) -> usize {
     let mut count: isize = 0;
     loop {
@@ -490,3 +562,6 @@ unsafe fn count_until_zero(ptr: *const i8) -> usize {
         count += 1;
     }
 }
+
+#[cfg(all(test, feature = "mock"))]
+mod tests;
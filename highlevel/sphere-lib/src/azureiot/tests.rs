@@ -0,0 +1,175 @@
+use alloc::rc::Rc;
+use alloc::string::String;
+use alloc::vec::Vec;
+use core::cell::{Cell, RefCell};
+
+use sphere_sys::mock::{self, IotHubOption};
+use sphere_sys::AZURE_SPHERE_PROV_RESULT_AZURE_SPHERE_PROV_RESULT_DEVICEAUTH_NOT_READY;
+use sphere_sys::AZURE_SPHERE_PROV_RESULT_AZURE_SPHERE_PROV_RESULT_NETWORK_NOT_READY;
+use sphere_sys::DEVICE_TWIN_UPDATE_STATE_TAG_DEVICE_TWIN_UPDATE_PARTIAL;
+use sphere_sys::IOTHUB_CLIENT_CONFIRMATION_RESULT_TAG_IOTHUB_CLIENT_CONFIRMATION_OK;
+use sphere_sys::IOTHUB_CLIENT_CONNECTION_STATUS_REASON_TAG_IOTHUB_CLIENT_CONNECTION_OK;
+use sphere_sys::IOTHUB_CLIENT_CONNECTION_STATUS_TAG_IOTHUB_CLIENT_CONNECTION_AUTHENTICATED;
+use sphere_sys::IOTHUB_CLIENT_CONNECTION_STATUS_TAG_IOTHUB_CLIENT_CONNECTION_UNAUTHENTICATED;
+use sphere_sys::IOTHUB_CLIENT_RESULT_TAG_IOTHUB_CLIENT_ERROR;
+
+use super::AzureProvisioning;
+use crate::Error;
+
+const SCOPE_ID: &str = "0ne000ABCDE";
+
+fn connect() -> AzureProvisioning<'static> {
+    AzureProvisioning::azure_create_from_connection_string("HostName=example").unwrap()
+}
+
+#[test]
+fn waits_until_device_auth_is_ready() {
+    mock::script_provisioning(&[
+        AZURE_SPHERE_PROV_RESULT_AZURE_SPHERE_PROV_RESULT_DEVICEAUTH_NOT_READY,
+        AZURE_SPHERE_PROV_RESULT_AZURE_SPHERE_PROV_RESULT_DEVICEAUTH_NOT_READY,
+    ]);
+
+    let provisioning =
+        AzureProvisioning::azure_create_device_auth_provisioning(SCOPE_ID, true).unwrap();
+    assert!(provisioning.is_authenticated());
+    let name = "IoTHubDeviceClient_LL_CreateWithAzureSphereDeviceAuthProvisioning";
+    assert_eq!(mock::call_count(name), 3);
+}
+
+#[test]
+fn reports_provisioning_errors() {
+    mock::script_provisioning(&[
+        AZURE_SPHERE_PROV_RESULT_AZURE_SPHERE_PROV_RESULT_NETWORK_NOT_READY,
+    ]);
+
+    let result = AzureProvisioning::azure_create_device_auth_provisioning(SCOPE_ID, true);
+    assert_eq!(
+        result.err(),
+        Some(Error::Provisioning {
+            result: AZURE_SPHERE_PROV_RESULT_AZURE_SPHERE_PROV_RESULT_NETWORK_NOT_READY,
+            prov_device_error: 0,
+            iothub_client_error: 0,
+        })
+    );
+
+    mock::fail_next("IoTHubDeviceClient_LL_CreateFromConnectionString", 1);
+    let result = AzureProvisioning::azure_create_from_connection_string("HostName=example");
+    let error = IOTHUB_CLIENT_RESULT_TAG_IOTHUB_CLIENT_ERROR;
+    assert_eq!(result.err(), Some(Error::IotHub(error)));
+}
+
+#[test]
+fn sets_options() {
+    let provisioning = connect();
+    provisioning.set_keep_alive_seconds(30).unwrap();
+    provisioning
+        .set_option("TrustedCerts", "-----BEGIN")
+        .unwrap();
+
+    let handle = *provisioning.provisioning_handle.borrow();
+    assert_eq!(
+        mock::iothub_options(handle),
+        [
+            (String::from("keepalive"), IotHubOption::Int(30)),
+            (
+                String::from("TrustedCerts"),
+                IotHubOption::Str(String::from("-----BEGIN"))
+            ),
+        ]
+    );
+
+    let error = IOTHUB_CLIENT_RESULT_TAG_IOTHUB_CLIENT_ERROR;
+    mock::fail_next("IoTHubDeviceClient_LL_SetOption", error as i32);
+    assert_eq!(
+        provisioning.set_keep_alive_seconds(60),
+        Err(Error::IotHub(error))
+    );
+}
+
+#[test]
+fn confirms_telemetry_from_do_work() {
+    let provisioning = connect();
+    let confirmation = Rc::new(Cell::new(None));
+
+    provisioning.send_telemetry("{\"a\":1}").unwrap();
+    let confirmed = confirmation.clone();
+    provisioning
+        .send_telemetry_confirmed("{\"b\":2}", move |result| confirmed.set(Some(result)))
+        .unwrap();
+    assert_eq!(mock::iothub_sent_messages(), ["{\"a\":1}", "{\"b\":2}"]);
+    assert_eq!(confirmation.get(), None);
+
+    provisioning.do_work();
+    let ok = IOTHUB_CLIENT_CONFIRMATION_RESULT_TAG_IOTHUB_CLIENT_CONFIRMATION_OK;
+    assert_eq!(confirmation.get(), Some(ok));
+}
+
+#[test]
+fn reports_failed_sends() {
+    let provisioning = connect();
+
+    mock::fail_next("IoTHubMessage_CreateFromString", 1);
+    let result = provisioning.send_telemetry_confirmed("{}", |_| panic!("not sent"));
+    let error = IOTHUB_CLIENT_RESULT_TAG_IOTHUB_CLIENT_ERROR;
+    assert_eq!(result, Err(Error::IotHub(error)));
+
+    provisioning.do_work();
+    assert!(mock::iothub_sent_messages().is_empty());
+}
+
+#[test]
+fn tracks_the_connection_status() {
+    let provisioning = connect();
+    let statuses = Rc::new(RefCell::new(Vec::new()));
+
+    let received = statuses.clone();
+    provisioning.set_connection_status_callback(move |status, reason, _| {
+        received.borrow_mut().push((status, reason))
+    });
+
+    let reason = IOTHUB_CLIENT_CONNECTION_STATUS_REASON_TAG_IOTHUB_CLIENT_CONNECTION_OK;
+    let unauthenticated =
+        IOTHUB_CLIENT_CONNECTION_STATUS_TAG_IOTHUB_CLIENT_CONNECTION_UNAUTHENTICATED;
+    mock::iothub_set_connection_status(unauthenticated, reason);
+    assert!(!provisioning.is_authenticated());
+
+    let authenticated = IOTHUB_CLIENT_CONNECTION_STATUS_TAG_IOTHUB_CLIENT_CONNECTION_AUTHENTICATED;
+    mock::iothub_set_connection_status(authenticated, reason);
+    assert!(provisioning.is_authenticated());
+    assert_eq!(
+        *statuses.borrow(),
+        [(unauthenticated, reason), (authenticated, reason)]
+    );
+}
+
+#[test]
+fn delivers_device_twin_updates() {
+    let provisioning = connect();
+    let updates = Rc::new(RefCell::new(Vec::new()));
+
+    let received = updates.clone();
+    provisioning.set_device_twin_callback(move |state, payload| {
+        received.borrow_mut().push((state, payload.to_vec()))
+    });
+
+    let partial = DEVICE_TWIN_UPDATE_STATE_TAG_DEVICE_TWIN_UPDATE_PARTIAL;
+    mock::iothub_update_device_twin(partial, b"{\"led\":true}");
+    assert_eq!(*updates.borrow(), [(partial, b"{\"led\":true}".to_vec())]);
+}
+
+#[test]
+fn answers_direct_methods() {
+    let provisioning = connect();
+    assert_eq!(mock::iothub_invoke_method("reboot", b"{}"), None);
+
+    provisioning.set_device_method_callback(|method, payload| match method {
+        "echo" => (200, String::from_utf8(payload.to_vec()).unwrap()),
+        _ => (404, String::from("{}")),
+    });
+
+    let response = mock::iothub_invoke_method("echo", b"{\"x\":1}");
+    // the SDK gets a zero terminated payload
+    assert_eq!(response, Some((200, b"{\"x\":1}\0".to_vec())));
+    let response = mock::iothub_invoke_method("reboot", b"{}");
+    assert_eq!(response, Some((404, b"{}\0".to_vec())));
+}
@@ -3,15 +3,21 @@
 extern crate sphere_sys;
 
 use sphere_sys::std::os::raw::c_char;
+use sphere_sys::std::os::raw::c_long;
 use sphere_sys::std::os::raw::c_void;
 
+use sphere_sys::curl_easy_cleanup;
 use sphere_sys::curl_easy_getinfo;
 use sphere_sys::curl_easy_init;
 use sphere_sys::curl_easy_perform;
 use sphere_sys::curl_easy_setopt;
+use sphere_sys::curl_global_cleanup;
 use sphere_sys::curl_global_init;
 use sphere_sys::curl_slist_append;
+use sphere_sys::curl_slist_free_all;
 use sphere_sys::curl_write_callback;
+use sphere_sys::CURLcode;
+use sphere_sys::CURLcode_CURLE_FAILED_INIT;
 use sphere_sys::CURLcode_CURLE_OK;
 use sphere_sys::CURLoption_CURLOPT_CAINFO;
 use sphere_sys::CURLoption_CURLOPT_CUSTOMREQUEST;
@@ -28,41 +34,47 @@ use sphere_sys::CURL;
 use sphere_sys::CURLINFO_CURLINFO_RESPONSE_CODE;
 use sphere_sys::CURL_GLOBAL_ALL;
 
+use crate::shutdown;
 use crate::storage::get_absolute_path_in_image_package;
+use crate::Error;
 use alloc::boxed::Box;
 use alloc::format;
 use alloc::string::String;
+use alloc::vec::Vec;
 use core::ptr::*;
 
-pub fn curl_init() -> Result<&'static str, &'static str> {
-    unsafe {
-        if curl_global_init(CURL_GLOBAL_ALL as i32) != CURLcode_CURLE_OK {
-            Err("Unable to initialize curl")
-        } else {
-            Ok("Ok")
-        }
+/// Initializes curl once per app, it is cleaned up on `shutdown::run`.
+pub fn curl_init() -> Result<(), Error> {
+    check(unsafe { curl_global_init(CURL_GLOBAL_ALL as i32) })?;
+    shutdown::register("curl", || unsafe { curl_global_cleanup() }).forget();
+    Ok(())
+}
+
+fn check(code: CURLcode) -> Result<(), Error> {
+    if code == CURLcode_CURLE_OK {
+        Ok(())
+    } else {
+        Err(Error::Curl(code))
     }
 }
 
-pub trait CurlWriteCallback<'a> = FnMut(&[u8], bool) -> () + 'a;
+pub trait CurlWriteCallback<'a> = FnMut(&[u8], bool) + 'a;
 
 pub struct Curl {
     handle: *mut CURL,
 }
 
 struct Context<'a> {
-    curl: &'a Curl,
     write_callback: Option<Box<dyn CurlWriteCallback<'a> + 'a>>,
 }
 
-// TODO call `curl_easy_cleanup(self.handle);` when dropped
 impl Curl {
     pub fn get_request_as_string(
         &self,
         url: &str,
         ca_file: &str,
         authenticated: bool,
-    ) -> Result<String, u32> {
+    ) -> Result<String, Error> {
         unsafe extern "C" fn write_callback_c(
             buffer: *mut c_char,
             _size: usize,
@@ -81,13 +93,13 @@ impl Curl {
             nitems
         }
 
-        let null_ending_ca_file =
-            format!("{}\0", get_absolute_path_in_image_package(ca_file).unwrap());
+        let null_ending_ca_file = format!("{}\0", get_absolute_path_in_image_package(ca_file)?);
 
-        let mut response = String::new();
+        // a chunk may end within a multi-byte character, decoded at the end
+        let mut response = Vec::new();
 
         let write_callback = |data: &[u8], _done: bool| {
-            response.push_str(core::str::from_utf8(data).unwrap());
+            response.extend_from_slice(data);
         };
 
         unsafe {
@@ -111,7 +123,7 @@ impl Curl {
         ) -> u32 {
             DeviceAuth_SslCtxFunc(sslctx);
             sphere_sys::CURLcode_CURLE_OK
-        };
+        }
 
         if authenticated {
             unsafe {
@@ -127,7 +139,6 @@ impl Curl {
         }
 
         let ctx = Context {
-            curl: self,
             write_callback: Some(Box::new(write_callback)),
         };
         let ctx = Box::new(ctx);
@@ -138,31 +149,18 @@ impl Curl {
         }
 
         let null_ending = format!("{}\0", url);
-        let curl_result: u32 = 0;
+        let curl_result;
         unsafe {
             curl_easy_setopt(self.handle, CURLoption_CURLOPT_URL, null_ending.as_ptr());
-            let _curl_result = curl_easy_perform(self.handle);
-            curl_easy_setopt(self.handle, CURLoption_CURLOPT_WRITEDATA, null::<c_void>());
-        }
-
-        unsafe {
+            curl_result = curl_easy_perform(self.handle);
             curl_easy_setopt(self.handle, CURLoption_CURLOPT_WRITEDATA, null::<c_void>());
+            drop(Box::from_raw(ctx));
         }
 
-        if curl_result == CURLcode_CURLE_OK {
-            let mut response_code: u32 = 0;
-            unsafe {
-                curl_easy_getinfo(self.handle, CURLINFO_CURLINFO_RESPONSE_CODE, &response_code);
-            }
+        check(curl_result)?;
+        self.check_response_code()?;
 
-            if response_code >= 200 && response_code <= 299 {
-                Ok(response)
-            } else {
-                Err(response_code)
-            }
-        } else {
-            Err(9999)
-        }
+        String::from_utf8(response).map_err(|error| Error::Utf8(error.utf8_error()))
     }
 
     pub fn post_request_as_string(
@@ -172,7 +170,7 @@ impl Curl {
         content_type: &str,
         ca_file: &str,
         authenticated: bool,
-    ) -> Result<String, u32> {
+    ) -> Result<String, Error> {
         unsafe extern "C" fn write_callback_c(
             buffer: *mut c_char,
             _size: usize,
@@ -191,8 +189,11 @@ impl Curl {
             nitems
         }
 
-        let null_ending_payload_ptr = format!("{}\0", post_data);
-        unsafe {
+        let null_ending_ca_file = format!("{}\0", get_absolute_path_in_image_package(ca_file)?);
+
+        // curl doesn't copy the post data, it has to outlive the transfer
+        let null_ending_payload = format!("{}\0", post_data);
+        let headers = unsafe {
             let headers = curl_slist_append(
                 core::ptr::null_mut(),
                 format!("Accept: {}\0", content_type).as_mut_ptr() as *mut _,
@@ -201,8 +202,7 @@ impl Curl {
                 headers,
                 format!("Content-Type: {}\0", content_type).as_mut_ptr() as *mut _,
             );
-            let headers =
-                curl_slist_append(headers, format!("Charset: utf-8\0").as_mut_ptr() as *mut _);
+            let headers = curl_slist_append(headers, "Charset: utf-8\0".as_ptr() as *const _);
 
             curl_easy_setopt(
                 self.handle,
@@ -215,17 +215,17 @@ impl Curl {
             curl_easy_setopt(
                 self.handle,
                 CURLoption_CURLOPT_POSTFIELDS,
-                null_ending_payload_ptr,
+                null_ending_payload.as_ptr(),
             );
-        };
 
-        let null_ending_ca_file =
-            format!("{}\0", get_absolute_path_in_image_package(ca_file).unwrap());
+            headers
+        };
 
-        let mut response = String::new();
+        // a chunk may end within a multi-byte character, decoded at the end
+        let mut response = Vec::new();
 
         let write_callback = |data: &[u8], _done: bool| {
-            response.push_str(core::str::from_utf8(data).unwrap());
+            response.extend_from_slice(data);
         };
 
         unsafe {
@@ -249,7 +249,7 @@ impl Curl {
         ) -> u32 {
             DeviceAuth_SslCtxFunc(sslctx);
             sphere_sys::CURLcode_CURLE_OK
-        };
+        }
 
         if authenticated {
             unsafe {
@@ -265,7 +265,6 @@ impl Curl {
         }
 
         let ctx = Context {
-            curl: self,
             write_callback: Some(Box::new(write_callback)),
         };
         let ctx = Box::new(ctx);
@@ -276,39 +275,31 @@ impl Curl {
         }
 
         let null_ending = format!("{}\0", url);
-        let curl_result: u32 = 0;
+        let curl_result;
         unsafe {
             curl_easy_setopt(self.handle, CURLoption_CURLOPT_URL, null_ending.as_ptr());
-            let _curl_result = curl_easy_perform(self.handle);
+            curl_result = curl_easy_perform(self.handle);
             curl_easy_setopt(self.handle, CURLoption_CURLOPT_WRITEDATA, null::<c_void>());
-        }
+            drop(Box::from_raw(ctx));
 
-        unsafe {
-            curl_easy_setopt(self.handle, CURLoption_CURLOPT_WRITEDATA, null::<c_void>());
+            // the handle is reused, don't leave it pointing at freed memory
+            curl_easy_setopt(self.handle, CURLoption_CURLOPT_HTTPHEADER, null::<c_void>());
+            curl_easy_setopt(self.handle, CURLoption_CURLOPT_POSTFIELDS, null::<c_void>());
+            curl_easy_setopt(
+                self.handle,
+                CURLoption_CURLOPT_CUSTOMREQUEST,
+                null::<c_void>(),
+            );
+            curl_slist_free_all(headers);
         }
 
-        if curl_result == CURLcode_CURLE_OK {
-            let mut response_code: u32 = 0;
-            unsafe {
-                curl_easy_getinfo(self.handle, CURLINFO_CURLINFO_RESPONSE_CODE, &response_code);
-            }
+        check(curl_result)?;
+        self.check_response_code()?;
 
-            if response_code >= 200 && response_code <= 299 {
-                Ok(response)
-            } else {
-                Err(response_code)
-            }
-        } else {
-            Err(9999)
-        }
+        String::from_utf8(response).map_err(|error| Error::Utf8(error.utf8_error()))
     }
 
-    pub fn download<'a, F>(
-        &self,
-        url: &str,
-        ca_file: &str,
-        write_callback: F,
-    ) -> Result<&'static str, &'static str>
+    pub fn download<'a, F>(&self, url: &str, ca_file: &str, write_callback: F) -> Result<(), Error>
     where
         F: CurlWriteCallback<'a>,
         F: 'a,
@@ -331,8 +322,7 @@ impl Curl {
             nitems
         }
 
-        let null_ending_ca_file =
-            format!("{}\0", get_absolute_path_in_image_package(ca_file).unwrap());
+        let null_ending_ca_file = format!("{}\0", get_absolute_path_in_image_package(ca_file)?);
 
         unsafe {
             curl_easy_setopt(
@@ -349,7 +339,6 @@ impl Curl {
         }
 
         let ctx = Context {
-            curl: self,
             write_callback: Some(Box::new(write_callback)),
         };
         let ctx = Box::new(ctx);
@@ -360,13 +349,10 @@ impl Curl {
         }
 
         let null_ending = format!("{}\0", url);
+        let curl_result;
         unsafe {
             curl_easy_setopt(self.handle, CURLoption_CURLOPT_URL, null_ending.as_ptr());
-            curl_easy_perform(self.handle);
-            curl_easy_setopt(self.handle, CURLoption_CURLOPT_WRITEDATA, null::<c_void>());
-        }
-
-        unsafe {
+            curl_result = curl_easy_perform(self.handle);
             curl_easy_setopt(self.handle, CURLoption_CURLOPT_WRITEDATA, null::<c_void>());
         }
 
@@ -377,17 +363,37 @@ impl Curl {
             if let Some(cb) = callback {
                 (cb)(&[0u8; 0], true);
             }
+            drop(Box::from_raw(ctx));
         }
 
-        Ok("Ok")
+        check(curl_result)?;
+        self.check_response_code()
+    }
+
+    // Any HTTP status outside of 2xx fails the request
+    fn check_response_code(&self) -> Result<(), Error> {
+        let mut response_code: c_long = 0;
+        check(unsafe {
+            curl_easy_getinfo(
+                self.handle,
+                CURLINFO_CURLINFO_RESPONSE_CODE,
+                &mut response_code,
+            )
+        })?;
+
+        if (200..300).contains(&response_code) {
+            Ok(())
+        } else {
+            Err(Error::Http(response_code as i32))
+        }
     }
 
-    pub fn new() -> Result<Curl, &'static str> {
+    pub fn new() -> Result<Curl, Error> {
         unsafe {
             let curl = curl_easy_init();
 
             if curl.is_null() {
-                Err("Unable to initialize easy curl")
+                Err(Error::Curl(CURLcode_CURLE_FAILED_INIT))
             } else {
                 curl_easy_setopt(curl, CURLoption_CURLOPT_VERBOSE, 1u64);
 
@@ -398,3 +404,14 @@ impl Curl {
         }
     }
 }
+
+impl Drop for Curl {
+    fn drop(&mut self) {
+        unsafe {
+            curl_easy_cleanup(self.handle);
+        }
+    }
+}
+
+#[cfg(all(test, feature = "mock"))]
+mod tests;
@@ -0,0 +1,128 @@
+use alloc::string::String;
+use alloc::vec::Vec;
+use core::cell::RefCell;
+
+use sphere_sys::mock::{self, CurlRequest, CurlResponse};
+use sphere_sys::{CURLcode_CURLE_COULDNT_CONNECT, CURLcode_CURLE_OK};
+
+use super::Curl;
+use crate::Error;
+
+const URL: &str = "https://example.com/api";
+const CA_FILE: &str = "certs/ca.pem";
+
+fn respond(status: i64, body: &[u8]) {
+    mock::script_curl_response(CurlResponse {
+        result: CURLcode_CURLE_OK,
+        status: status as _,
+        body: body.to_vec(),
+    });
+}
+
+fn ca_info() -> Option<String> {
+    Some(String::from("/mnt/apps/approot/certs/ca.pem"))
+}
+
+#[test]
+fn gets_a_string() {
+    let curl = Curl::new().unwrap();
+    respond(200, b"{\"ok\":true}");
+
+    let body = curl.get_request_as_string(URL, CA_FILE, false).unwrap();
+    assert_eq!(body, "{\"ok\":true}");
+    assert_eq!(
+        mock::curl_requests(),
+        [CurlRequest {
+            url: String::from(URL),
+            ca_info: ca_info(),
+            ..CurlRequest::default()
+        }]
+    );
+}
+
+#[test]
+fn posts_with_device_auth() {
+    let curl = Curl::new().unwrap();
+    respond(201, b"created");
+
+    let body = curl
+        .post_request_as_string(URL, "{\"id\":1}", "application/json", CA_FILE, true)
+        .unwrap();
+    assert_eq!(body, "created");
+
+    let request = mock::curl_requests().remove(0);
+    assert_eq!(request.method.as_deref(), Some("POST"));
+    assert_eq!(request.post_fields.as_deref(), Some("{\"id\":1}"));
+    assert_eq!(
+        request.headers,
+        [
+            "Accept: application/json",
+            "Content-Type: application/json",
+            "Charset: utf-8"
+        ]
+    );
+    assert!(request.device_auth);
+    assert_eq!(request.verify_host, Some(0));
+}
+
+#[test]
+fn resets_the_post_options_for_the_next_request() {
+    let curl = Curl::new().unwrap();
+    respond(200, b"");
+    respond(200, b"");
+
+    curl.post_request_as_string(URL, "{}", "application/json", CA_FILE, false)
+        .unwrap();
+    curl.get_request_as_string(URL, CA_FILE, false).unwrap();
+
+    let request = mock::curl_requests().remove(1);
+    assert_eq!(request.method, None);
+    assert_eq!(request.post_fields, None);
+    assert!(request.headers.is_empty());
+}
+
+#[test]
+fn downloads_in_chunks() {
+    let curl = Curl::new().unwrap();
+    respond(200, b"firmware");
+    let chunks = RefCell::new(Vec::new());
+
+    curl.download(URL, CA_FILE, |data: &[u8], done| {
+        chunks.borrow_mut().push((data.to_vec(), done))
+    })
+    .unwrap();
+    assert_eq!(
+        chunks.into_inner(),
+        [(b"firmware".to_vec(), false), (Vec::new(), true)]
+    );
+}
+
+#[test]
+fn fails_on_http_errors() {
+    let curl = Curl::new().unwrap();
+    respond(404, b"not found");
+    respond(302, b"");
+
+    let result = curl.get_request_as_string(URL, CA_FILE, false);
+    assert_eq!(result, Err(Error::Http(404)));
+    let result = curl.download(URL, CA_FILE, |_: &[u8], _| {});
+    assert_eq!(result, Err(Error::Http(302)));
+}
+
+#[test]
+fn fails_on_transfer_errors() {
+    let curl = Curl::new().unwrap();
+
+    // nothing scripted, nothing answers
+    let result = curl.get_request_as_string(URL, CA_FILE, false);
+    assert_eq!(result, Err(Error::Curl(CURLcode_CURLE_COULDNT_CONNECT)));
+}
+
+#[test]
+fn rejects_bodies_that_arent_utf8() {
+    let curl = Curl::new().unwrap();
+    respond(200, &[0x68, 0xff]);
+
+    let result = curl.get_request_as_string(URL, CA_FILE, false);
+    assert!(matches!(result, Err(Error::Utf8(_))));
+}
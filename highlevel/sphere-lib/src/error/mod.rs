@@ -0,0 +1,90 @@
+use core::fmt;
+use core::str::Utf8Error;
+
+use crate::util::errno;
+
+/// The error type of all sphere-lib APIs.
+///
+/// Each variant keeps the raw code of the layer that failed, so it can be
+/// looked up in the Azure Sphere, curl or Azure IoT SDK documentation.
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub enum Error {
+    /// A POSIX or Applibs call failed with this errno.
+    Os(i32),
+    /// A curl call failed with this `CURLcode`.
+    Curl(u32),
+    /// The server answered with an HTTP status outside of 2xx.
+    Http(i32),
+    /// An IoT Hub client call failed with this `IOTHUB_CLIENT_RESULT`.
+    IotHub(u32),
+    /// Device provisioning failed, see `AZURE_SPHERE_PROV_RETURN_VALUE`.
+    Provisioning {
+        result: u32,
+        prov_device_error: u32,
+        iothub_client_error: u32,
+    },
+    /// A transfer moved only this many bytes.
+    Incomplete(usize),
+    /// Data read as text isn't valid UTF-8.
+    Utf8(Utf8Error),
+}
+
+impl Error {
+    /// Wraps errno as left by the last failing call.
+    pub fn last_os_error() -> Error {
+        Error::Os(errno())
+    }
+}
+
+impl fmt::Display for Error {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Error::Os(errno) => write!(f, "OS error, errno {}", errno),
+            Error::Curl(code) => write!(f, "curl error {}", code),
+            Error::Http(status) => write!(f, "HTTP status {}", status),
+            Error::IotHub(result) => write!(f, "IoT Hub client error {}", result),
+            Error::Provisioning {
+                result,
+                prov_device_error,
+                iothub_client_error,
+            } => write!(
+                f,
+                "provisioning failed with {} (device {}, IoT Hub client {})",
+                result, prov_device_error, iothub_client_error
+            ),
+            Error::Incomplete(transferred) => {
+                write!(f, "incomplete transfer, {} bytes", transferred)
+            }
+            Error::Utf8(error) => write!(f, "{}", error),
+        }
+    }
+}
+
+/// For Applibs calls that return -1 and set errno on failure.
+pub(crate) fn check(result: i32) -> Result<i32, Error> {
+    if result < 0 {
+        Err(Error::last_os_error())
+    } else {
+        Ok(result)
+    }
+}
+
+/// Like `check`, for calls returning a byte count.
+#[allow(dead_code)] // unused if only some modules are enabled
+pub(crate) fn check_size(result: isize) -> Result<usize, Error> {
+    if result < 0 {
+        Err(Error::last_os_error())
+    } else {
+        Ok(result as usize)
+    }
+}
+
+/// Fails with `Error::Incomplete` unless all `expected` bytes were transferred.
+#[allow(dead_code)]
+pub(crate) fn check_complete(transferred: usize, expected: usize) -> Result<(), Error> {
+    if transferred == expected {
+        Ok(())
+    } else {
+        Err(Error::Incomplete(transferred))
+    }
+}
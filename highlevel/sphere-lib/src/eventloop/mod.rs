@@ -0,0 +1,253 @@
+#![allow(non_camel_case_types)]
+
+use alloc::collections::BTreeMap;
+use alloc::rc::Rc;
+use core::cell::{Cell, RefCell};
+use core::mem::MaybeUninit;
+use core::ops::BitOr;
+use core::time::Duration;
+
+extern crate sphere_sys;
+
+use sphere_sys::close;
+use sphere_sys::epoll_create1;
+use sphere_sys::epoll_ctl;
+use sphere_sys::epoll_data;
+use sphere_sys::epoll_event;
+use sphere_sys::epoll_wait;
+use sphere_sys::EINTR;
+use sphere_sys::EPOLLERR;
+use sphere_sys::EPOLLHUP;
+use sphere_sys::EPOLLIN;
+use sphere_sys::EPOLLOUT;
+use sphere_sys::EPOLL_CLOEXEC;
+use sphere_sys::EPOLL_CTL_ADD;
+use sphere_sys::EPOLL_CTL_DEL;
+use sphere_sys::EPOLL_CTL_MOD;
+
+use crate::error::check;
+use crate::fd::{AsRawFd, RawFd};
+use crate::signal::Signal;
+use crate::time::Timer;
+use crate::Error;
+
+/// Readiness of a file descriptor, as passed to and reported by epoll.
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub struct IoEvents(u32);
+
+impl IoEvents {
+    pub const INPUT: IoEvents = IoEvents(EPOLLIN);
+    pub const OUTPUT: IoEvents = IoEvents(EPOLLOUT);
+    /// Always reported, no need to register for it.
+    pub const ERROR: IoEvents = IoEvents(EPOLLERR);
+    /// Always reported, no need to register for it.
+    pub const HANGUP: IoEvents = IoEvents(EPOLLHUP);
+
+    pub fn contains(self, other: IoEvents) -> bool {
+        self.0 & other.0 == other.0
+    }
+}
+
+impl BitOr for IoEvents {
+    type Output = IoEvents;
+
+    fn bitor(self, other: IoEvents) -> IoEvents {
+        IoEvents(self.0 | other.0)
+    }
+}
+
+pub trait IoCallback<'a> = FnMut(&EventLoop<'a>, IoEvents) + 'a;
+
+pub trait TimerCallback<'a> = FnMut(&EventLoop<'a>) + 'a;
+
+pub trait SignalCallback<'a> = FnMut(&EventLoop<'a>) + 'a;
+
+type Registration<'a> = Rc<RefCell<dyn IoCallback<'a> + 'a>>;
+
+/// Runs callbacks when registered file descriptors become ready, so a single
+/// thread can serve all peripherals without polling.
+///
+/// Callbacks get the loop passed in and may register, unregister or stop.
+/// Note that GPIO fds never become ready on Azure Sphere, sample inputs from
+/// a timer instead.
+pub struct EventLoop<'a> {
+    epoll_fd: RawFd,
+    registrations: RefCell<BTreeMap<RawFd, Registration<'a>>>,
+    stopped: Cell<bool>,
+}
+
+const MAX_EVENTS: usize = 16;
+
+impl<'a> EventLoop<'a> {
+    pub fn new() -> Result<EventLoop<'a>, Error> {
+        let epoll_fd = unsafe { epoll_create1(EPOLL_CLOEXEC as i32) };
+
+        Ok(EventLoop {
+            epoll_fd: check(epoll_fd)?,
+            registrations: RefCell::new(BTreeMap::new()),
+            stopped: Cell::new(false),
+        })
+    }
+
+    /// Calls `callback` whenever `source` is ready for any of `events`. The
+    /// source must stay open until it is unregistered.
+    pub fn register_io<F>(
+        &self,
+        source: &dyn AsRawFd,
+        events: IoEvents,
+        callback: F,
+    ) -> Result<(), Error>
+    where
+        F: IoCallback<'a>,
+        F: 'a,
+    {
+        let fd = source.as_raw_fd();
+        self.register(fd, events, Rc::new(RefCell::new(callback)))
+    }
+
+    /// Changes the events `source` was registered for.
+    pub fn modify_io(&self, source: &dyn AsRawFd, events: IoEvents) -> Result<(), Error> {
+        let fd = source.as_raw_fd();
+        let mut event = to_epoll_event(fd, events);
+        check(unsafe { epoll_ctl(self.epoll_fd, EPOLL_CTL_MOD as i32, fd, &mut event) }).map(|_| ())
+    }
+
+    /// Calls `callback` whenever `timer` expires. The loop takes ownership of
+    /// the timer, it is closed when unregistered with the returned fd.
+    pub fn register_timer<F>(&self, timer: Timer, mut callback: F) -> Result<RawFd, Error>
+    where
+        F: TimerCallback<'a>,
+        F: 'a,
+    {
+        let fd = timer.as_raw_fd();
+        // owns the timer, so it is closed along with the registration
+        let on_expiry = move |event_loop: &EventLoop<'a>, _events: IoEvents| {
+            if let Ok(expirations) = timer.expirations() {
+                if expirations > 0 {
+                    callback(event_loop);
+                }
+            }
+        };
+
+        self.register(fd, IoEvents::INPUT, Rc::new(RefCell::new(on_expiry)))?;
+        Ok(fd)
+    }
+
+    /// Calls `callback` after `signal` arrived, outside of the signal handler.
+    /// The loop takes ownership of the signal, the default action is restored
+    /// when unregistered with the returned fd.
+    pub fn register_signal<F>(&self, signal: Signal, mut callback: F) -> Result<RawFd, Error>
+    where
+        F: SignalCallback<'a>,
+        F: 'a,
+    {
+        let fd = signal.as_raw_fd();
+        let on_signal = move |event_loop: &EventLoop<'a>, _events: IoEvents| {
+            if let Ok(count) = signal.take() {
+                if count > 0 {
+                    callback(event_loop);
+                }
+            }
+        };
+
+        self.register(fd, IoEvents::INPUT, Rc::new(RefCell::new(on_signal)))?;
+        Ok(fd)
+    }
+
+    /// Removes the registration of `fd`, closing it if it is a timer or
+    /// signal owned by the loop.
+    pub fn unregister(&self, fd: RawFd) -> Result<(), Error> {
+        check(unsafe {
+            epoll_ctl(
+                self.epoll_fd,
+                EPOLL_CTL_DEL as i32,
+                fd,
+                core::ptr::null_mut(),
+            )
+        })?;
+        // dropped after the borrow ends, the callback may own anything
+        let registration = self.registrations.borrow_mut().remove(&fd);
+        drop(registration);
+        Ok(())
+    }
+
+    /// Waits up to `timeout` (forever if `None`) for registered fds to become
+    /// ready and runs their callbacks. Returns the number of callbacks run.
+    pub fn run_once(&self, timeout: Option<Duration>) -> Result<usize, Error> {
+        let timeout = timeout.map_or(-1, |timeout| {
+            timeout.as_millis().min(i32::MAX as u128) as i32
+        });
+        let mut events: [epoll_event; MAX_EVENTS] = unsafe { MaybeUninit::zeroed().assume_init() };
+
+        let count = unsafe {
+            epoll_wait(
+                self.epoll_fd,
+                events.as_mut_ptr(),
+                MAX_EVENTS as i32,
+                timeout,
+            )
+        };
+        let count = match check(count) {
+            Ok(count) => count as usize,
+            // a signal arrived, let the caller look at it
+            Err(Error::Os(errno)) if errno == EINTR as i32 => return Ok(0),
+            Err(error) => return Err(error),
+        };
+        let mut dispatched = 0;
+        for event in &events[..count] {
+            let fd = unsafe { event.data.fd };
+            // an earlier callback may have unregistered it
+            let callback = match self.registrations.borrow().get(&fd) {
+                Some(callback) => callback.clone(),
+                None => continue,
+            };
+            (*callback.borrow_mut())(self, IoEvents(event.events));
+            dispatched += 1;
+        }
+
+        Ok(dispatched)
+    }
+
+    /// Runs callbacks until `stop` is called.
+    pub fn run(&self) -> Result<(), Error> {
+        self.stopped.set(false);
+        while !self.stopped.get() {
+            self.run_once(None)?;
+        }
+        Ok(())
+    }
+
+    /// Makes `run` return after the current callback.
+    pub fn stop(&self) {
+        self.stopped.set(true);
+    }
+
+    fn register(
+        &self,
+        fd: RawFd,
+        events: IoEvents,
+        callback: Registration<'a>,
+    ) -> Result<(), Error> {
+        let mut event = to_epoll_event(fd, events);
+        check(unsafe { epoll_ctl(self.epoll_fd, EPOLL_CTL_ADD as i32, fd, &mut event) })?;
+
+        self.registrations.borrow_mut().insert(fd, callback);
+        Ok(())
+    }
+}
+
+impl<'a> Drop for EventLoop<'a> {
+    fn drop(&mut self) {
+        unsafe { close(self.epoll_fd) };
+    }
+}
+
+fn to_epoll_event(fd: RawFd, events: IoEvents) -> epoll_event {
+    epoll_event {
+        events: events.0,
+        data: epoll_data { u64: fd as u64 },
+    }
+}
+
+#[cfg(all(test, feature = "mock"))]
+mod tests;
@@ -0,0 +1,92 @@
+use alloc::rc::Rc;
+use core::cell::Cell;
+use core::time::Duration;
+
+use sphere_sys::mock;
+use sphere_sys::mock::errno::ENOSPC;
+
+use super::{EventLoop, IoEvents};
+use crate::signal::{Signal, SIGTERM};
+use crate::time::Timer;
+use crate::Error;
+#[cfg(feature = "uart")]
+use crate::{isu::Isu, uart::Uart, uart::UartConfig};
+
+#[test]
+fn runs_timer_callbacks_in_simulated_time() {
+    let event_loop = EventLoop::new().unwrap();
+    let ticks = Rc::new(Cell::new(0));
+
+    let timer = Timer::periodic(Duration::from_millis(100)).unwrap();
+    let counter = ticks.clone();
+    let fd = event_loop
+        .register_timer(timer, move |event_loop| {
+            counter.set(counter.get() + 1);
+            if counter.get() == 3 {
+                event_loop.stop();
+            }
+        })
+        .unwrap();
+
+    event_loop.run().unwrap();
+    assert_eq!(ticks.get(), 3);
+    assert_eq!(mock::monotonic_ns(), 300_000_000);
+
+    event_loop.unregister(fd).unwrap();
+    assert_eq!(event_loop.run_once(Some(Duration::from_secs(1))), Ok(0));
+    assert_eq!(ticks.get(), 3);
+}
+
+#[cfg(feature = "uart")]
+#[test]
+fn calls_back_once_input_arrives() {
+    let event_loop = EventLoop::new().unwrap();
+    let uart = Rc::new(Uart::open(Isu::Isu0, UartConfig::default()).unwrap());
+    let received = Rc::new(Cell::new(0));
+
+    let reader = uart.clone();
+    let counter = received.clone();
+    event_loop
+        .register_io(&*uart, IoEvents::INPUT, move |_, events| {
+            assert!(events.contains(IoEvents::INPUT));
+            let mut buffer = [0; 16];
+            counter.set(counter.get() + reader.read(&mut buffer).unwrap());
+        })
+        .unwrap();
+
+    assert_eq!(event_loop.run_once(Some(Duration::from_millis(10))), Ok(0));
+    // ISU0 is UART 4
+    mock::uart_inject(4, b"ping");
+    assert_eq!(event_loop.run_once(Some(Duration::from_millis(10))), Ok(1));
+    assert_eq!(received.get(), 4);
+}
+
+#[test]
+fn stops_on_a_signal() {
+    let event_loop = EventLoop::new().unwrap();
+    let signal = Signal::catch(SIGTERM).unwrap();
+    event_loop
+        .register_signal(signal, |event_loop| event_loop.stop())
+        .unwrap();
+
+    assert!(mock::raise_signal(SIGTERM));
+    event_loop.run().unwrap();
+}
+
+#[test]
+fn returns_nothing_when_interrupted() {
+    let event_loop = EventLoop::new().unwrap();
+    mock::fail_next("epoll_wait", sphere_sys::EINTR as i32);
+    assert_eq!(event_loop.run_once(None), Ok(0));
+}
+
+#[test]
+fn reports_failing_registrations() {
+    let event_loop = EventLoop::new().unwrap();
+    mock::fail_next("epoll_ctl", ENOSPC);
+    let timer = Timer::periodic(Duration::from_millis(100)).unwrap();
+
+    let result = event_loop.register_timer(timer, |_| panic!("not registered"));
+    assert_eq!(result, Err(Error::Os(ENOSPC)));
+    assert_eq!(event_loop.run_once(Some(Duration::from_secs(1))), Ok(0));
+}
@@ -0,0 +1,17 @@
+extern crate sphere_sys;
+
+use sphere_sys::std::os::raw::c_int;
+
+pub type RawFd = c_int;
+
+/// A handle backed by a file descriptor, e.g. to register it with an
+/// `EventLoop`. The fd stays owned by the handle.
+pub trait AsRawFd {
+    fn as_raw_fd(&self) -> RawFd;
+}
+
+impl AsRawFd for RawFd {
+    fn as_raw_fd(&self) -> RawFd {
+        *self
+    }
+}
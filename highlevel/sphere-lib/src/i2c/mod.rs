@@ -0,0 +1,150 @@
+use embedded_hal::blocking::i2c;
+
+extern crate sphere_sys;
+
+use sphere_sys::close;
+use sphere_sys::I2CMaster_Open;
+use sphere_sys::I2CMaster_Read;
+use sphere_sys::I2CMaster_SetBusSpeed;
+use sphere_sys::I2CMaster_SetDefaultTargetAddress;
+use sphere_sys::I2CMaster_SetTimeout;
+use sphere_sys::I2CMaster_Write;
+use sphere_sys::I2CMaster_WriteThenRead;
+use sphere_sys::I2C_BUS_SPEED_FAST;
+use sphere_sys::I2C_BUS_SPEED_FAST_PLUS;
+use sphere_sys::I2C_BUS_SPEED_STANDARD;
+
+use crate::error::{check, check_complete, check_size};
+use crate::fd::{AsRawFd, RawFd};
+pub use crate::isu::Isu;
+use crate::Error;
+
+pub enum BusSpeed {
+    /// 100 kHz
+    Standard,
+    /// 400 kHz
+    Fast,
+    /// 1 MHz
+    FastPlus,
+}
+
+/// An ISU opened as I2C master. The ISU has to be listed under `I2cMaster`
+/// in the app manifest.
+///
+/// Addresses are 7 bit, the MT3620 doesn't support 10 bit addressing.
+pub struct I2cMaster {
+    fd: sphere_sys::std::os::raw::c_int,
+}
+
+impl I2cMaster {
+    pub fn open(isu: Isu) -> Result<I2cMaster, Error> {
+        let fd = unsafe { I2CMaster_Open(isu.id()) };
+
+        Ok(I2cMaster { fd: check(fd)? })
+    }
+
+    pub fn set_bus_speed(&self, speed: BusSpeed) -> Result<(), Error> {
+        let speed = match speed {
+            BusSpeed::Standard => I2C_BUS_SPEED_STANDARD,
+            BusSpeed::Fast => I2C_BUS_SPEED_FAST,
+            BusSpeed::FastPlus => I2C_BUS_SPEED_FAST_PLUS,
+        };
+
+        check(unsafe { I2CMaster_SetBusSpeed(self.fd, speed) }).map(|_| ())
+    }
+
+    /// Timeout for each transfer, in milliseconds.
+    pub fn set_timeout(&self, timeout_ms: u32) -> Result<(), Error> {
+        check(unsafe { I2CMaster_SetTimeout(self.fd, timeout_ms) }).map(|_| ())
+    }
+
+    /// Target of plain `read`/`write` calls on the file descriptor.
+    pub fn set_default_target_address(&self, address: u8) -> Result<(), Error> {
+        check(unsafe { I2CMaster_SetDefaultTargetAddress(self.fd, address as u32) }).map(|_| ())
+    }
+
+    /// Returns the number of bytes written.
+    pub fn write(&self, address: u8, data: &[u8]) -> Result<usize, Error> {
+        let written =
+            unsafe { I2CMaster_Write(self.fd, address as u32, data.as_ptr(), data.len()) };
+
+        check_size(written)
+    }
+
+    /// Returns the number of bytes read.
+    pub fn read(&self, address: u8, buffer: &mut [u8]) -> Result<usize, Error> {
+        let read =
+            unsafe { I2CMaster_Read(self.fd, address as u32, buffer.as_mut_ptr(), buffer.len()) };
+
+        check_size(read)
+    }
+
+    /// Writes and reads in one transaction, with a repeated start in between.
+    /// Returns the total number of bytes written and read.
+    pub fn write_then_read(
+        &self,
+        address: u8,
+        data: &[u8],
+        buffer: &mut [u8],
+    ) -> Result<usize, Error> {
+        let transferred = unsafe {
+            I2CMaster_WriteThenRead(
+                self.fd,
+                address as u32,
+                data.as_ptr(),
+                data.len(),
+                buffer.as_mut_ptr(),
+                buffer.len(),
+            )
+        };
+
+        check_size(transferred)
+    }
+}
+
+impl AsRawFd for I2cMaster {
+    fn as_raw_fd(&self) -> RawFd {
+        self.fd
+    }
+}
+
+impl Drop for I2cMaster {
+    fn drop(&mut self) {
+        unsafe {
+            close(self.fd);
+        }
+    }
+}
+
+impl i2c::Write for I2cMaster {
+    type Error = Error;
+
+    fn write(&mut self, address: u8, bytes: &[u8]) -> Result<(), Self::Error> {
+        check_complete(I2cMaster::write(self, address, bytes)?, bytes.len())
+    }
+}
+
+impl i2c::Read for I2cMaster {
+    type Error = Error;
+
+    fn read(&mut self, address: u8, buffer: &mut [u8]) -> Result<(), Self::Error> {
+        check_complete(I2cMaster::read(self, address, buffer)?, buffer.len())
+    }
+}
+
+impl i2c::WriteRead for I2cMaster {
+    type Error = Error;
+
+    fn write_read(
+        &mut self,
+        address: u8,
+        bytes: &[u8],
+        buffer: &mut [u8],
+    ) -> Result<(), Self::Error> {
+        let transferred = I2cMaster::write_then_read(self, address, bytes, buffer)?;
+        check_complete(transferred, bytes.len() + buffer.len())
+    }
+}
+
+#[cfg(all(test, feature = "mock"))]
+mod tests;
@@ -0,0 +1,95 @@
+use embedded_hal::blocking::i2c::{Read, Write, WriteRead};
+use sphere_sys::mock;
+use sphere_sys::mock::errno::{EBUSY, EIO, ENXIO};
+
+use super::{BusSpeed, I2cMaster};
+use crate::isu::Isu;
+use crate::Error;
+
+// the I2C interface id of ISU2
+const ISU2: i32 = 6;
+const SENSOR: u8 = 0x48;
+
+fn open() -> I2cMaster {
+    mock::i2c_attach(ISU2, SENSOR as u32);
+    I2cMaster::open(Isu::Isu2).unwrap()
+}
+
+#[test]
+fn configures_the_bus() {
+    let i2c = open();
+    i2c.set_bus_speed(BusSpeed::FastPlus).unwrap();
+    i2c.set_timeout(100).unwrap();
+    i2c.set_default_target_address(SENSOR).unwrap();
+
+    let bus = mock::i2c_bus(ISU2).unwrap();
+    assert_eq!(bus.speed, sphere_sys::I2C_BUS_SPEED_FAST_PLUS);
+    assert_eq!(bus.timeout_ms, 100);
+    assert_eq!(bus.default_address, Some(SENSOR as u32));
+}
+
+#[test]
+fn writes_to_the_target() {
+    let mut i2c = open();
+
+    assert_eq!(i2c.write(SENSOR, &[0x01, 0x60]), Ok(2));
+    Write::write(&mut i2c, SENSOR, &[0x02]).unwrap();
+    assert_eq!(
+        mock::i2c_take_written(ISU2, SENSOR as u32),
+        [0x01, 0x60, 0x02]
+    );
+}
+
+#[test]
+fn reads_from_the_target() {
+    let mut i2c = open();
+    mock::i2c_inject(ISU2, SENSOR as u32, &[0x19, 0x80, 0x7f]);
+
+    let mut buffer = [0; 2];
+    assert_eq!(i2c.read(SENSOR, &mut buffer), Ok(2));
+    assert_eq!(buffer, [0x19, 0x80]);
+
+    // the target returns 0xff once it has nothing queued
+    Read::read(&mut i2c, SENSOR, &mut buffer).unwrap();
+    assert_eq!(buffer, [0x7f, 0xff]);
+}
+
+#[test]
+fn writes_then_reads() {
+    let mut i2c = open();
+    mock::i2c_inject(ISU2, SENSOR as u32, &[0x1a, 0x40, 0x21]);
+
+    let mut buffer = [0; 2];
+    assert_eq!(i2c.write_then_read(SENSOR, &[0x00], &mut buffer), Ok(3));
+    assert_eq!(buffer, [0x1a, 0x40]);
+
+    let mut buffer = [0; 1];
+    i2c.write_read(SENSOR, &[0x03], &mut buffer).unwrap();
+    assert_eq!(buffer, [0x21]);
+    assert_eq!(mock::i2c_take_written(ISU2, SENSOR as u32), [0x00, 0x03]);
+}
+
+#[test]
+fn reports_errors() {
+    let mut i2c = open();
+    assert_eq!(I2cMaster::open(Isu::Isu2).err(), Some(Error::Os(EBUSY)));
+
+    // nothing acknowledges at other addresses
+    assert_eq!(i2c.write(0x10, &[0x01]), Err(Error::Os(ENXIO)));
+    assert_eq!(i2c.read(0x10, &mut [0; 1]), Err(Error::Os(ENXIO)));
+
+    mock::fail_next("I2CMaster_Write", EIO);
+    assert_eq!(Write::write(&mut i2c, SENSOR, &[0x01]), Err(Error::Os(EIO)));
+    mock::fail_next("I2CMaster_Read", EIO);
+    assert_eq!(
+        Read::read(&mut i2c, SENSOR, &mut [0; 1]),
+        Err(Error::Os(EIO))
+    );
+    mock::fail_next("I2CMaster_WriteThenRead", EIO);
+    let result = i2c.write_read(SENSOR, &[0x00], &mut [0; 1]);
+    assert_eq!(result, Err(Error::Os(EIO)));
+    mock::fail_next("I2CMaster_SetBusSpeed", EIO);
+    assert_eq!(i2c.set_bus_speed(BusSpeed::Fast), Err(Error::Os(EIO)));
+
+    assert!(mock::i2c_take_written(ISU2, SENSOR as u32).is_empty());
+}
@@ -0,0 +1,23 @@
+/// The serial interface blocks of the MT3620, each usable as UART, I2C master
+/// or SPI master.
+pub enum Isu {
+    Isu0,
+    Isu1,
+    Isu2,
+    Isu3,
+    Isu4,
+}
+
+impl Isu {
+    // for MT3620 only
+    #[allow(dead_code)] // unused without the uart, i2c and spi modules
+    pub(crate) fn id(&self) -> i32 {
+        match self {
+            Isu::Isu0 => 4,
+            Isu::Isu1 => 5,
+            Isu::Isu2 => 6,
+            Isu::Isu3 => 7,
+            Isu::Isu4 => 8,
+        }
+    }
+}
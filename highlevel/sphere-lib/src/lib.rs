@@ -6,13 +6,34 @@ extern crate alloc;
 
 extern crate sphere_sys;
 
+#[cfg(feature = "application")]
 pub mod application;
+#[cfg(feature = "iot")]
 pub mod azureiot;
+#[cfg(feature = "curl")]
 pub mod curl;
+mod error;
+pub mod eventloop;
+pub mod fd;
+#[cfg(feature = "i2c")]
+pub mod i2c;
+pub mod isu;
 pub mod logging;
+#[cfg(feature = "gpio")]
 pub mod mt3620_gpio;
+#[cfg(feature = "networking")]
 pub mod networking;
+pub mod settings;
+pub mod shutdown;
+pub mod signal;
+#[cfg(feature = "spi")]
+pub mod spi;
+#[cfg(feature = "storage")]
 pub mod storage;
+pub mod time;
+#[cfg(feature = "uart")]
 pub mod uart;
 pub mod util;
 pub mod watchdog;
+
+pub use error::Error;
@@ -1,14 +1,456 @@
+//! Backend of the `log` crate facade.
+//!
+//! `Builder::init` installs a logger that stamps each record with the uptime
+//! and hands it to the configured sinks, if its level passes the filter of
+//! the most specific module. Levels can be changed at runtime.
+//!
+//! Like the rest of sphere-lib this is meant for the main thread. Records
+//! logged from within a sink, or while another thread logs, are dropped and
+//! counted, see `dropped`.
+
 #![allow(non_camel_case_types)]
+use alloc::collections::VecDeque;
 use alloc::format;
+use alloc::string::String;
+use alloc::vec::Vec;
+use core::fmt::{self, Write};
+use core::sync::atomic::{AtomicUsize, Ordering};
+use core::time::Duration;
+
+use log::{Level, LevelFilter, Log, Metadata, Record, SetLoggerError};
 
 extern crate sphere_sys;
 use sphere_sys::Log_Debug;
 
+#[cfg(feature = "iot")]
+use crate::azureiot::AzureProvisioning;
+use crate::shutdown;
+use crate::time::Instant;
+#[cfg(feature = "uart")]
+use crate::uart::Uart;
+#[cfg(feature = "iot")]
+use crate::util::push_json_string;
+use crate::util::SpinLock;
+#[cfg(feature = "iot")]
+use crate::Error;
+
+/// Logs `message` as is, without going through the `log` facade.
 pub fn log(message: &str) {
-    let null_ending = format!("{}\n\0", message);
+    let mut writer = DebugWriter::new();
+    let _ = writer.write_str(message);
+    let _ = writer.write_str("\n");
+}
+
+const CHUNK_SIZE: usize = 128;
+
+/// Formats into a buffer on the stack and passes it to `Log_Debug` in chunks,
+/// whenever it's full and when dropped. Doesn't allocate, so it works while
+/// panicking or out of memory.
+///
+/// Long output may be split in the middle of a character.
+pub struct DebugWriter {
+    // one more for the terminating null
+    buf: [u8; CHUNK_SIZE + 1],
+    len: usize,
+}
+
+impl DebugWriter {
+    pub const fn new() -> DebugWriter {
+        DebugWriter {
+            buf: [0; CHUNK_SIZE + 1],
+            len: 0,
+        }
+    }
+
+    /// Passes what's buffered to `Log_Debug`.
+    pub fn flush(&mut self) {
+        if self.len == 0 {
+            return;
+        }
+
+        self.buf[self.len] = 0;
+        unsafe {
+            // not as the format string, the text may contain '%'
+            Log_Debug(b"%s\0".as_ptr() as *const i8, self.buf.as_ptr());
+        }
+        self.len = 0;
+    }
+}
+
+impl Default for DebugWriter {
+    fn default() -> DebugWriter {
+        DebugWriter::new()
+    }
+}
+
+impl fmt::Write for DebugWriter {
+    fn write_str(&mut self, s: &str) -> fmt::Result {
+        for &byte in s.as_bytes() {
+            if self.len == CHUNK_SIZE {
+                self.flush();
+            }
+            self.buf[self.len] = byte;
+            self.len += 1;
+        }
+        Ok(())
+    }
+}
+
+impl Drop for DebugWriter {
+    fn drop(&mut self) {
+        self.flush();
+    }
+}
+
+/// A record as handed to sinks. Displays as
+/// `    12.345 INFO  app::net: connected`.
+pub struct Entry<'a> {
+    /// Time since boot.
+    pub uptime: Duration,
+    pub level: Level,
+    /// The module path, unless set explicitly with `target:`.
+    pub target: &'a str,
+    pub args: fmt::Arguments<'a>,
+}
+
+impl fmt::Display for Entry<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(
+            f,
+            "{:>6}.{:03} {:<5} {}: {}",
+            self.uptime.as_secs(),
+            self.uptime.subsec_millis(),
+            self.level,
+            self.target,
+            self.args
+        )
+    }
+}
+
+/// Where log entries go. Shared with every thread that logs, so it has to be
+/// `Sync`.
+pub trait Sink: Sync {
+    fn log(&self, entry: &Entry);
+
+    fn flush(&self) {}
+}
+
+/// Configures the logger, see `init`.
+pub struct Builder {
+    level: LevelFilter,
+    filters: Vec<(String, LevelFilter)>,
+    sinks: Vec<&'static dyn Sink>,
+}
+
+impl Builder {
+    /// `Info` and above to `LOG_DEBUG`, unless sinks are added.
+    pub fn new() -> Builder {
+        Builder {
+            level: LevelFilter::Info,
+            filters: Vec::new(),
+            sinks: Vec::new(),
+        }
+    }
+
+    /// The level of modules without a filter of their own.
+    pub fn level(mut self, level: LevelFilter) -> Builder {
+        self.level = level;
+        self
+    }
+
+    /// The level of `module` and its submodules.
+    pub fn filter(mut self, module: &str, level: LevelFilter) -> Builder {
+        set_filter(&mut self.filters, module, level);
+        self
+    }
+
+    pub fn sink(mut self, sink: &'static dyn Sink) -> Builder {
+        self.sinks.push(sink);
+        self
+    }
+
+    /// Installs the logger, fails if there already is one. Sinks are flushed
+    /// on `shutdown::run`.
+    pub fn init(mut self) -> Result<(), SetLoggerError> {
+        if self.sinks.is_empty() {
+            self.sinks.push(&LOG_DEBUG);
+        }
+
+        log::set_logger(&LOGGER)?;
+        LOGGER.with_config(|config| {
+            config.level = self.level;
+            config.filters = self.filters;
+            config.sinks = self.sinks;
+            log::set_max_level(config.max_level());
+        });
+        shutdown::register("logging", || log::logger().flush()).forget();
+        Ok(())
+    }
+}
+
+impl Default for Builder {
+    fn default() -> Builder {
+        Builder::new()
+    }
+}
+
+/// Changes the level of modules without a filter of their own.
+pub fn set_level(level: LevelFilter) {
+    LOGGER.with_config(|config| {
+        config.level = level;
+        log::set_max_level(config.max_level());
+    });
+}
+
+/// Changes or adds the filter of `module` and its submodules.
+pub fn set_module_level(module: &str, level: LevelFilter) {
+    LOGGER.with_config(|config| {
+        set_filter(&mut config.filters, module, level);
+        log::set_max_level(config.max_level());
+    });
+}
+
+/// How many records were dropped because the logger was busy, logging from
+/// within a sink or on another thread.
+pub fn dropped() -> usize {
+    LOGGER.dropped.load(Ordering::Relaxed)
+}
+
+fn set_filter(filters: &mut Vec<(String, LevelFilter)>, module: &str, level: LevelFilter) {
+    match filters.iter_mut().find(|(filtered, _)| filtered == module) {
+        Some(filter) => filter.1 = level,
+        None => filters.push((String::from(module), level)),
+    }
+}
+
+struct Config {
+    level: LevelFilter,
+    filters: Vec<(String, LevelFilter)>,
+    sinks: Vec<&'static dyn Sink>,
+}
 
-    unsafe {
-        let ptr = null_ending.as_ptr();
-        Log_Debug(ptr as *const i8);
+impl Config {
+    fn enabled(&self, metadata: &Metadata) -> bool {
+        let target = metadata.target();
+        let level = self
+            .filters
+            .iter()
+            .filter(|(module, _)| {
+                target.starts_with(module.as_str())
+                    && (target.len() == module.len() || target[module.len()..].starts_with("::"))
+            })
+            .max_by_key(|(module, _)| module.len())
+            .map_or(self.level, |(_, level)| *level);
+
+        metadata.level() <= level
+    }
+
+    fn max_level(&self) -> LevelFilter {
+        self.filters
+            .iter()
+            .map(|(_, level)| *level)
+            .fold(self.level, core::cmp::max)
+    }
+}
+
+struct Logger {
+    // not waited for, sinks may log
+    config: SpinLock<Config>,
+    dropped: AtomicUsize,
+}
+
+static LOGGER: Logger = Logger {
+    config: SpinLock::new(Config {
+        level: LevelFilter::Info,
+        filters: Vec::new(),
+        sinks: Vec::new(),
+    }),
+    dropped: AtomicUsize::new(0),
+};
+
+impl Logger {
+    fn with_config<R>(&self, f: impl FnOnce(&mut Config) -> R) -> Option<R> {
+        self.config.try_with(f)
+    }
+}
+
+impl Log for Logger {
+    fn enabled(&self, metadata: &Metadata) -> bool {
+        // while busy only the global maximum is known
+        self.with_config(|config| config.enabled(metadata))
+            .unwrap_or_else(|| metadata.level() <= log::max_level())
+    }
+
+    fn log(&self, record: &Record) {
+        let logged = self.with_config(|config| {
+            if !config.enabled(record.metadata()) {
+                return;
+            }
+
+            let entry = Entry {
+                uptime: Instant::now().uptime(),
+                level: record.level(),
+                target: record.target(),
+                args: *record.args(),
+            };
+            for sink in &config.sinks {
+                sink.log(&entry);
+            }
+        });
+        if logged.is_none() {
+            self.dropped.fetch_add(1, Ordering::Relaxed);
+        }
+    }
+
+    fn flush(&self) {
+        self.with_config(|config| {
+            for sink in &config.sinks {
+                sink.flush();
+            }
+        });
+    }
+}
+
+/// Sink for `Log_Debug`, the output of `azsphere device app start -d`.
+pub struct LogDebug;
+
+pub static LOG_DEBUG: LogDebug = LogDebug;
+
+impl Sink for LogDebug {
+    fn log(&self, entry: &Entry) {
+        let _ = writeln!(DebugWriter::new(), "{}", entry);
+    }
+}
+
+/// Writes entries as lines to a UART, e.g. a debug header.
+#[cfg(feature = "uart")]
+pub struct UartSink {
+    uart: Uart,
+}
+
+#[cfg(feature = "uart")]
+impl UartSink {
+    pub fn new(uart: Uart) -> UartSink {
+        UartSink { uart }
+    }
+}
+
+#[cfg(feature = "uart")]
+impl Sink for UartSink {
+    fn log(&self, entry: &Entry) {
+        let _ = self.uart.write_all(format!("{}\r\n", entry).as_bytes());
+    }
+
+    fn flush(&self) {
+        let _ = self.uart.flush();
     }
 }
+
+/// Keeps the last entries in memory, e.g. to attach them to a crash report.
+pub struct RingBuffer {
+    capacity: usize,
+    lines: SpinLock<VecDeque<String>>,
+}
+
+impl RingBuffer {
+    /// Keeps up to `capacity` lines.
+    pub fn new(capacity: usize) -> RingBuffer {
+        RingBuffer {
+            capacity,
+            lines: SpinLock::new(VecDeque::with_capacity(capacity)),
+        }
+    }
+
+    /// Oldest first.
+    pub fn lines(&self) -> Vec<String> {
+        self.lines.with(|lines| lines.iter().cloned().collect())
+    }
+
+    pub fn clear(&self) {
+        self.lines.with(|lines| lines.clear());
+    }
+}
+
+impl Sink for RingBuffer {
+    fn log(&self, entry: &Entry) {
+        if self.capacity == 0 {
+            return;
+        }
+
+        // formatted before locking, the arguments may log
+        let line = format!("{}", entry);
+        self.lines.with(|lines| {
+            if lines.len() == self.capacity {
+                lines.pop_front();
+            }
+            lines.push_back(line);
+        });
+    }
+}
+
+/// Queues entries of `level` and above as JSON telemetry messages, until
+/// `send` hands them to the IoT Hub client. Sending from within the logger
+/// would recurse into the SDK.
+#[cfg(feature = "iot")]
+pub struct TelemetrySink {
+    level: Level,
+    capacity: usize,
+    queue: SpinLock<VecDeque<String>>,
+}
+
+#[cfg(feature = "iot")]
+impl TelemetrySink {
+    /// Keeps up to `capacity` messages, dropping the oldest.
+    pub fn new(level: Level, capacity: usize) -> TelemetrySink {
+        TelemetrySink {
+            level,
+            capacity,
+            queue: SpinLock::new(VecDeque::new()),
+        }
+    }
+
+    /// Sends the queued messages. On failure the rest stays queued.
+    pub fn send(&self, provisioning: &AzureProvisioning) -> Result<(), Error> {
+        loop {
+            // not locked while sending, the client may log
+            let message = match self.queue.with(|queue| queue.pop_front()) {
+                Some(message) => message,
+                None => return Ok(()),
+            };
+            if let Err(error) = provisioning.send_telemetry(&message) {
+                self.queue.with(|queue| queue.push_front(message));
+                return Err(error);
+            }
+        }
+    }
+}
+
+#[cfg(feature = "iot")]
+impl Sink for TelemetrySink {
+    fn log(&self, entry: &Entry) {
+        if entry.level > self.level || self.capacity == 0 {
+            return;
+        }
+
+        let mut message = String::from("{\"level\":\"");
+        message.push_str(entry.level.as_str());
+        message.push_str("\",\"target\":");
+        push_json_string(&mut message, entry.target);
+        message.push_str(&format!(
+            ",\"uptimeMs\":{},\"message\":",
+            entry.uptime.as_millis()
+        ));
+        push_json_string(&mut message, &format!("{}", entry.args));
+        message.push('}');
+
+        self.queue.with(|queue| {
+            if queue.len() == self.capacity {
+                queue.pop_front();
+            }
+            queue.push_back(message);
+        });
+    }
+}
+
+#[cfg(test)]
+mod tests;
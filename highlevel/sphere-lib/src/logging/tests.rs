@@ -0,0 +1,43 @@
+use alloc::boxed::Box;
+
+use log::{info, warn, LevelFilter};
+
+use super::{Builder, Entry, RingBuffer, Sink};
+
+// logs from within the logger, which has to be dropped
+struct Reentrant;
+
+impl Sink for Reentrant {
+    fn log(&self, entry: &Entry) {
+        if entry.target != "reentrant" {
+            warn!(target: "reentrant", "logged from a sink");
+        }
+    }
+}
+
+// the logger is global, so this is the only test installing it
+#[test]
+fn filters_and_drops_records_logged_from_sinks() {
+    static REENTRANT: Reentrant = Reentrant;
+    let lines: &'static RingBuffer = Box::leak(Box::new(RingBuffer::new(2)));
+
+    Builder::new()
+        .level(LevelFilter::Warn)
+        .filter("app::net", LevelFilter::Debug)
+        .sink(lines)
+        .sink(&REENTRANT)
+        .init()
+        .unwrap();
+
+    info!(target: "app", "filtered");
+    info!(target: "app::net::dns", "first");
+    info!(target: "app::network", "filtered by prefix");
+    warn!(target: "app", "second");
+    warn!(target: "app", "third");
+
+    let lines = lines.lines();
+    assert_eq!(lines.len(), 2);
+    assert!(lines[0].ends_with("WARN  app: second"), "{}", lines[0]);
+    assert!(lines[1].ends_with("WARN  app: third"), "{}", lines[1]);
+    assert_eq!(super::dropped(), 3);
+}
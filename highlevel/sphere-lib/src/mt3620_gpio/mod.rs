@@ -1,39 +1,139 @@
+use embedded_hal::digital::v2::{toggleable, InputPin, OutputPin, StatefulOutputPin};
+
 extern crate sphere_sys;
+use sphere_sys::close;
+use sphere_sys::GPIO_GetValue;
+use sphere_sys::GPIO_OpenAsInput;
 use sphere_sys::GPIO_OpenAsOutput;
+use sphere_sys::GPIO_OutputMode_OpenDrain;
+use sphere_sys::GPIO_OutputMode_OpenSource;
+use sphere_sys::GPIO_OutputMode_PushPull;
 use sphere_sys::GPIO_SetValue;
+use sphere_sys::GPIO_Value_High;
+use sphere_sys::GPIO_Value_Low;
+use sphere_sys::GPIO_Value_Type;
+
+use crate::error::check;
+use crate::fd::{AsRawFd, RawFd};
+use crate::Error;
 
-const GPIO_OUTPUT_MODE_PUSH_PULL: u8 = 0;
-const GPIO_VALUE_LOW: u8 = 0;
-const GPIO_VALUE_HIGH: u8 = 1;
+pub enum OutputMode {
+    PushPull,
+    /// only drives low, needs a pull-up
+    OpenDrain,
+    /// only drives high, needs a pull-down
+    OpenSource,
+}
 
 pub struct GpioPort {
     fd: i32,
 }
 
 impl GpioPort {
-    pub fn open(number: i32) -> GpioPort {
-        let out_fd =
-            unsafe { GPIO_OpenAsOutput(number, GPIO_OUTPUT_MODE_PUSH_PULL, GPIO_VALUE_HIGH) };
+    /// Opens a push-pull output, initially high.
+    pub fn open(number: i32) -> Result<GpioPort, Error> {
+        GpioPort::open_output(number, OutputMode::PushPull, true)
+    }
+
+    pub fn open_output(
+        number: i32,
+        mode: OutputMode,
+        initial_value: bool,
+    ) -> Result<GpioPort, Error> {
+        let mode = match mode {
+            OutputMode::PushPull => GPIO_OutputMode_PushPull,
+            OutputMode::OpenDrain => GPIO_OutputMode_OpenDrain,
+            OutputMode::OpenSource => GPIO_OutputMode_OpenSource,
+        };
+
+        let out_fd = unsafe { GPIO_OpenAsOutput(number, mode, to_value(initial_value)) };
 
-        GpioPort { fd: out_fd }
+        Ok(GpioPort { fd: check(out_fd)? })
     }
 
-    pub fn set_high(&self) {
-        unsafe {
-            GPIO_SetValue(self.fd, GPIO_VALUE_HIGH);
-        }
+    pub fn open_input(number: i32) -> Result<GpioPort, Error> {
+        let in_fd = unsafe { GPIO_OpenAsInput(number) };
+
+        Ok(GpioPort { fd: check(in_fd)? })
+    }
+
+    pub fn set_high(&self) -> Result<(), Error> {
+        self.set(true)
     }
 
-    pub fn set_low(&self) {
-        unsafe {
-            GPIO_SetValue(self.fd, GPIO_VALUE_LOW);
-        }
+    pub fn set_low(&self) -> Result<(), Error> {
+        self.set(false)
     }
 
-    pub fn set(&self, state: bool) {
-        match state {
-            true => self.set_high(),
-            false => self.set_low(),
-        }
+    pub fn set(&self, state: bool) -> Result<(), Error> {
+        check(unsafe { GPIO_SetValue(self.fd, to_value(state)) }).map(|_| ())
+    }
+
+    /// The level on the pin, `true` for high. For outputs that's the level
+    /// last set.
+    pub fn get(&self) -> Result<bool, Error> {
+        let mut value: GPIO_Value_Type = GPIO_Value_Low;
+
+        check(unsafe { GPIO_GetValue(self.fd, &mut value) })?;
+
+        Ok(value == GPIO_Value_High)
+    }
+}
+
+impl AsRawFd for GpioPort {
+    fn as_raw_fd(&self) -> RawFd {
+        self.fd
     }
 }
+
+impl Drop for GpioPort {
+    fn drop(&mut self) {
+        unsafe { close(self.fd) };
+    }
+}
+
+impl OutputPin for GpioPort {
+    type Error = Error;
+
+    fn set_low(&mut self) -> Result<(), Self::Error> {
+        self.set(false)
+    }
+
+    fn set_high(&mut self) -> Result<(), Self::Error> {
+        self.set(true)
+    }
+}
+
+impl StatefulOutputPin for GpioPort {
+    fn is_set_high(&self) -> Result<bool, Self::Error> {
+        self.get()
+    }
+
+    fn is_set_low(&self) -> Result<bool, Self::Error> {
+        self.get().map(|high| !high)
+    }
+}
+
+impl toggleable::Default for GpioPort {}
+
+impl InputPin for GpioPort {
+    type Error = Error;
+
+    fn is_high(&self) -> Result<bool, Self::Error> {
+        self.get()
+    }
+
+    fn is_low(&self) -> Result<bool, Self::Error> {
+        self.get().map(|high| !high)
+    }
+}
+
+fn to_value(state: bool) -> GPIO_Value_Type {
+    match state {
+        true => GPIO_Value_High,
+        false => GPIO_Value_Low,
+    }
+}
+
+#[cfg(all(test, feature = "mock"))]
+mod tests;
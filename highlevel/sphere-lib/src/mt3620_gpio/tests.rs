@@ -0,0 +1,47 @@
+use embedded_hal::digital::v2::{InputPin, ToggleableOutputPin};
+use sphere_sys::mock;
+use sphere_sys::mock::errno::{EBADF, EBUSY};
+
+use super::{GpioPort, OutputMode};
+use crate::Error;
+
+const LED: i32 = 9;
+const BUTTON: i32 = 12;
+
+#[test]
+fn drives_outputs() {
+    let mut led = GpioPort::open_output(LED, OutputMode::PushPull, false).unwrap();
+    assert_eq!(mock::gpio_value(LED), Some(0));
+
+    led.set_high().unwrap();
+    assert_eq!(mock::gpio_value(LED), Some(1));
+    assert_eq!(led.get(), Ok(true));
+
+    led.toggle().unwrap();
+    assert_eq!(mock::gpio_value(LED), Some(0));
+}
+
+#[test]
+fn reads_inputs() {
+    let button = GpioPort::open_input(BUTTON).unwrap();
+    assert_eq!(button.is_high(), Ok(true));
+
+    mock::set_gpio_value(BUTTON, 0);
+    assert_eq!(button.is_low(), Ok(true));
+}
+
+#[test]
+fn fails_to_open_a_gpio_twice() {
+    let _led = GpioPort::open(LED).unwrap();
+    assert_eq!(GpioPort::open_input(LED).err(), Some(Error::Os(EBUSY)));
+}
+
+#[test]
+fn reports_failing_calls() {
+    let led = GpioPort::open(LED).unwrap();
+    mock::fail_next("GPIO_SetValue", EBADF);
+
+    assert_eq!(led.set_low(), Err(Error::Os(EBADF)));
+    assert_eq!(mock::gpio_value(LED), Some(1));
+    assert_eq!(mock::call_count("GPIO_SetValue"), 1);
+}
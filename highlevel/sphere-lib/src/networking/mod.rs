@@ -6,7 +6,10 @@ use sphere_sys::Networking_GetInterfaceCount;
 use sphere_sys::Networking_IsNetworkingReady;
 use sphere_sys::Networking_SetInterfaceState;
 
-pub fn set_interface_state(interface: &str, enable: bool) -> i32 {
+use crate::error::{check, check_size};
+use crate::Error;
+
+pub fn set_interface_state(interface: &str, enable: bool) -> Result<(), Error> {
     let null_ending = format!("{}\0", interface);
 
     let result = unsafe {
@@ -14,19 +17,17 @@ pub fn set_interface_state(interface: &str, enable: bool) -> i32 {
         Networking_SetInterfaceState(ptr as *const i8, enable)
     };
 
-    result
+    check(result).map(|_| ())
 }
 
-pub fn get_interface_count() -> isize {
-    unsafe { Networking_GetInterfaceCount() }
+pub fn get_interface_count() -> Result<usize, Error> {
+    check_size(unsafe { Networking_GetInterfaceCount() })
 }
 
-pub fn is_networking_ready() -> bool {
+pub fn is_networking_ready() -> Result<bool, Error> {
     let mut is_ready = false;
 
-    unsafe {
-        Networking_IsNetworkingReady(&mut is_ready);
-    };
+    check(unsafe { Networking_IsNetworkingReady(&mut is_ready) })?;
 
-    is_ready
+    Ok(is_ready)
 }
@@ -0,0 +1,494 @@
+//! A small typed key-value store in mutable storage.
+//!
+//! The region given to `Settings::open` is split into two pages. The active
+//! page holds a header (generation and schema version) followed by a log of
+//! records, each change is appended as a CRC protected record. Once the page
+//! is full, the live entries are written to the other page, which becomes
+//! active when its header is written last. A reset at any point leaves either
+//! the old or the new state.
+//!
+//! Record checksums include the page generation, so leftovers of earlier
+//! uses of a page end the log instead of being read back.
+//!
+//! The format only needs a `Backend`, which is implemented for
+//! `MutableFile` and, to run it on the host, for a `RefCell<Vec<u8>>` in
+//! memory. In mutable storage the region has to start at
+//! `storage::APP_DATA_OFFSET` or later.
+
+use alloc::collections::BTreeMap;
+use alloc::string::String;
+use alloc::vec;
+use alloc::vec::Vec;
+use core::cell::RefCell;
+use core::convert::TryInto;
+use core::str;
+
+use sphere_sys::EINVAL;
+use sphere_sys::ENOSPC;
+
+#[cfg(feature = "storage")]
+use crate::storage::{MutableFile, SeekFrom, APP_DATA_OFFSET};
+use crate::util::crc32;
+use crate::Error;
+
+/// Where `Settings` keeps its pages.
+pub trait Backend {
+    /// Reads up to `buffer.len()` bytes at `offset`, returns how many. Less
+    /// than asked for means the end of the backend.
+    fn read_at(&self, offset: u64, buffer: &mut [u8]) -> Result<usize, Error>;
+
+    fn write_at(&self, offset: u64, data: &[u8]) -> Result<(), Error>;
+
+    /// Returns once all writes are persistent.
+    fn sync(&self) -> Result<(), Error>;
+
+    /// Bytes at the start that belong to someone else, `Settings::open`
+    /// refuses regions overlapping them.
+    fn reserved_size(&self) -> u64 {
+        0
+    }
+}
+
+#[cfg(feature = "storage")]
+impl Backend for MutableFile {
+    fn read_at(&self, offset: u64, buffer: &mut [u8]) -> Result<usize, Error> {
+        self.seek(SeekFrom::Start(offset))?;
+        let mut read = 0;
+        while read < buffer.len() {
+            match self.read(&mut buffer[read..])? {
+                0 => break,
+                count => read += count,
+            }
+        }
+        Ok(read)
+    }
+
+    fn write_at(&self, offset: u64, data: &[u8]) -> Result<(), Error> {
+        self.seek(SeekFrom::Start(offset))?;
+        self.write_all(data)
+    }
+
+    fn sync(&self) -> Result<(), Error> {
+        MutableFile::sync(self)
+    }
+
+    fn reserved_size(&self) -> u64 {
+        APP_DATA_OFFSET
+    }
+}
+
+/// Grows like a file when written past its end.
+impl Backend for RefCell<Vec<u8>> {
+    fn read_at(&self, offset: u64, buffer: &mut [u8]) -> Result<usize, Error> {
+        let memory = self.borrow();
+        let start = (offset as usize).min(memory.len());
+        let count = buffer.len().min(memory.len() - start);
+        buffer[..count].copy_from_slice(&memory[start..start + count]);
+        Ok(count)
+    }
+
+    fn write_at(&self, offset: u64, data: &[u8]) -> Result<(), Error> {
+        let mut memory = self.borrow_mut();
+        let start = offset as usize;
+        let end = start + data.len();
+        if memory.len() < end {
+            memory.resize(end, 0);
+        }
+        memory[start..end].copy_from_slice(data);
+        Ok(())
+    }
+
+    fn sync(&self) -> Result<(), Error> {
+        Ok(())
+    }
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub enum Value {
+    Bool(bool),
+    Int(i64),
+    Str(String),
+    Bytes(Vec<u8>),
+}
+
+const PAGE_MAGIC: [u8; 4] = *b"SETS";
+const PAGE_HEADER_SIZE: usize = 16;
+const RECORD_HEADER_SIZE: usize = 4;
+const CRC_SIZE: usize = 4;
+const MAX_KEY_LEN: usize = 255;
+
+// record tags, 0 and 0xff (zeroed or erased) end the log
+const TAG_BOOL: u8 = 1;
+const TAG_INT: u8 = 2;
+const TAG_STR: u8 = 3;
+const TAG_BYTES: u8 = 4;
+const TAG_REMOVE: u8 = 5;
+
+pub struct Settings<B: Backend> {
+    backend: B,
+    offset: u64,
+    page_size: usize,
+    /// the active page, `None` until the first write
+    page: Option<usize>,
+    generation: u32,
+    version: u32,
+    /// end of the log in the active page
+    end: usize,
+    entries: BTreeMap<String, Value>,
+}
+
+impl<B: Backend> Settings<B> {
+    /// Loads the settings kept in the `size` bytes at `offset`. Each change
+    /// is limited to half of that, minus a few bytes of overhead.
+    pub fn open(backend: B, offset: u64, size: usize) -> Result<Settings<B>, Error> {
+        let page_size = size / 2;
+        if page_size < PAGE_HEADER_SIZE + RECORD_HEADER_SIZE + CRC_SIZE
+            || offset < backend.reserved_size()
+        {
+            return Err(Error::Os(EINVAL as i32));
+        }
+
+        let mut settings = Settings {
+            backend,
+            offset,
+            page_size,
+            page: None,
+            generation: 0,
+            version: 0,
+            end: PAGE_HEADER_SIZE,
+            entries: BTreeMap::new(),
+        };
+
+        let first = settings.read_page(0)?;
+        let second = settings.read_page(1)?;
+        let newest = match (first, second) {
+            (Some(first), Some(second)) => {
+                // generations wrap around
+                if (second.generation.wrapping_sub(first.generation) as i32) > 0 {
+                    Some(second)
+                } else {
+                    Some(first)
+                }
+            }
+            (first, second) => first.or(second),
+        };
+
+        if let Some(page) = newest {
+            settings.page = Some(page.index);
+            settings.generation = page.generation;
+            settings.version = page.version;
+            settings.end = page.end;
+            settings.entries = page.entries;
+        }
+
+        Ok(settings)
+    }
+
+    /// The schema version stored with the settings, 0 for a new store.
+    pub fn version(&self) -> u32 {
+        self.version
+    }
+
+    /// Stores a new schema version, call it after migrating the entries.
+    pub fn set_version(&mut self, version: u32) -> Result<(), Error> {
+        let previous = self.version;
+        self.version = version;
+        let entries = self.entries.clone();
+        if let Err(error) = self.compact(&entries) {
+            self.version = previous;
+            return Err(error);
+        }
+        Ok(())
+    }
+
+    pub fn get(&self, key: &str) -> Option<&Value> {
+        self.entries.get(key)
+    }
+
+    /// `None` if the key is missing or holds another type, same for the
+    /// other getters.
+    pub fn get_bool(&self, key: &str) -> Option<bool> {
+        match self.get(key) {
+            Some(Value::Bool(value)) => Some(*value),
+            _ => None,
+        }
+    }
+
+    pub fn get_int(&self, key: &str) -> Option<i64> {
+        match self.get(key) {
+            Some(Value::Int(value)) => Some(*value),
+            _ => None,
+        }
+    }
+
+    pub fn get_str(&self, key: &str) -> Option<&str> {
+        match self.get(key) {
+            Some(Value::Str(value)) => Some(value),
+            _ => None,
+        }
+    }
+
+    pub fn get_bytes(&self, key: &str) -> Option<&[u8]> {
+        match self.get(key) {
+            Some(Value::Bytes(value)) => Some(value),
+            _ => None,
+        }
+    }
+
+    pub fn keys(&self) -> impl Iterator<Item = &str> {
+        self.entries.keys().map(|key| key.as_str())
+    }
+
+    /// Persists `value` under `key`, keys are at most 255 bytes. Storing the
+    /// current value again doesn't write anything.
+    pub fn set(&mut self, key: &str, value: Value) -> Result<(), Error> {
+        if key.len() > MAX_KEY_LEN {
+            return Err(Error::Os(EINVAL as i32));
+        }
+        if self.entries.get(key) == Some(&value) {
+            return Ok(());
+        }
+        self.persist(key, Some(value))
+    }
+
+    pub fn set_bool(&mut self, key: &str, value: bool) -> Result<(), Error> {
+        self.set(key, Value::Bool(value))
+    }
+
+    pub fn set_int(&mut self, key: &str, value: i64) -> Result<(), Error> {
+        self.set(key, Value::Int(value))
+    }
+
+    pub fn set_str(&mut self, key: &str, value: &str) -> Result<(), Error> {
+        self.set(key, Value::Str(String::from(value)))
+    }
+
+    pub fn set_bytes(&mut self, key: &str, value: &[u8]) -> Result<(), Error> {
+        self.set(key, Value::Bytes(value.to_vec()))
+    }
+
+    pub fn remove(&mut self, key: &str) -> Result<(), Error> {
+        if !self.entries.contains_key(key) {
+            return Ok(());
+        }
+        self.persist(key, None)
+    }
+
+    pub fn into_backend(self) -> B {
+        self.backend
+    }
+
+    fn persist(&mut self, key: &str, value: Option<Value>) -> Result<(), Error> {
+        let record = encode_record(self.generation, key, value.as_ref())?;
+
+        match self.page {
+            Some(page) if self.end + record.len() <= self.page_size => {
+                self.backend
+                    .write_at(self.page_offset(page) + self.end as u64, &record)?;
+                self.backend.sync()?;
+                self.end += record.len();
+                apply(&mut self.entries, key, value);
+                Ok(())
+            }
+            // full, or nothing written yet
+            _ => {
+                let mut entries = self.entries.clone();
+                apply(&mut entries, key, value);
+                self.compact(&entries)?;
+                self.entries = entries;
+                Ok(())
+            }
+        }
+    }
+
+    // writes `entries` to the inactive page and switches over to it
+    fn compact(&mut self, entries: &BTreeMap<String, Value>) -> Result<(), Error> {
+        let page = self.page.map_or(0, |page| 1 - page);
+        let generation = self.generation.wrapping_add(1);
+
+        let mut records = Vec::new();
+        for (key, value) in entries {
+            records.extend_from_slice(&encode_record(generation, key, Some(value))?);
+        }
+        if PAGE_HEADER_SIZE + records.len() > self.page_size {
+            return Err(Error::Os(ENOSPC as i32));
+        }
+
+        let mut header = [0u8; PAGE_HEADER_SIZE];
+        header[0..4].copy_from_slice(&PAGE_MAGIC);
+        header[4..8].copy_from_slice(&generation.to_le_bytes());
+        header[8..12].copy_from_slice(&self.version.to_le_bytes());
+        let crc = crc32(&header[..12]);
+        header[12..16].copy_from_slice(&crc.to_le_bytes());
+
+        // the header goes last, until then the old page stays active
+        let page_offset = self.page_offset(page);
+        self.backend
+            .write_at(page_offset + PAGE_HEADER_SIZE as u64, &records)?;
+        self.backend.sync()?;
+        self.backend.write_at(page_offset, &header)?;
+        self.backend.sync()?;
+
+        self.page = Some(page);
+        self.generation = generation;
+        self.end = PAGE_HEADER_SIZE + records.len();
+        Ok(())
+    }
+
+    fn page_offset(&self, page: usize) -> u64 {
+        self.offset + (page * self.page_size) as u64
+    }
+
+    fn read_page(&self, index: usize) -> Result<Option<Page>, Error> {
+        let mut data = vec![0u8; self.page_size];
+        let read = self.backend.read_at(self.page_offset(index), &mut data)?;
+        data.truncate(read);
+
+        if data.len() < PAGE_HEADER_SIZE || data[0..4] != PAGE_MAGIC {
+            return Ok(None);
+        }
+        if crc32(&data[..12]) != u32::from_le_bytes(data[12..16].try_into().unwrap()) {
+            return Ok(None);
+        }
+
+        let generation = u32::from_le_bytes(data[4..8].try_into().unwrap());
+        let mut entries = BTreeMap::new();
+        let mut end = PAGE_HEADER_SIZE;
+        while let Some((key, value, len)) = decode_record(generation, &data[end..]) {
+            apply(&mut entries, key, value);
+            end += len;
+        }
+
+        Ok(Some(Page {
+            index,
+            generation,
+            version: u32::from_le_bytes(data[8..12].try_into().unwrap()),
+            end,
+            entries,
+        }))
+    }
+}
+
+struct Page {
+    index: usize,
+    generation: u32,
+    version: u32,
+    end: usize,
+    entries: BTreeMap<String, Value>,
+}
+
+fn apply(entries: &mut BTreeMap<String, Value>, key: &str, value: Option<Value>) {
+    match value {
+        Some(value) => entries.insert(String::from(key), value),
+        None => entries.remove(key),
+    };
+}
+
+// tag, key length, value length (u16), key, value, CRC of the generation and
+// all of the above
+fn encode_record(generation: u32, key: &str, value: Option<&Value>) -> Result<Vec<u8>, Error> {
+    let mut encoded = Vec::new();
+    let tag = match value {
+        Some(Value::Bool(value)) => {
+            encoded.push(*value as u8);
+            TAG_BOOL
+        }
+        Some(Value::Int(value)) => {
+            encode_int(*value, &mut encoded);
+            TAG_INT
+        }
+        Some(Value::Str(value)) => {
+            encoded.extend_from_slice(value.as_bytes());
+            TAG_STR
+        }
+        Some(Value::Bytes(value)) => {
+            encoded.extend_from_slice(value);
+            TAG_BYTES
+        }
+        None => TAG_REMOVE,
+    };
+    if encoded.len() > u16::MAX as usize {
+        return Err(Error::Os(EINVAL as i32));
+    }
+
+    let mut record = Vec::with_capacity(RECORD_HEADER_SIZE + key.len() + encoded.len() + CRC_SIZE);
+    record.push(tag);
+    record.push(key.len() as u8);
+    record.extend_from_slice(&(encoded.len() as u16).to_le_bytes());
+    record.extend_from_slice(key.as_bytes());
+    record.extend_from_slice(&encoded);
+    let crc = record_crc(generation, &record);
+    record.extend_from_slice(&crc.to_le_bytes());
+    Ok(record)
+}
+
+// `None` at the end of the log
+fn decode_record(generation: u32, data: &[u8]) -> Option<(&str, Option<Value>, usize)> {
+    if data.len() < RECORD_HEADER_SIZE {
+        return None;
+    }
+    let tag = data[0];
+    let key_len = data[1] as usize;
+    let value_len = u16::from_le_bytes(data[2..4].try_into().unwrap()) as usize;
+
+    let value_start = RECORD_HEADER_SIZE + key_len;
+    let crc_start = value_start + value_len;
+    if data.len() < crc_start + CRC_SIZE {
+        return None;
+    }
+    let crc = u32::from_le_bytes(data[crc_start..crc_start + CRC_SIZE].try_into().unwrap());
+    if record_crc(generation, &data[..crc_start]) != crc {
+        return None;
+    }
+
+    let key = str::from_utf8(&data[RECORD_HEADER_SIZE..value_start]).ok()?;
+    let encoded = &data[value_start..crc_start];
+    let value = match tag {
+        TAG_BOOL if value_len == 1 => Some(Value::Bool(encoded[0] != 0)),
+        TAG_INT => Some(Value::Int(decode_int(encoded)?)),
+        TAG_STR => Some(Value::Str(String::from(str::from_utf8(encoded).ok()?))),
+        TAG_BYTES => Some(Value::Bytes(encoded.to_vec())),
+        TAG_REMOVE => None,
+        _ => return None,
+    };
+
+    Some((key, value, crc_start + CRC_SIZE))
+}
+
+fn record_crc(generation: u32, record: &[u8]) -> u32 {
+    let mut data = Vec::with_capacity(4 + record.len());
+    data.extend_from_slice(&generation.to_le_bytes());
+    data.extend_from_slice(record);
+    crc32(&data)
+}
+
+// zigzag LEB128, small magnitudes take a single byte
+fn encode_int(value: i64, encoded: &mut Vec<u8>) {
+    let mut zigzag = ((value << 1) ^ (value >> 63)) as u64;
+    loop {
+        let byte = (zigzag & 0x7f) as u8;
+        zigzag >>= 7;
+        if zigzag == 0 {
+            encoded.push(byte);
+            return;
+        }
+        encoded.push(byte | 0x80);
+    }
+}
+
+fn decode_int(encoded: &[u8]) -> Option<i64> {
+    let mut zigzag = 0u64;
+    for (index, byte) in encoded.iter().enumerate().take(10) {
+        zigzag |= ((byte & 0x7f) as u64) << (7 * index);
+        if byte & 0x80 == 0 {
+            if index + 1 != encoded.len() {
+                return None;
+            }
+            return Some((zigzag >> 1) as i64 ^ -((zigzag & 1) as i64));
+        }
+    }
+    None
+}
+
+#[cfg(test)]
+mod tests;
@@ -0,0 +1,223 @@
+use alloc::vec::Vec;
+use core::cell::RefCell;
+
+use sphere_sys::EINVAL;
+
+use super::{Settings, Value, PAGE_HEADER_SIZE};
+use crate::Error;
+
+// two pages of 64 bytes, room for three `set_int` records each
+const SIZE: usize = 128;
+
+fn open(memory: Vec<u8>) -> Settings<RefCell<Vec<u8>>> {
+    Settings::open(RefCell::new(memory), 0, SIZE).unwrap()
+}
+
+fn reopen(settings: Settings<RefCell<Vec<u8>>>) -> Settings<RefCell<Vec<u8>>> {
+    open(settings.into_backend().into_inner())
+}
+
+#[test]
+fn first_open_is_empty_and_writes_nothing() {
+    let settings = open(Vec::new());
+
+    assert_eq!(settings.version(), 0);
+    assert_eq!(settings.keys().count(), 0);
+    assert_eq!(settings.get("missing"), None);
+    assert!(settings.into_backend().into_inner().is_empty());
+}
+
+#[test]
+fn rejects_regions_too_small_for_a_record() {
+    let result = Settings::open(RefCell::new(Vec::new()), 0, 2 * PAGE_HEADER_SIZE);
+    assert_eq!(result.err(), Some(Error::Os(EINVAL as i32)));
+}
+
+#[test]
+fn appends_and_overwrites() {
+    let mut settings = open(Vec::new());
+    settings.set_bool("enabled", true).unwrap();
+    settings.set_str("name", "sphere").unwrap();
+    settings.set_bool("enabled", false).unwrap();
+
+    let mut settings = reopen(settings);
+    assert_eq!(settings.get_bool("enabled"), Some(false));
+    assert_eq!(settings.get_str("name"), Some("sphere"));
+    assert_eq!(settings.get_int("name"), None);
+
+    settings.remove("name").unwrap();
+    let settings = reopen(settings);
+    assert_eq!(settings.get("name"), None);
+    assert_eq!(settings.keys().collect::<Vec<_>>(), ["enabled"]);
+}
+
+#[test]
+fn unchanged_values_are_not_written() {
+    let mut settings = open(Vec::new());
+    settings.set_int("count", 1).unwrap();
+    let end = settings.end;
+
+    settings.set_int("count", 1).unwrap();
+    settings.remove("missing").unwrap();
+    assert_eq!(settings.end, end);
+}
+
+#[test]
+fn compacts_into_the_other_page_when_full() {
+    let mut settings = open(Vec::new());
+    settings.set_bytes("blob", &[7; 8]).unwrap();
+    for count in 0..4 {
+        settings.set_int("count", count).unwrap();
+    }
+    assert_eq!(settings.page, Some(1));
+    assert_eq!(settings.generation, 2);
+
+    let settings = reopen(settings);
+    assert_eq!(settings.page, Some(1));
+    assert_eq!(settings.get_int("count"), Some(3));
+    assert_eq!(settings.get_bytes("blob"), Some(&[7; 8][..]));
+}
+
+#[test]
+fn fails_when_the_live_entries_outgrow_a_page() {
+    let mut settings = open(Vec::new());
+    settings.set_bytes("blob", &[0; 32]).unwrap();
+
+    let result = settings.set_bytes("other", &[0; 32]);
+    assert_eq!(result, Err(Error::Os(sphere_sys::ENOSPC as i32)));
+    assert_eq!(settings.get("other"), None);
+
+    let settings = reopen(settings);
+    assert_eq!(settings.get("other"), None);
+    assert_eq!(settings.get_bytes("blob"), Some(&[0; 32][..]));
+}
+
+#[test]
+fn ignores_a_torn_trailing_record() {
+    let mut settings = open(Vec::new());
+    settings.set_int("first", 1).unwrap();
+    let first_end = settings.end;
+    settings.set_int("second", 2).unwrap();
+
+    // a reset before the last bytes of the record made it
+    let torn = settings.end - 1;
+    let mut memory = settings.into_backend().into_inner();
+    memory[torn] ^= 0xff;
+
+    let mut settings = open(memory);
+    assert_eq!(settings.get_int("first"), Some(1));
+    assert_eq!(settings.get("second"), None);
+    assert_eq!(settings.end, first_end);
+
+    // the next record replaces the torn one
+    settings.set_int("third", 3).unwrap();
+    let settings = reopen(settings);
+    assert_eq!(settings.get_int("first"), Some(1));
+    assert_eq!(settings.get_int("third"), Some(3));
+}
+
+#[test]
+fn picks_the_newer_page_across_a_generation_wrap() {
+    let mut settings = open(Vec::new());
+    settings.generation = u32::MAX - 1;
+    settings.set_int("count", 0).unwrap();
+    assert_eq!(settings.generation, u32::MAX);
+
+    for count in 1..4 {
+        settings.set_int("count", count).unwrap();
+    }
+    assert_eq!(settings.generation, 0);
+    assert_eq!(settings.page, Some(1));
+
+    // both pages are valid, page 0 with the numerically higher generation
+    let settings = reopen(settings);
+    assert_eq!(settings.generation, 0);
+    assert_eq!(settings.page, Some(1));
+    assert_eq!(settings.get_int("count"), Some(3));
+}
+
+#[test]
+fn keeps_the_schema_version() {
+    let mut settings = open(Vec::new());
+    settings.set_int("timeout", 30).unwrap();
+    settings.set_version(1).unwrap();
+
+    // an app expecting version 2 sees the mismatch and migrates
+    let mut settings = reopen(settings);
+    assert_eq!(settings.version(), 1);
+    assert_eq!(settings.get_int("timeout"), Some(30));
+    settings.remove("timeout").unwrap();
+    settings.set_int("timeout_ms", 30_000).unwrap();
+    settings.set_version(2).unwrap();
+
+    let settings = reopen(settings);
+    assert_eq!(settings.version(), 2);
+    assert_eq!(settings.get("timeout"), None);
+    assert_eq!(settings.get_int("timeout_ms"), Some(30_000));
+}
+
+#[test]
+fn rejects_a_page_with_a_corrupt_header() {
+    let mut settings = open(Vec::new());
+    settings.set_version(3).unwrap();
+    settings.set_bool("enabled", true).unwrap();
+
+    let mut memory = settings.into_backend().into_inner();
+    memory[8] ^= 0xff;
+
+    let settings = open(memory);
+    assert_eq!(settings.version(), 0);
+    assert_eq!(settings.get("enabled"), None);
+}
+
+#[test]
+fn round_trips_int_extremes() {
+    let mut settings = open(Vec::new());
+    settings.set_int("min", i64::MIN).unwrap();
+    settings.set_int("max", i64::MAX).unwrap();
+
+    let settings = reopen(settings);
+    assert_eq!(settings.get("min"), Some(&Value::Int(i64::MIN)));
+    assert_eq!(settings.get("max"), Some(&Value::Int(i64::MAX)));
+}
+
+#[cfg(all(feature = "mock", feature = "storage"))]
+mod mutable_file {
+    use sphere_sys::mock;
+    use sphere_sys::mock::errno::{EINVAL, ENOSPC};
+
+    use super::super::Settings;
+    use crate::storage::{MutableFile, APP_DATA_OFFSET};
+    use crate::Error;
+
+    #[test]
+    fn rejects_the_reserved_region() {
+        let file = MutableFile::open().unwrap();
+        let result = Settings::open(file, 0, 1024);
+        assert_eq!(result.err(), Some(Error::Os(EINVAL)));
+    }
+
+    #[test]
+    fn persists_after_the_reserved_region() {
+        let file = MutableFile::open().unwrap();
+        let mut settings = Settings::open(file, APP_DATA_OFFSET, 1024).unwrap();
+        settings.set_str("name", "sphere").unwrap();
+        drop(settings);
+
+        assert!(mock::mutable_storage().len() > APP_DATA_OFFSET as usize);
+        let file = MutableFile::open().unwrap();
+        let settings = Settings::open(file, APP_DATA_OFFSET, 1024).unwrap();
+        assert_eq!(settings.get_str("name"), Some("sphere"));
+    }
+
+    #[test]
+    fn keeps_the_version_if_it_cant_be_stored() {
+        let file = MutableFile::open().unwrap();
+        let mut settings = Settings::open(file, APP_DATA_OFFSET, 1024).unwrap();
+        settings.set_version(1).unwrap();
+
+        mock::fail_next("fsync", ENOSPC);
+        assert_eq!(settings.set_version(2), Err(Error::Os(ENOSPC)));
+        assert_eq!(settings.version(), 1);
+    }
+}
@@ -0,0 +1,112 @@
+//! Cleanup that has to happen before the app exits.
+//!
+//! The OS sends `SIGTERM` before it stops or updates an app, and kills it a
+//! few seconds later. Catch it with `Signal`, stop the event loop from the
+//! signal callback and exit normally, which runs the registered hooks:
+//!
+//! ```ignore
+//! let event_loop = EventLoop::new()?;
+//! event_loop.register_signal(Signal::catch(SIGTERM)?, |event_loop| event_loop.stop())?;
+//!
+//! let led = GpioPort::open(LED)?;
+//! let _hook = shutdown::register("led", move || {
+//!     let _ = led.set_low();
+//! });
+//!
+//! event_loop.run()?;
+//! ```
+//!
+//! sphere-rt's `process::exit` runs the hooks, other apps call `run`
+//! themselves. Modules with global state, like curl, the IoT Hub client and
+//! the logger, register their own hooks, and so does each open `MutableFile`
+//! to sync it.
+//!
+//! Hooks run on the thread calling `run`.
+
+use alloc::boxed::Box;
+use alloc::vec::Vec;
+use core::sync::atomic::{AtomicU32, Ordering};
+
+use log::debug;
+
+use crate::util::SpinLock;
+
+struct Entry {
+    id: u32,
+    name: &'static str,
+    hook: Box<dyn FnOnce() + Send>,
+}
+
+struct Registry {
+    next_id: AtomicU32,
+    // hooks never run while this is locked, so they may register and
+    // unregister
+    hooks: SpinLock<Vec<Entry>>,
+}
+
+static REGISTRY: Registry = Registry {
+    next_id: AtomicU32::new(0),
+    hooks: SpinLock::new(Vec::new()),
+};
+
+impl Registry {
+    fn with_hooks<R>(&self, f: impl FnOnce(&mut Vec<Entry>) -> R) -> R {
+        self.hooks.with(f)
+    }
+}
+
+/// A registered shutdown hook. Dropping it unregisters the hook, e.g. along
+/// with the object it cleans up.
+#[must_use = "the hook is unregistered when this is dropped, call `forget` to keep it"]
+pub struct Hook {
+    id: u32,
+}
+
+impl Hook {
+    /// Keeps the hook registered until `run`.
+    pub fn forget(self) {
+        core::mem::forget(self);
+    }
+}
+
+impl Drop for Hook {
+    fn drop(&mut self) {
+        let id = self.id;
+        // dropped outside of the borrow, the hook may own anything
+        let removed = REGISTRY.with_hooks(|hooks| {
+            hooks
+                .iter()
+                .position(|entry| entry.id == id)
+                .map(|index| hooks.remove(index))
+        });
+        drop(removed);
+    }
+}
+
+/// Runs `hook` on shutdown, after hooks registered later. `name` shows up in
+/// the debug log.
+pub fn register<F>(name: &'static str, hook: F) -> Hook
+where
+    F: FnOnce() + Send + 'static,
+{
+    let id = REGISTRY.next_id.fetch_add(1, Ordering::Relaxed);
+    let entry = Entry {
+        id,
+        name,
+        hook: Box::new(hook),
+    };
+    REGISTRY.with_hooks(|hooks| hooks.push(entry));
+    Hook { id }
+}
+
+/// Runs all registered hooks, the most recently registered first. Each runs
+/// only once, hooks registered meanwhile run as well.
+pub fn run() {
+    while let Some(entry) = REGISTRY.with_hooks(|hooks| hooks.pop()) {
+        debug!("shutdown: {}", entry.name);
+        (entry.hook)();
+    }
+}
+
+#[cfg(test)]
+mod tests;
@@ -0,0 +1,28 @@
+extern crate std;
+
+use std::sync::{Arc, Mutex};
+use std::vec::Vec;
+
+use super::{register, run};
+
+// other tests register hooks too, e.g. for `MutableFile`, so this only looks
+// at its own
+#[test]
+fn runs_hooks_newest_first_unless_dropped() {
+    let ran = Arc::new(Mutex::new(Vec::new()));
+    let hook = |name: &'static str| {
+        let ran = ran.clone();
+        register(name, move || ran.lock().unwrap().push(name))
+    };
+
+    hook("first").forget();
+    let dropped = hook("dropped");
+    hook("second").forget();
+    drop(dropped);
+
+    run();
+    assert_eq!(*ran.lock().unwrap(), ["second", "first"]);
+
+    run();
+    assert_eq!(ran.lock().unwrap().len(), 2);
+}
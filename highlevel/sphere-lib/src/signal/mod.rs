@@ -0,0 +1,177 @@
+#![allow(non_camel_case_types)]
+
+extern crate sphere_sys;
+
+use sphere_sys::__errno_location;
+use sphere_sys::close;
+use sphere_sys::eventfd;
+use sphere_sys::read;
+use sphere_sys::sigaddset;
+use sphere_sys::sigemptyset;
+use sphere_sys::signal;
+use sphere_sys::sigprocmask;
+use sphere_sys::sigset_t;
+use sphere_sys::std::os::raw::c_int;
+use sphere_sys::write;
+use sphere_sys::EAGAIN;
+use sphere_sys::EBUSY;
+use sphere_sys::EFD_CLOEXEC;
+use sphere_sys::EFD_NONBLOCK;
+use sphere_sys::EINVAL;
+use sphere_sys::SIGALRM;
+use sphere_sys::SIG_BLOCK;
+use sphere_sys::SIG_SETMASK;
+
+use core::mem::MaybeUninit;
+use core::ptr;
+use core::sync::atomic::{AtomicI32, Ordering};
+
+use crate::error::check;
+use crate::fd::{AsRawFd, RawFd};
+use crate::Error;
+
+/// Sent by the OS before it stops or updates the app. The app is killed if
+/// it doesn't exit within a few seconds.
+pub const SIGTERM: i32 = sphere_sys::SIGTERM as i32;
+
+// signals caught at the same time
+const MAX_SIGNALS: usize = 4;
+
+struct Slot {
+    // 0 while free
+    signo: AtomicI32,
+    fd: AtomicI32,
+}
+
+impl Slot {
+    const fn new() -> Slot {
+        Slot {
+            signo: AtomicI32::new(0),
+            fd: AtomicI32::new(-1),
+        }
+    }
+}
+
+static SLOTS: [Slot; MAX_SIGNALS] = [Slot::new(), Slot::new(), Slot::new(), Slot::new()];
+
+/// A caught signal as a readable fd, so it can be handled in the event loop
+/// instead of the signal handler, see `EventLoop::register_signal`.
+///
+/// The handler only writes to an eventfd, the self-pipe trick. Dropping the
+/// `Signal` restores the default action.
+pub struct Signal {
+    signo: i32,
+    fd: RawFd,
+}
+
+impl Signal {
+    /// Catches `signo` from now on. Fails with `EBUSY` if it's already
+    /// caught, and with `EINVAL` for `SIGALRM`, which belongs to the
+    /// `Watchdog`.
+    pub fn catch(signo: i32) -> Result<Signal, Error> {
+        if signo <= 0 || signo == SIGALRM as i32 {
+            return Err(Error::Os(EINVAL as i32));
+        }
+        if SLOTS
+            .iter()
+            .any(|slot| slot.signo.load(Ordering::SeqCst) == signo)
+        {
+            return Err(Error::Os(EBUSY as i32));
+        }
+
+        let fd = check(unsafe { eventfd(0, (EFD_NONBLOCK | EFD_CLOEXEC) as i32) })?;
+        let slot = SLOTS.iter().find(|slot| {
+            slot.signo
+                .compare_exchange(0, signo, Ordering::SeqCst, Ordering::SeqCst)
+                .is_ok()
+        });
+        let slot = match slot {
+            Some(slot) => slot,
+            None => {
+                unsafe { close(fd) };
+                return Err(Error::Os(EBUSY as i32));
+            }
+        };
+
+        slot.fd.store(fd, Ordering::SeqCst);
+        unsafe { signal(signo, Some(on_signal)) };
+
+        Ok(Signal { signo, fd })
+    }
+
+    pub fn signo(&self) -> i32 {
+        self.signo
+    }
+
+    /// How often the signal arrived since the last call, 0 if it didn't.
+    pub fn take(&self) -> Result<u64, Error> {
+        let mut count: u64 = 0;
+        let result = unsafe { read(self.fd, &mut count as *mut u64 as *mut _, 8) };
+
+        match check(result as i32) {
+            Ok(_) => Ok(count),
+            Err(Error::Os(errno)) if errno == EAGAIN as i32 => Ok(0),
+            Err(error) => Err(error),
+        }
+    }
+}
+
+impl AsRawFd for Signal {
+    fn as_raw_fd(&self) -> RawFd {
+        self.fd
+    }
+}
+
+impl Drop for Signal {
+    fn drop(&mut self) {
+        // blocked until the fd is closed, so the handler can't write to it
+        // once it's closed or reused. Arriving meanwhile, the signal gets the
+        // default action once unblocked.
+        let previous_mask = block(self.signo);
+        unsafe { signal(self.signo, None) };
+
+        for slot in &SLOTS {
+            if slot.signo.load(Ordering::SeqCst) == self.signo {
+                slot.fd.store(-1, Ordering::SeqCst);
+                slot.signo.store(0, Ordering::SeqCst);
+            }
+        }
+
+        unsafe { close(self.fd) };
+        if let Some(previous_mask) = previous_mask {
+            unsafe { sigprocmask(SIG_SETMASK as i32, &previous_mask, ptr::null_mut()) };
+        }
+    }
+}
+
+// blocks `signo` on this thread, returns the mask to restore
+fn block(signo: i32) -> Option<sigset_t> {
+    unsafe {
+        let mut set = MaybeUninit::<sigset_t>::zeroed().assume_init();
+        let mut previous = MaybeUninit::<sigset_t>::zeroed().assume_init();
+        sigemptyset(&mut set);
+        sigaddset(&mut set, signo);
+        check(sigprocmask(SIG_BLOCK as i32, &set, &mut previous)).ok()?;
+        Some(previous)
+    }
+}
+
+// only async-signal-safe calls in here
+unsafe extern "C" fn on_signal(signo: c_int) {
+    let saved_errno = *__errno_location();
+
+    for slot in &SLOTS {
+        if slot.signo.load(Ordering::SeqCst) == signo {
+            let fd = slot.fd.load(Ordering::SeqCst);
+            if fd >= 0 {
+                let one: u64 = 1;
+                write(fd, &one as *const u64 as *const _, 8);
+            }
+        }
+    }
+
+    *__errno_location() = saved_errno;
+}
+
+#[cfg(all(test, feature = "mock"))]
+mod tests;
@@ -0,0 +1,43 @@
+use alloc::vec::Vec;
+
+use sphere_sys::mock;
+use sphere_sys::mock::errno::{EBUSY, EINVAL};
+use sphere_sys::SIGALRM;
+
+use super::{Signal, SIGTERM};
+use crate::Error;
+
+#[test]
+fn counts_caught_signals() {
+    let signal = Signal::catch(SIGTERM).unwrap();
+    assert_eq!(signal.take(), Ok(0));
+
+    assert!(mock::raise_signal(SIGTERM));
+    assert!(mock::raise_signal(SIGTERM));
+    assert_eq!(signal.take(), Ok(2));
+    assert_eq!(signal.take(), Ok(0));
+}
+
+#[test]
+fn rejects_sigalrm_and_duplicates() {
+    assert_eq!(Signal::catch(SIGALRM as i32).err(), Some(Error::Os(EINVAL)));
+
+    let _signal = Signal::catch(SIGTERM).unwrap();
+    assert_eq!(Signal::catch(SIGTERM).err(), Some(Error::Os(EBUSY)));
+}
+
+#[test]
+fn blocks_the_signal_while_dropping() {
+    let signal = Signal::catch(SIGTERM).unwrap();
+    mock::clear_calls();
+    drop(signal);
+
+    let calls: Vec<_> = mock::calls().into_iter().map(|call| call.name).collect();
+    assert_eq!(calls, ["sigprocmask", "signal", "close", "sigprocmask"]);
+    assert!(!mock::raise_signal(SIGTERM));
+
+    // the slot is free again
+    let signal = Signal::catch(SIGTERM).unwrap();
+    assert!(mock::raise_signal(SIGTERM));
+    assert_eq!(signal.take(), Ok(1));
+}
@@ -0,0 +1,237 @@
+use core::mem::MaybeUninit;
+
+use embedded_hal::blocking::spi;
+use embedded_hal::spi::{Mode, Phase, Polarity};
+
+extern crate sphere_sys;
+
+use sphere_sys::close;
+use sphere_sys::z__SPIMaster_Config_Base;
+use sphere_sys::z__SPIMaster_Config_v1;
+use sphere_sys::z__SPIMaster_InitConfig;
+use sphere_sys::z__SPIMaster_InitTransfers;
+use sphere_sys::z__SPIMaster_Open;
+use sphere_sys::z__SPIMaster_TransferSequential;
+use sphere_sys::z__SPIMaster_Transfer_Base;
+use sphere_sys::z__SPIMaster_Transfer_v1;
+use sphere_sys::SPIMaster_SetBitOrder;
+use sphere_sys::SPIMaster_SetBusSpeed;
+use sphere_sys::SPIMaster_SetMode;
+use sphere_sys::SPIMaster_WriteThenRead;
+use sphere_sys::SPI_BitOrder_LsbFirst;
+use sphere_sys::SPI_BitOrder_MsbFirst;
+use sphere_sys::SPI_ChipSelectPolarity_ActiveHigh;
+use sphere_sys::SPI_ChipSelectPolarity_ActiveLow;
+use sphere_sys::SPI_Mode_0;
+use sphere_sys::SPI_Mode_1;
+use sphere_sys::SPI_Mode_2;
+use sphere_sys::SPI_Mode_3;
+use sphere_sys::SPI_TransferFlags_Read;
+use sphere_sys::SPI_TransferFlags_Write;
+use sphere_sys::EINVAL;
+use sphere_sys::SPI_STRUCTS_VERSION;
+
+use crate::error::{check, check_complete, check_size};
+use crate::fd::{AsRawFd, RawFd};
+pub use crate::isu::Isu;
+use crate::Error;
+pub use embedded_hal::spi::{MODE_0, MODE_1, MODE_2, MODE_3};
+
+/// Chip select line of the ISU.
+pub enum ChipSelect {
+    A,
+    B,
+}
+
+pub enum ChipSelectPolarity {
+    ActiveLow,
+    ActiveHigh,
+}
+
+pub enum BitOrder {
+    MsbFirst,
+    LsbFirst,
+}
+
+/// An ISU opened as SPI master talking to the device behind one chip select.
+/// The ISU has to be listed under `SpiMaster` in the app manifest.
+pub struct SpiMaster {
+    fd: sphere_sys::std::os::raw::c_int,
+}
+
+impl SpiMaster {
+    pub fn open(
+        isu: Isu,
+        chip_select: ChipSelect,
+        polarity: ChipSelectPolarity,
+    ) -> Result<SpiMaster, Error> {
+        // for MT3620 only
+        let chip_select = match chip_select {
+            ChipSelect::A => 0,
+            ChipSelect::B => 1,
+        };
+
+        let fd = unsafe {
+            let mut config = MaybeUninit::<z__SPIMaster_Config_v1>::uninit();
+            let config_base = config.as_mut_ptr() as *mut z__SPIMaster_Config_Base;
+
+            check(z__SPIMaster_InitConfig(config_base, SPI_STRUCTS_VERSION))?;
+
+            (*config.as_mut_ptr()).csPolarity = match polarity {
+                ChipSelectPolarity::ActiveLow => SPI_ChipSelectPolarity_ActiveLow,
+                ChipSelectPolarity::ActiveHigh => SPI_ChipSelectPolarity_ActiveHigh,
+            };
+
+            z__SPIMaster_Open(isu.id(), chip_select, config_base)
+        };
+
+        Ok(SpiMaster { fd: check(fd)? })
+    }
+
+    pub fn set_bus_speed(&self, speed_hz: u32) -> Result<(), Error> {
+        check(unsafe { SPIMaster_SetBusSpeed(self.fd, speed_hz) }).map(|_| ())
+    }
+
+    /// Clock polarity and phase, e.g. [`MODE_0`].
+    pub fn set_mode(&self, mode: Mode) -> Result<(), Error> {
+        let mode = match (mode.polarity, mode.phase) {
+            (Polarity::IdleLow, Phase::CaptureOnFirstTransition) => SPI_Mode_0,
+            (Polarity::IdleLow, Phase::CaptureOnSecondTransition) => SPI_Mode_1,
+            (Polarity::IdleHigh, Phase::CaptureOnFirstTransition) => SPI_Mode_2,
+            (Polarity::IdleHigh, Phase::CaptureOnSecondTransition) => SPI_Mode_3,
+        };
+
+        check(unsafe { SPIMaster_SetMode(self.fd, mode) }).map(|_| ())
+    }
+
+    pub fn set_bit_order(&self, order: BitOrder) -> Result<(), Error> {
+        let order = match order {
+            BitOrder::MsbFirst => SPI_BitOrder_MsbFirst,
+            BitOrder::LsbFirst => SPI_BitOrder_LsbFirst,
+        };
+
+        check(unsafe { SPIMaster_SetBitOrder(self.fd, order) }).map(|_| ())
+    }
+
+    /// Writes `data` while reading into `buffer` of the same length, in one
+    /// chip select cycle. Returns the number of bytes transferred.
+    pub fn transfer(&self, data: &[u8], buffer: &mut [u8]) -> Result<usize, Error> {
+        if data.len() != buffer.len() {
+            return Err(Error::Os(EINVAL as i32));
+        }
+
+        self.transfer_sequential(
+            SPI_TransferFlags_Read | SPI_TransferFlags_Write,
+            data.as_ptr(),
+            buffer.as_mut_ptr(),
+            data.len(),
+        )
+    }
+
+    /// Like `transfer`, reading into the buffer that is written. Applibs
+    /// doesn't say whether the write and read data may overlap, so the data
+    /// is written from a copy.
+    pub fn transfer_in_place(&self, buffer: &mut [u8]) -> Result<usize, Error> {
+        let data = buffer.to_vec();
+        self.transfer(&data, buffer)
+    }
+
+    /// Returns the number of bytes written.
+    pub fn write(&self, data: &[u8]) -> Result<usize, Error> {
+        self.transfer_sequential(
+            SPI_TransferFlags_Write,
+            data.as_ptr(),
+            core::ptr::null_mut(),
+            data.len(),
+        )
+    }
+
+    /// Returns the number of bytes read.
+    pub fn read(&self, buffer: &mut [u8]) -> Result<usize, Error> {
+        self.transfer_sequential(
+            SPI_TransferFlags_Read,
+            core::ptr::null(),
+            buffer.as_mut_ptr(),
+            buffer.len(),
+        )
+    }
+
+    /// Writes, then reads, in one chip select cycle. Returns the total number
+    /// of bytes written and read.
+    pub fn write_then_read(&self, data: &[u8], buffer: &mut [u8]) -> Result<usize, Error> {
+        let transferred = unsafe {
+            SPIMaster_WriteThenRead(
+                self.fd,
+                data.as_ptr(),
+                data.len(),
+                buffer.as_mut_ptr(),
+                buffer.len(),
+            )
+        };
+
+        check_size(transferred)
+    }
+
+    fn transfer_sequential(
+        &self,
+        flags: u32,
+        write_data: *const u8,
+        read_data: *mut u8,
+        length: usize,
+    ) -> Result<usize, Error> {
+        let transferred = unsafe {
+            let mut transfer = MaybeUninit::<z__SPIMaster_Transfer_v1>::uninit();
+            let transfer_base = transfer.as_mut_ptr() as *mut z__SPIMaster_Transfer_Base;
+
+            check(z__SPIMaster_InitTransfers(
+                transfer_base,
+                1,
+                SPI_STRUCTS_VERSION,
+            ))?;
+
+            let transfer = &mut *transfer.as_mut_ptr();
+            transfer.flags = flags;
+            transfer.writeData = write_data;
+            transfer.readData = read_data;
+            transfer.length = length;
+
+            z__SPIMaster_TransferSequential(self.fd, transfer_base, 1, SPI_STRUCTS_VERSION)
+        };
+
+        check_size(transferred)
+    }
+}
+
+impl AsRawFd for SpiMaster {
+    fn as_raw_fd(&self) -> RawFd {
+        self.fd
+    }
+}
+
+impl Drop for SpiMaster {
+    fn drop(&mut self) {
+        unsafe {
+            close(self.fd);
+        }
+    }
+}
+
+impl spi::Transfer<u8> for SpiMaster {
+    type Error = Error;
+
+    fn transfer<'w>(&mut self, words: &'w mut [u8]) -> Result<&'w [u8], Self::Error> {
+        check_complete(self.transfer_in_place(words)?, words.len())?;
+        Ok(words)
+    }
+}
+
+impl spi::Write<u8> for SpiMaster {
+    type Error = Error;
+
+    fn write(&mut self, words: &[u8]) -> Result<(), Self::Error> {
+        check_complete(SpiMaster::write(self, words)?, words.len())
+    }
+}
+
+#[cfg(all(test, feature = "mock"))]
+mod tests;
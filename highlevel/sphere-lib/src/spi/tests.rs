@@ -0,0 +1,98 @@
+use embedded_hal::blocking::spi::{Transfer, Write};
+use sphere_sys::mock;
+use sphere_sys::mock::errno::{EBUSY, EINVAL, EIO};
+
+use super::{BitOrder, ChipSelect, ChipSelectPolarity, SpiMaster, MODE_3};
+use crate::isu::Isu;
+use crate::Error;
+
+// the SPI interface id of ISU1, with chip select B
+const ISU1: i32 = 5;
+const CS_B: i32 = 1;
+
+fn open() -> SpiMaster {
+    SpiMaster::open(Isu::Isu1, ChipSelect::B, ChipSelectPolarity::ActiveLow).unwrap()
+}
+
+#[test]
+fn configures_the_bus() {
+    let spi = SpiMaster::open(Isu::Isu1, ChipSelect::B, ChipSelectPolarity::ActiveHigh).unwrap();
+    spi.set_bus_speed(4_000_000).unwrap();
+    spi.set_mode(MODE_3).unwrap();
+    spi.set_bit_order(BitOrder::LsbFirst).unwrap();
+
+    let bus = mock::spi_bus(ISU1).unwrap();
+    assert_eq!(bus.chip_select, CS_B);
+    assert_eq!(
+        bus.cs_polarity,
+        sphere_sys::SPI_ChipSelectPolarity_ActiveHigh
+    );
+    assert_eq!(bus.speed, 4_000_000);
+    assert_eq!(bus.mode, sphere_sys::SPI_Mode_3);
+    assert_eq!(bus.bit_order, sphere_sys::SPI_BitOrder_LsbFirst);
+}
+
+#[test]
+fn transfers_full_duplex() {
+    let spi = open();
+    mock::spi_inject(ISU1, CS_B, &[0x10, 0x20]);
+
+    let mut buffer = [0; 3];
+    assert_eq!(spi.transfer(&[1, 2, 3], &mut buffer), Ok(3));
+    assert_eq!(mock::spi_take_written(ISU1, CS_B), [1, 2, 3]);
+    // the device shifts out 0xff once it has nothing queued
+    assert_eq!(buffer, [0x10, 0x20, 0xff]);
+
+    let result = spi.transfer(&[1, 2], &mut buffer);
+    assert_eq!(result, Err(Error::Os(EINVAL)));
+}
+
+#[test]
+fn transfers_in_place() {
+    let mut spi = open();
+    mock::spi_inject(ISU1, CS_B, &[4, 5, 6, 7]);
+
+    let mut buffer = [1, 2];
+    assert_eq!(spi.transfer_in_place(&mut buffer), Ok(2));
+    assert_eq!(mock::spi_take_written(ISU1, CS_B), [1, 2]);
+    assert_eq!(buffer, [4, 5]);
+
+    assert_eq!(Transfer::transfer(&mut spi, &mut buffer), Ok(&[6, 7][..]));
+    assert_eq!(mock::spi_take_written(ISU1, CS_B), [4, 5]);
+}
+
+#[test]
+fn writes_and_reads() {
+    let mut spi = open();
+    mock::spi_inject(ISU1, CS_B, &[0xaa, 0xbb, 0xcc]);
+
+    assert_eq!(spi.write(&[0x01, 0x02]), Ok(2));
+    Write::write(&mut spi, &[0x03]).unwrap();
+    assert_eq!(mock::spi_take_written(ISU1, CS_B), [0x01, 0x02, 0x03]);
+
+    let mut buffer = [0; 2];
+    assert_eq!(spi.read(&mut buffer), Ok(2));
+    assert_eq!(buffer, [0xaa, 0xbb]);
+
+    let mut buffer = [0; 1];
+    assert_eq!(spi.write_then_read(&[0x9f], &mut buffer), Ok(2));
+    assert_eq!(mock::spi_take_written(ISU1, CS_B), [0x9f]);
+    assert_eq!(buffer, [0xcc]);
+}
+
+#[test]
+fn reports_errors() {
+    let spi = open();
+    let busy = SpiMaster::open(Isu::Isu1, ChipSelect::A, ChipSelectPolarity::ActiveLow);
+    assert_eq!(busy.err(), Some(Error::Os(EBUSY)));
+
+    mock::fail_next("z__SPIMaster_TransferSequential", EIO);
+    assert_eq!(spi.write(&[1]), Err(Error::Os(EIO)));
+    mock::fail_next("SPIMaster_WriteThenRead", EIO);
+    assert_eq!(spi.write_then_read(&[1], &mut [0]), Err(Error::Os(EIO)));
+    mock::fail_next("SPIMaster_SetBusSpeed", EIO);
+    assert_eq!(spi.set_bus_speed(1_000_000), Err(Error::Os(EIO)));
+
+    // nothing reached the device
+    assert!(mock::spi_take_written(ISU1, CS_B).is_empty());
+}
@@ -1,12 +1,38 @@
 #![allow(non_camel_case_types)]
 use alloc::format;
 use alloc::string::String;
+use alloc::vec;
+use alloc::vec::Vec;
+use core::convert::TryInto;
+use core::str;
 
 extern crate sphere_sys;
+use sphere_sys::close;
 use sphere_sys::free;
+use sphere_sys::fsync;
+use sphere_sys::ftruncate;
+use sphere_sys::lseek;
+use sphere_sys::off_t;
+use sphere_sys::Storage_DeleteMutableFile;
 use sphere_sys::Storage_GetAbsolutePathInImagePackage;
+use sphere_sys::Storage_OpenFileInImagePackage;
+use sphere_sys::Storage_OpenMutableFile;
+use sphere_sys::EINVAL;
+use sphere_sys::SEEK_CUR;
+use sphere_sys::SEEK_END;
+use sphere_sys::SEEK_SET;
 
-pub fn get_absolute_path_in_image_package(path: &str) -> Result<String, &str> {
+use crate::error::{check, check_size};
+use crate::fd::{AsRawFd, RawFd};
+use crate::shutdown::{self, Hook};
+use crate::util::crc32;
+use crate::Error;
+
+/// Where app data starts in mutable storage. The bytes before are reserved,
+/// sphere-rt keeps its crash record there.
+pub const APP_DATA_OFFSET: u64 = 512;
+
+pub fn get_absolute_path_in_image_package(path: &str) -> Result<String, Error> {
     let null_terminated = format!("{}\0", path);
 
     unsafe {
@@ -15,7 +41,7 @@ pub fn get_absolute_path_in_image_package(path: &str) -> Result<String, &str> {
             Storage_GetAbsolutePathInImagePackage(ptr as *const sphere_sys::std::os::raw::c_char);
 
         if absolute_path_c.is_null() {
-            Err("Unable to get absolute path")
+            Err(Error::last_os_error())
         } else {
             let mut absolute_path = String::new();
             let mut absolute_path_c_ptr = absolute_path_c;
@@ -30,4 +56,326 @@ pub fn get_absolute_path_in_image_package(path: &str) -> Result<String, &str> {
     }
 }
 
-// for now no support for other functions from storage.h since it's not needed right now
+/// Opens a file of the image package read-only. `path` is relative to its
+/// root, e.g. `certs/DigiCertGlobalRootCA.pem` for `approot/certs/...`.
+pub fn open_in_image_package(path: &str) -> Result<ImageFile, Error> {
+    let null_terminated = format!("{}\0", path);
+    let fd = unsafe {
+        Storage_OpenFileInImagePackage(
+            null_terminated.as_ptr() as *const sphere_sys::std::os::raw::c_char
+        )
+    };
+
+    Ok(ImageFile { fd: check(fd)? })
+}
+
+/// A read-only file from the image package, see `open_in_image_package`.
+pub struct ImageFile {
+    fd: sphere_sys::std::os::raw::c_int,
+}
+
+impl ImageFile {
+    /// Returns the number of bytes read, 0 at the end of the file.
+    pub fn read(&self, buffer: &mut [u8]) -> Result<usize, Error> {
+        check_size(unsafe {
+            sphere_sys::read(self.fd, buffer.as_mut_ptr() as *mut _, buffer.len())
+        })
+    }
+
+    /// Appends the rest of the file to `buffer`, returns the number of bytes.
+    pub fn read_to_end(&self, buffer: &mut Vec<u8>) -> Result<usize, Error> {
+        let start = buffer.len();
+        let mut chunk = [0u8; 512];
+        loop {
+            match self.read(&mut chunk)? {
+                0 => return Ok(buffer.len() - start),
+                count => buffer.extend_from_slice(&chunk[..count]),
+            }
+        }
+    }
+
+    /// Like `read_to_end`, leaves `buffer` as is unless the rest of the file
+    /// is valid UTF-8.
+    pub fn read_to_string(&self, buffer: &mut String) -> Result<usize, Error> {
+        let mut bytes = Vec::new();
+        let count = self.read_to_end(&mut bytes)?;
+        buffer.push_str(str::from_utf8(&bytes).map_err(Error::Utf8)?);
+        Ok(count)
+    }
+}
+
+impl AsRawFd for ImageFile {
+    fn as_raw_fd(&self) -> RawFd {
+        self.fd
+    }
+}
+
+impl Drop for ImageFile {
+    fn drop(&mut self) {
+        unsafe { close(self.fd) };
+    }
+}
+
+/// Where `MutableFile::seek` moves to.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum SeekFrom {
+    Start(u64),
+    End(i64),
+    Current(i64),
+}
+
+/// The app's mutable storage, a single file that persists across reboots
+/// and updates. Its size is limited by `MutableStorage` in the app manifest.
+///
+/// Every `open` starts at offset 0 with its own file position. The first
+/// `APP_DATA_OFFSET` bytes are reserved, `AtomicStore` and
+/// `settings::Settings` refuse to use them and raw writes should start after
+/// them too.
+///
+/// While open, the file is synced on `shutdown::run`.
+pub struct MutableFile {
+    fd: sphere_sys::std::os::raw::c_int,
+    // `None` only while dropping
+    sync_hook: Option<Hook>,
+}
+
+impl MutableFile {
+    pub fn open() -> Result<MutableFile, Error> {
+        let fd = check(unsafe { Storage_OpenMutableFile() })?;
+        let sync_hook = shutdown::register("storage", move || unsafe {
+            fsync(fd);
+        });
+
+        Ok(MutableFile {
+            fd,
+            sync_hook: Some(sync_hook),
+        })
+    }
+
+    /// Deletes the contents. Fails while the file is open.
+    pub fn delete() -> Result<(), Error> {
+        check(unsafe { Storage_DeleteMutableFile() }).map(|_| ())
+    }
+
+    /// Returns the number of bytes read, 0 at the end of the file.
+    pub fn read(&self, buffer: &mut [u8]) -> Result<usize, Error> {
+        check_size(unsafe {
+            sphere_sys::read(self.fd, buffer.as_mut_ptr() as *mut _, buffer.len())
+        })
+    }
+
+    /// Fills `buffer`, or fails with `Error::Incomplete` if the file ends
+    /// before.
+    pub fn read_exact(&self, buffer: &mut [u8]) -> Result<(), Error> {
+        let mut read = 0;
+        while read < buffer.len() {
+            match self.read(&mut buffer[read..])? {
+                0 => return Err(Error::Incomplete(read)),
+                count => read += count,
+            }
+        }
+        Ok(())
+    }
+
+    /// Returns the number of bytes written.
+    pub fn write(&self, data: &[u8]) -> Result<usize, Error> {
+        check_size(unsafe { sphere_sys::write(self.fd, data.as_ptr() as *const _, data.len()) })
+    }
+
+    pub fn write_all(&self, mut data: &[u8]) -> Result<(), Error> {
+        let mut written = 0;
+        while !data.is_empty() {
+            match self.write(data)? {
+                0 => return Err(Error::Incomplete(written)),
+                count => {
+                    written += count;
+                    data = &data[count..];
+                }
+            }
+        }
+        Ok(())
+    }
+
+    /// Returns the new position from the start of the file.
+    pub fn seek(&self, position: SeekFrom) -> Result<u64, Error> {
+        let (offset, whence) = match position {
+            SeekFrom::Start(offset) => (offset as off_t, SEEK_SET),
+            SeekFrom::End(offset) => (offset as off_t, SEEK_END),
+            SeekFrom::Current(offset) => (offset as off_t, SEEK_CUR),
+        };
+
+        let position = unsafe { lseek(self.fd, offset, whence as i32) };
+        if position < 0 {
+            Err(Error::last_os_error())
+        } else {
+            Ok(position as u64)
+        }
+    }
+
+    /// Cuts the file off at `len` bytes, or extends it with zeros. The file
+    /// position stays where it is.
+    pub fn truncate(&self, len: u64) -> Result<(), Error> {
+        check(unsafe { ftruncate(self.fd, len as off_t) }).map(|_| ())
+    }
+
+    /// Waits until all writes are on flash.
+    pub fn sync(&self) -> Result<(), Error> {
+        check(unsafe { fsync(self.fd) }).map(|_| ())
+    }
+}
+
+impl AsRawFd for MutableFile {
+    fn as_raw_fd(&self) -> RawFd {
+        self.fd
+    }
+}
+
+impl Drop for MutableFile {
+    fn drop(&mut self) {
+        // unregistered first, the fd may be reused once closed
+        drop(self.sync_hook.take());
+        unsafe { close(self.fd) };
+    }
+}
+
+const SLOT_MAGIC: [u8; 4] = *b"ASLT";
+const SLOT_HEADER_SIZE: usize = 16;
+
+/// Keeps a blob in a region of mutable storage so that a reset during
+/// `write` leaves either the old or the new contents, never a mix.
+///
+/// The region holds two slots of `capacity` bytes plus a small header. A
+/// write goes to the slot not holding the current contents and is synced
+/// before it counts, like writing a temporary file and renaming it. The
+/// newer of the slots with a valid checksum wins on `read`.
+#[derive(Copy, Clone, Debug)]
+pub struct AtomicStore {
+    offset: u64,
+    capacity: usize,
+}
+
+struct Slot {
+    index: u64,
+    sequence: u32,
+    data: Vec<u8>,
+}
+
+impl AtomicStore {
+    /// `offset` is where the region starts in the file, see `region_size`
+    /// for its length. Reading and writing fail with `EINVAL` if it's below
+    /// `APP_DATA_OFFSET`.
+    pub const fn new(offset: u64, capacity: usize) -> AtomicStore {
+        AtomicStore { offset, capacity }
+    }
+
+    /// Bytes of mutable storage taken from `offset` on.
+    pub const fn region_size(&self) -> u64 {
+        2 * self.slot_size()
+    }
+
+    /// The last contents written completely, if any.
+    pub fn read(&self, file: &MutableFile) -> Result<Option<Vec<u8>>, Error> {
+        self.check_region()?;
+        Ok(self.current(file)?.map(|slot| slot.data))
+    }
+
+    /// Replaces the contents. At most `capacity` bytes.
+    pub fn write(&self, file: &MutableFile, data: &[u8]) -> Result<(), Error> {
+        self.check_region()?;
+        if data.len() > self.capacity {
+            return Err(Error::Os(EINVAL as i32));
+        }
+
+        let (index, sequence) = match self.current(file)? {
+            Some(current) => (1 - current.index, current.sequence.wrapping_add(1)),
+            None => (0, 0),
+        };
+
+        let mut slot = vec![0u8; SLOT_HEADER_SIZE];
+        slot[0..4].copy_from_slice(&SLOT_MAGIC);
+        slot[4..8].copy_from_slice(&sequence.to_le_bytes());
+        slot[8..12].copy_from_slice(&(data.len() as u32).to_le_bytes());
+        slot.extend_from_slice(data);
+        let crc = slot_crc(&slot);
+        slot[12..16].copy_from_slice(&crc.to_le_bytes());
+
+        file.seek(SeekFrom::Start(self.slot_offset(index)))?;
+        file.write_all(&slot)?;
+        file.sync()
+    }
+
+    fn check_region(&self) -> Result<(), Error> {
+        if self.offset < APP_DATA_OFFSET {
+            return Err(Error::Os(EINVAL as i32));
+        }
+        Ok(())
+    }
+
+    const fn slot_size(&self) -> u64 {
+        (SLOT_HEADER_SIZE + self.capacity) as u64
+    }
+
+    fn slot_offset(&self, index: u64) -> u64 {
+        self.offset + index * self.slot_size()
+    }
+
+    fn current(&self, file: &MutableFile) -> Result<Option<Slot>, Error> {
+        let first = self.read_slot(file, 0)?;
+        let second = self.read_slot(file, 1)?;
+
+        Ok(match (first, second) {
+            (Some(first), Some(second)) => {
+                // sequence numbers wrap around
+                if (second.sequence.wrapping_sub(first.sequence) as i32) > 0 {
+                    Some(second)
+                } else {
+                    Some(first)
+                }
+            }
+            (first, second) => first.or(second),
+        })
+    }
+
+    // `None` for slots never or only partly written
+    fn read_slot(&self, file: &MutableFile, index: u64) -> Result<Option<Slot>, Error> {
+        file.seek(SeekFrom::Start(self.slot_offset(index)))?;
+
+        let mut slot = vec![0u8; SLOT_HEADER_SIZE];
+        match file.read_exact(&mut slot) {
+            Err(Error::Incomplete(_)) => return Ok(None),
+            result => result?,
+        }
+
+        let len = u32::from_le_bytes(slot[8..12].try_into().unwrap()) as usize;
+        if slot[0..4] != SLOT_MAGIC || len > self.capacity {
+            return Ok(None);
+        }
+
+        slot.resize(SLOT_HEADER_SIZE + len, 0);
+        match file.read_exact(&mut slot[SLOT_HEADER_SIZE..]) {
+            Err(Error::Incomplete(_)) => return Ok(None),
+            result => result?,
+        }
+
+        let crc = u32::from_le_bytes(slot[12..16].try_into().unwrap());
+        if slot_crc(&slot) != crc {
+            return Ok(None);
+        }
+
+        Ok(Some(Slot {
+            index,
+            sequence: u32::from_le_bytes(slot[4..8].try_into().unwrap()),
+            data: slot.split_off(SLOT_HEADER_SIZE),
+        }))
+    }
+}
+
+// over the header with the checksum field zeroed, and the data
+fn slot_crc(slot: &[u8]) -> u32 {
+    let mut unchecked = slot.to_vec();
+    unchecked[12..16].copy_from_slice(&[0; 4]);
+    crc32(&unchecked)
+}
+
+#[cfg(all(test, feature = "mock"))]
+mod tests;
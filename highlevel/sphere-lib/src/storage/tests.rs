@@ -0,0 +1,73 @@
+use alloc::vec;
+
+use sphere_sys::mock;
+use sphere_sys::mock::errno::{EACCES, EINVAL, ENOSPC};
+
+use super::{AtomicStore, MutableFile, SeekFrom, APP_DATA_OFFSET, SLOT_HEADER_SIZE};
+use crate::Error;
+
+#[test]
+fn reads_back_what_was_written() {
+    let file = MutableFile::open().unwrap();
+    file.seek(SeekFrom::Start(APP_DATA_OFFSET)).unwrap();
+    file.write_all(b"persisted").unwrap();
+    file.sync().unwrap();
+    drop(file);
+
+    assert_eq!(
+        &mock::mutable_storage()[APP_DATA_OFFSET as usize..],
+        b"persisted"
+    );
+
+    // each open starts at offset 0
+    let file = MutableFile::open().unwrap();
+    let mut buffer = vec![0; APP_DATA_OFFSET as usize + 9];
+    file.read_exact(&mut buffer).unwrap();
+    assert_eq!(&buffer[APP_DATA_OFFSET as usize..], b"persisted");
+    assert_eq!(file.read_exact(&mut [0; 1]), Err(Error::Incomplete(0)));
+}
+
+#[test]
+fn fails_without_the_capability() {
+    mock::set_mutable_storage_size(None);
+    assert_eq!(MutableFile::open().err(), Some(Error::Os(EACCES)));
+}
+
+#[test]
+fn fails_beyond_the_granted_size() {
+    mock::set_mutable_storage_size(Some(1024));
+    let file = MutableFile::open().unwrap();
+    file.seek(SeekFrom::Start(1020)).unwrap();
+
+    assert_eq!(file.write_all(&[0; 8]), Err(Error::Os(ENOSPC)));
+}
+
+#[test]
+fn keeps_the_last_complete_atomic_write() {
+    let store = AtomicStore::new(APP_DATA_OFFSET, 16);
+    let file = MutableFile::open().unwrap();
+    assert_eq!(store.read(&file), Ok(None));
+
+    store.write(&file, b"first").unwrap();
+    store.write(&file, b"second").unwrap();
+    assert_eq!(store.read(&file), Ok(Some(b"second".to_vec())));
+
+    // the third write goes to the first slot, a reset while syncing it
+    // leaves it torn
+    mock::fail_next("fsync", ENOSPC);
+    assert_eq!(store.write(&file, b"third"), Err(Error::Os(ENOSPC)));
+    let mut storage = mock::mutable_storage();
+    storage[APP_DATA_OFFSET as usize + SLOT_HEADER_SIZE] ^= 0xff;
+    mock::set_mutable_storage(&storage);
+    assert_eq!(store.read(&file), Ok(Some(b"second".to_vec())));
+}
+
+#[test]
+fn refuses_the_reserved_region() {
+    let store = AtomicStore::new(0, 16);
+    let file = MutableFile::open().unwrap();
+
+    assert_eq!(store.write(&file, b"data"), Err(Error::Os(EINVAL)));
+    assert_eq!(store.read(&file), Err(Error::Os(EINVAL)));
+    assert!(mock::mutable_storage().is_empty());
+}
@@ -0,0 +1,225 @@
+#![allow(non_camel_case_types)]
+
+use core::ops::{Add, Sub};
+
+extern crate sphere_sys;
+
+use sphere_sys::clock_gettime;
+use sphere_sys::clockid_t;
+use sphere_sys::close;
+use sphere_sys::itimerspec;
+use sphere_sys::read;
+use sphere_sys::time_t;
+use sphere_sys::timerfd_create;
+use sphere_sys::timerfd_settime;
+use sphere_sys::timespec;
+use sphere_sys::CLOCK_MONOTONIC;
+use sphere_sys::CLOCK_REALTIME;
+use sphere_sys::EAGAIN;
+use sphere_sys::TFD_CLOEXEC;
+use sphere_sys::TFD_NONBLOCK;
+
+use crate::error::check;
+use crate::fd::{AsRawFd, RawFd};
+use crate::Error;
+
+pub use core::time::Duration;
+
+/// A point in time on the monotonic clock, which starts at boot and is not
+/// affected by changes of the system time.
+#[derive(Copy, Clone, Debug, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct Instant(Duration);
+
+impl Instant {
+    pub fn now() -> Instant {
+        Instant(now(CLOCK_MONOTONIC))
+    }
+
+    /// Zero if `earlier` is later than `self`.
+    pub fn duration_since(&self, earlier: Instant) -> Duration {
+        self.0.checked_sub(earlier.0).unwrap_or_default()
+    }
+
+    pub fn elapsed(&self) -> Duration {
+        Instant::now().duration_since(*self)
+    }
+
+    /// Time since boot.
+    pub fn uptime(&self) -> Duration {
+        self.0
+    }
+}
+
+impl Add<Duration> for Instant {
+    type Output = Instant;
+
+    fn add(self, duration: Duration) -> Instant {
+        Instant(self.0 + duration)
+    }
+}
+
+impl Sub<Duration> for Instant {
+    type Output = Instant;
+
+    fn sub(self, duration: Duration) -> Instant {
+        Instant(self.0 - duration)
+    }
+}
+
+impl Sub<Instant> for Instant {
+    type Output = Duration;
+
+    fn sub(self, earlier: Instant) -> Duration {
+        self.duration_since(earlier)
+    }
+}
+
+/// Wall clock time. It jumps when the time is synced over NTP or set, so
+/// use `Instant` to measure durations.
+#[derive(Copy, Clone, Debug, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct SystemTime(Duration);
+
+pub const UNIX_EPOCH: SystemTime = SystemTime(Duration::from_secs(0));
+
+impl SystemTime {
+    pub const UNIX_EPOCH: SystemTime = UNIX_EPOCH;
+
+    pub fn now() -> SystemTime {
+        SystemTime(now(CLOCK_REALTIME))
+    }
+
+    /// Fails with how much later `earlier` is if it isn't earlier.
+    pub fn duration_since(&self, earlier: SystemTime) -> Result<Duration, Duration> {
+        self.0.checked_sub(earlier.0).ok_or_else(|| earlier.0 - self.0)
+    }
+
+    pub fn elapsed(&self) -> Result<Duration, Duration> {
+        SystemTime::now().duration_since(*self)
+    }
+}
+
+impl Add<Duration> for SystemTime {
+    type Output = SystemTime;
+
+    fn add(self, duration: Duration) -> SystemTime {
+        SystemTime(self.0 + duration)
+    }
+}
+
+impl Sub<Duration> for SystemTime {
+    type Output = SystemTime;
+
+    fn sub(self, duration: Duration) -> SystemTime {
+        SystemTime(self.0 - duration)
+    }
+}
+
+/// Blocks the thread for `duration`. In an event loop use a `Timer` instead.
+pub fn sleep(duration: Duration) {
+    unsafe {
+        // usleep only takes up to a second on some libcs
+        if duration.as_secs() > 0 {
+            sphere_sys::sleep(duration.as_secs() as u32);
+        }
+        if duration.subsec_micros() > 0 {
+            sphere_sys::usleep(duration.subsec_micros());
+        }
+    }
+}
+
+fn now(clock: u32) -> Duration {
+    let mut time = timespec {
+        tv_sec: 0,
+        tv_nsec: 0,
+    };
+    // only fails for unknown clocks
+    let result = unsafe { clock_gettime(clock as clockid_t, &mut time) };
+    debug_assert_eq!(result, 0);
+
+    Duration::new(time.tv_sec as u64, time.tv_nsec as u32)
+}
+
+/// A non-blocking timerfd on the monotonic clock. It becomes readable when
+/// it expires, so it can be registered with an `EventLoop`.
+pub struct Timer {
+    fd: RawFd,
+}
+
+impl Timer {
+    /// A disarmed timer.
+    pub fn new() -> Result<Timer, Error> {
+        let fd =
+            unsafe { timerfd_create(CLOCK_MONOTONIC as i32, (TFD_NONBLOCK | TFD_CLOEXEC) as i32) };
+
+        Ok(Timer { fd: check(fd)? })
+    }
+
+    pub fn periodic(period: Duration) -> Result<Timer, Error> {
+        let timer = Timer::new()?;
+        timer.set_periodic(period)?;
+        Ok(timer)
+    }
+
+    pub fn one_shot(delay: Duration) -> Result<Timer, Error> {
+        let timer = Timer::new()?;
+        timer.set_one_shot(delay)?;
+        Ok(timer)
+    }
+
+    /// Expires every `period`, first after one `period`. A zero period
+    /// disarms the timer.
+    pub fn set_periodic(&self, period: Duration) -> Result<(), Error> {
+        self.set(period, period)
+    }
+
+    /// Expires once after `delay`, a zero delay fires right away.
+    pub fn set_one_shot(&self, delay: Duration) -> Result<(), Error> {
+        // an all-zero value would disarm the timer instead
+        let delay = delay.max(Duration::from_nanos(1));
+        self.set(delay, Duration::from_secs(0))
+    }
+
+    pub fn disarm(&self) -> Result<(), Error> {
+        self.set(Duration::from_secs(0), Duration::from_secs(0))
+    }
+
+    /// How often the timer expired since the last call, 0 if it didn't.
+    pub fn expirations(&self) -> Result<u64, Error> {
+        let mut expirations: u64 = 0;
+        let result = unsafe { read(self.fd, &mut expirations as *mut u64 as *mut _, 8) };
+
+        match check(result as i32) {
+            Ok(_) => Ok(expirations),
+            Err(Error::Os(errno)) if errno == EAGAIN as i32 => Ok(0),
+            Err(error) => Err(error),
+        }
+    }
+
+    fn set(&self, value: Duration, interval: Duration) -> Result<(), Error> {
+        let spec = itimerspec {
+            it_interval: to_timespec(interval),
+            it_value: to_timespec(value),
+        };
+
+        check(unsafe { timerfd_settime(self.fd, 0, &spec, core::ptr::null_mut()) }).map(|_| ())
+    }
+}
+
+impl AsRawFd for Timer {
+    fn as_raw_fd(&self) -> RawFd {
+        self.fd
+    }
+}
+
+impl Drop for Timer {
+    fn drop(&mut self) {
+        unsafe { close(self.fd) };
+    }
+}
+
+pub(crate) fn to_timespec(duration: Duration) -> timespec {
+    timespec {
+        tv_sec: duration.as_secs() as time_t,
+        tv_nsec: duration.subsec_nanos() as _,
+    }
+}
@@ -1,107 +1,247 @@
-#![allow(non_camel_case_types, unused)]
+#![allow(non_camel_case_types)]
 
-use alloc::boxed::Box;
-use alloc::format;
+use embedded_hal::blocking;
+use embedded_hal::serial;
 
 extern crate sphere_sys;
 
+use sphere_sys::poll;
+use sphere_sys::pollfd;
+use sphere_sys::tcdrain;
 use sphere_sys::z__UART_Config_Base;
 use sphere_sys::z__UART_Config_v1;
 use sphere_sys::z__UART_InitConfig;
 use sphere_sys::z__UART_Open;
+use sphere_sys::UART_BlockingMode_Blocking;
+use sphere_sys::UART_BlockingMode_NonBlocking;
+use sphere_sys::UART_DataBits_Eight;
+use sphere_sys::UART_DataBits_Five;
+use sphere_sys::UART_DataBits_Seven;
+use sphere_sys::UART_DataBits_Six;
+use sphere_sys::UART_FlowControl_None;
+use sphere_sys::UART_FlowControl_RTSCTS;
+use sphere_sys::UART_FlowControl_XONXOFF;
+use sphere_sys::UART_Parity_Even;
+use sphere_sys::UART_Parity_None;
+use sphere_sys::UART_Parity_Odd;
+use sphere_sys::UART_StopBits_One;
+use sphere_sys::UART_StopBits_Two;
+use sphere_sys::EAGAIN;
+use sphere_sys::EINTR;
+use sphere_sys::POLLOUT;
 use sphere_sys::UART_STRUCTS_VERSION;
 
-pub enum Isu {
-    Isu0,
-    Isu1,
-    Isu2,
-    Isu3,
-    Isu4,
-}
+use crate::error::{check, check_size};
+use crate::fd::{AsRawFd, RawFd};
+use crate::Error;
+
+pub use crate::isu::Isu;
 
 pub struct Uart {
-    config_base: Box<z__UART_Config_Base>, // prevent leak of config
     fd: sphere_sys::std::os::raw::c_int,
 }
 
 impl Uart {
-    // might only partially write ... for convenience a write_all would be nice
-    pub fn write(&self, data: &[u8]) -> isize {
-        unsafe { sphere_sys::write(self.fd, data.as_ptr() as *const _, data.len()) }
+    pub fn open(isu: Isu, config: UartConfig) -> Result<Uart, Error> {
+        let mut ffi_config = z__UART_Config_v1 {
+            z__magicAndVersion: 0,
+            baudRate: 0,
+            blockingMode: 0,
+            dataBits: 0,
+            parity: 0,
+            stopBits: 0,
+            flowControl: 0,
+        };
+        // the v1 config starts with the base, Applibs checks the version
+        unsafe {
+            z__UART_InitConfig(
+                &mut ffi_config as *mut _ as *mut z__UART_Config_Base,
+                UART_STRUCTS_VERSION,
+            )
+        };
+
+        ffi_config.baudRate = config.baud_rate;
+        ffi_config.blockingMode = match config.blocking_mode {
+            true => UART_BlockingMode_Blocking,
+            false => UART_BlockingMode_NonBlocking,
+        };
+        ffi_config.dataBits = match config.data_bits {
+            DataBits::Five => UART_DataBits_Five,
+            DataBits::Six => UART_DataBits_Six,
+            DataBits::Seven => UART_DataBits_Seven,
+            DataBits::Eight => UART_DataBits_Eight,
+        };
+        ffi_config.parity = match config.parity {
+            Parity::None => UART_Parity_None,
+            Parity::Even => UART_Parity_Even,
+            Parity::Odd => UART_Parity_Odd,
+        };
+        ffi_config.stopBits = match config.stop_bits {
+            StopBits::One => UART_StopBits_One,
+            StopBits::Two => UART_StopBits_Two,
+        };
+        ffi_config.flowControl = match config.flow_control {
+            UartFlowControl::None => UART_FlowControl_None,
+            UartFlowControl::RTSCTS => UART_FlowControl_RTSCTS,
+            UartFlowControl::XONXOFF => UART_FlowControl_XONXOFF,
+        };
+
+        let fd = unsafe { z__UART_Open(isu.id(), &ffi_config as *const _ as *const _) };
+
+        Ok(Uart { fd: check(fd)? })
+    }
+
+    /// Might only write part of `data`, see `write_all`.
+    pub fn write(&self, data: &[u8]) -> Result<usize, Error> {
+        check_size(unsafe { sphere_sys::write(self.fd, data.as_ptr() as *const _, data.len()) })
+    }
+
+    /// Writes all of `data`. On a non-blocking UART this waits for the
+    /// driver to take the rest, blocking the thread.
+    pub fn write_all(&self, mut data: &[u8]) -> Result<(), Error> {
+        let mut written = 0;
+        while !data.is_empty() {
+            match self.write(data) {
+                Ok(0) => return Err(Error::Incomplete(written)),
+                Ok(count) => {
+                    written += count;
+                    data = &data[count..];
+                }
+                Err(error) if error == Error::Os(EAGAIN as i32) => self.wait_writable()?,
+                Err(error) => return Err(error),
+            }
+        }
+        Ok(())
+    }
+
+    pub fn read(&self, buffer: &mut [u8]) -> Result<usize, Error> {
+        check_size(unsafe {
+            sphere_sys::read(self.fd, buffer.as_mut_ptr() as *mut _, buffer.len())
+        })
+    }
+
+    /// Waits until everything written has been sent, blocking the thread
+    /// even on a non-blocking UART.
+    pub fn flush(&self) -> Result<(), Error> {
+        check(unsafe { tcdrain(self.fd) }).map(|_| ())
+    }
+
+    /// Like dropping the `Uart`, but reports a failing `close`.
+    pub fn close(self) -> Result<(), Error> {
+        let fd = self.fd;
+        core::mem::forget(self);
+        check(unsafe { sphere_sys::close(fd) }).map(|_| ())
+    }
+
+    fn wait_writable(&self) -> Result<(), Error> {
+        let mut fds = pollfd {
+            fd: self.fd,
+            events: POLLOUT as i16,
+            revents: 0,
+        };
+        match check(unsafe { poll(&mut fds, 1, -1) }) {
+            Err(Error::Os(errno)) if errno == EINTR as i32 => Ok(()),
+            result => result.map(|_| ()),
+        }
+    }
+}
+
+impl AsRawFd for Uart {
+    fn as_raw_fd(&self) -> RawFd {
+        self.fd
+    }
+}
+
+impl Drop for Uart {
+    fn drop(&mut self) {
+        unsafe { sphere_sys::close(self.fd) };
     }
+}
 
-    pub fn read(&self, buffer: &mut [u8]) -> isize {
-        unsafe { sphere_sys::read(self.fd, buffer.as_mut_ptr() as *mut _, buffer.len()) }
+// A non-blocking UART reports an empty receive or full transmit buffer as EAGAIN.
+fn would_block(error: Error) -> nb::Error<Error> {
+    if error == Error::Os(EAGAIN as i32) {
+        nb::Error::WouldBlock
+    } else {
+        nb::Error::Other(error)
     }
 }
 
+impl serial::Read<u8> for Uart {
+    type Error = Error;
+
+    fn read(&mut self) -> nb::Result<u8, Self::Error> {
+        let mut byte = 0;
+        match Uart::read(self, core::slice::from_mut(&mut byte)).map_err(would_block)? {
+            1 => Ok(byte),
+            _ => Err(nb::Error::WouldBlock),
+        }
+    }
+}
+
+impl serial::Write<u8> for Uart {
+    type Error = Error;
+
+    fn write(&mut self, word: u8) -> nb::Result<(), Self::Error> {
+        match Uart::write(self, &[word]).map_err(would_block)? {
+            1 => Ok(()),
+            _ => Err(nb::Error::WouldBlock),
+        }
+    }
+
+    fn flush(&mut self) -> nb::Result<(), Self::Error> {
+        Ok(Uart::flush(self)?)
+    }
+}
+
+impl blocking::serial::write::Default<u8> for Uart {}
+
 pub enum UartFlowControl {
     None,
     RTSCTS,
     XONXOFF,
 }
 
+pub enum DataBits {
+    Five,
+    Six,
+    Seven,
+    Eight,
+}
+
+pub enum Parity {
+    None,
+    Even,
+    Odd,
+}
+
+pub enum StopBits {
+    One,
+    Two,
+}
+
 pub struct UartConfig {
     pub baud_rate: u32,
     pub blocking_mode: bool,
-    pub data_bits: u8,
-    pub parity: u8,
-    pub stop_bits: u8,
+    pub data_bits: DataBits,
+    pub parity: Parity,
+    pub stop_bits: StopBits,
     pub flow_control: UartFlowControl,
 }
 
-pub fn uart_open(isu: Isu, config: UartConfig) -> Result<Uart, Uart> {
-    // for MT3620 only
-    let isu_id = match isu {
-        Isu::Isu0 => 4,
-        Isu::Isu1 => 5,
-        Isu::Isu2 => 6,
-        Isu::Isu3 => 7,
-        Isu::Isu4 => 8,
-    };
-
-    unsafe {
-        let mut ffi_config = z__UART_Config_v1 {
-            z__magicAndVersion: UART_STRUCTS_VERSION,
-            baudRate: 0,
-            blockingMode: 0,
-            dataBits: 0,
-            parity: 0,
-            stopBits: 0,
-            flowControl: 0,
-        };
-
-        let mut fii_config_base: Box<z__UART_Config_Base> =
-            Box::from_raw((&mut ffi_config as *mut _) as *mut z__UART_Config_Base);
-
-        z__UART_InitConfig(&mut (*fii_config_base), UART_STRUCTS_VERSION);
-
-        ffi_config.baudRate = config.baud_rate;
-        ffi_config.blockingMode = match config.blocking_mode {
-            true => 1,
-            false => 0,
-        };
-        ffi_config.dataBits = config.data_bits;
-        ffi_config.parity = config.parity;
-        ffi_config.stopBits = config.stop_bits;
-        ffi_config.flowControl = match config.flow_control {
-            UartFlowControl::None => 0,
-            UartFlowControl::RTSCTS => 1,
-            UartFlowControl::XONXOFF => 2,
-        };
-
-        let descriptor = z__UART_Open(isu_id, &mut (*fii_config_base));
-
-        if descriptor < 0 {
-            Err(Uart {
-                config_base: fii_config_base,
-                fd: descriptor,
-            })
-        } else {
-            Ok(Uart {
-                config_base: fii_config_base,
-                fd: descriptor,
-            })
+/// 115200 baud 8N1 without flow control, non-blocking.
+impl Default for UartConfig {
+    fn default() -> UartConfig {
+        UartConfig {
+            baud_rate: 115200,
+            blocking_mode: false,
+            data_bits: DataBits::Eight,
+            parity: Parity::None,
+            stop_bits: StopBits::One,
+            flow_control: UartFlowControl::None,
         }
     }
 }
+
+#[cfg(all(test, feature = "mock"))]
+mod tests;
@@ -0,0 +1,76 @@
+use alloc::vec::Vec;
+
+use embedded_hal::serial;
+use sphere_sys::mock;
+use sphere_sys::mock::errno::{EBUSY, EIO};
+
+use super::{Uart, UartConfig};
+use crate::isu::Isu;
+use crate::Error;
+
+// the UART id of ISU0
+const ISU0: i32 = 4;
+
+fn call_names() -> Vec<&'static str> {
+    mock::calls().into_iter().map(|call| call.name).collect()
+}
+
+#[test]
+fn writes_and_reads_bytes() {
+    let uart = Uart::open(Isu::Isu0, UartConfig::default()).unwrap();
+
+    uart.write_all(b"hello").unwrap();
+    uart.flush().unwrap();
+    assert_eq!(mock::uart_take_written(ISU0), b"hello");
+
+    mock::uart_inject(ISU0, b"world");
+    let mut buffer = [0; 8];
+    assert_eq!(uart.read(&mut buffer), Ok(5));
+    assert_eq!(&buffer[..5], b"world");
+}
+
+#[test]
+fn reports_an_empty_receive_buffer_as_would_block() {
+    let mut uart = Uart::open(Isu::Isu0, UartConfig::default()).unwrap();
+    assert_eq!(serial::Read::read(&mut uart), Err(nb::Error::WouldBlock));
+
+    mock::uart_inject(ISU0, b"x");
+    assert_eq!(serial::Read::read(&mut uart), Ok(b'x'));
+    serial::Write::write(&mut uart, b'y').unwrap();
+    assert_eq!(mock::uart_take_written(ISU0), b"y");
+}
+
+#[test]
+fn waits_for_the_driver_when_writing_all() {
+    let uart = Uart::open(Isu::Isu0, UartConfig::default()).unwrap();
+    mock::fail_next("write", sphere_sys::EAGAIN as i32);
+    mock::clear_calls();
+
+    uart.write_all(b"data").unwrap();
+    assert_eq!(call_names(), ["write", "poll", "write"]);
+    assert_eq!(mock::uart_take_written(ISU0), b"data");
+}
+
+#[test]
+fn fails_to_open_a_uart_twice() {
+    let _uart = Uart::open(Isu::Isu0, UartConfig::default()).unwrap();
+    let result = Uart::open(Isu::Isu0, UartConfig::default());
+    assert_eq!(result.err(), Some(Error::Os(EBUSY)));
+}
+
+#[test]
+fn reports_failing_writes() {
+    let uart = Uart::open(Isu::Isu0, UartConfig::default()).unwrap();
+    mock::fail_next("write", EIO);
+
+    assert_eq!(uart.write_all(b"lost"), Err(Error::Os(EIO)));
+    assert!(mock::uart_take_written(ISU0).is_empty());
+}
+
+#[test]
+fn releases_the_uart_on_drop() {
+    let uart = Uart::open(Isu::Isu0, UartConfig::default()).unwrap();
+    drop(uart);
+    assert_eq!(mock::call_count("close"), 1);
+    Uart::open(Isu::Isu0, UartConfig::default()).unwrap();
+}
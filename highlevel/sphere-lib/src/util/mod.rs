@@ -1,15 +1,99 @@
 #![allow(non_camel_case_types)]
 
+use alloc::string::String;
+use core::cell::UnsafeCell;
+use core::fmt::Write;
+use core::hint::spin_loop;
+use core::sync::atomic::{AtomicBool, Ordering};
+
 extern crate sphere_sys;
 
+#[deprecated(note = "use `time::sleep`")]
 pub fn sleep(seconds: u32) {
     unsafe {
         sphere_sys::sleep(seconds);
     }
 }
 
+#[deprecated(note = "use `time::sleep`")]
 pub fn usleep(microseconds: u32) {
     unsafe {
         sphere_sys::usleep(microseconds);
     }
 }
+
+/// errno of the last failing call on this thread.
+pub fn errno() -> i32 {
+    unsafe { *sphere_sys::__errno_location() }
+}
+
+/// CRC-32 (IEEE 802.3, as used by zlib), for checking records in storage.
+pub fn crc32(data: &[u8]) -> u32 {
+    let mut crc = !0u32;
+    for byte in data {
+        crc ^= *byte as u32;
+        for _ in 0..8 {
+            let mask = (crc & 1).wrapping_neg();
+            crc = (crc >> 1) ^ (0xEDB8_8320 & mask);
+        }
+    }
+    !crc
+}
+
+/// Appends `value` as a quoted JSON string.
+pub fn push_json_string(json: &mut String, value: &str) {
+    json.push('"');
+    for c in value.chars() {
+        match c {
+            '"' => json.push_str("\\\""),
+            '\\' => json.push_str("\\\\"),
+            '\n' => json.push_str("\\n"),
+            c if (c as u32) < 0x20 => {
+                let _ = write!(json, "\\u{:04x}", c as u32);
+            }
+            c => json.push(c),
+        }
+    }
+    json.push('"');
+}
+
+/// A lock for globals that have to be `Sync`, e.g. for `log`. Waiting in
+/// `with` deadlocks if the lock is taken again from within, `try_with` is
+/// for where that can happen.
+pub(crate) struct SpinLock<T> {
+    locked: AtomicBool,
+    value: UnsafeCell<T>,
+}
+
+unsafe impl<T: Send> Sync for SpinLock<T> {}
+
+impl<T> SpinLock<T> {
+    pub(crate) const fn new(value: T) -> SpinLock<T> {
+        SpinLock {
+            locked: AtomicBool::new(false),
+            value: UnsafeCell::new(value),
+        }
+    }
+
+    pub(crate) fn with<R>(&self, f: impl FnOnce(&mut T) -> R) -> R {
+        while self.locked.swap(true, Ordering::Acquire) {
+            spin_loop();
+        }
+        self.run_locked(f)
+    }
+
+    /// `None` if the lock is taken.
+    pub(crate) fn try_with<R>(&self, f: impl FnOnce(&mut T) -> R) -> Option<R> {
+        if self.locked.swap(true, Ordering::Acquire) {
+            return None;
+        }
+        Some(self.run_locked(f))
+    }
+
+    // runs `f` with the lock taken, then releases it
+    fn run_locked<R>(&self, f: impl FnOnce(&mut T) -> R) -> R {
+        let result = f(unsafe { &mut *self.value.get() });
+        self.locked.store(false, Ordering::Release);
+        result
+    }
+}
@@ -2,65 +2,249 @@
 
 extern crate sphere_sys;
 
-use sphere_sys::*;
+use sphere_sys::_exit;
+use sphere_sys::itimerspec;
+use sphere_sys::sigevent;
+use sphere_sys::signal;
+use sphere_sys::std::os::raw::c_int;
+use sphere_sys::timer_create;
+use sphere_sys::timer_delete;
+use sphere_sys::timer_settime;
+use sphere_sys::timer_t;
+use sphere_sys::CLOCK_MONOTONIC;
+use sphere_sys::EINVAL;
+use sphere_sys::SIGALRM;
+use sphere_sys::SIGEV_SIGNAL;
 
-use alloc::boxed::Box;
+use alloc::rc::Rc;
+use alloc::vec::Vec;
 
+use core::cell::{Cell, RefCell};
 use core::mem::MaybeUninit;
+use core::ptr;
+use core::time::Duration;
 
-#[derive(Copy, Clone)]
+use crate::error::check;
+use crate::eventloop::{EventLoop, IoEvents};
+use crate::fd::{AsRawFd, RawFd};
+use crate::time::{to_timespec, Instant, Timer};
+use crate::Error;
+
+/// Exit code of an app ended by the watchdog ('W'), so the crash is
+/// distinguishable from the app's own exit codes.
+pub const EXIT_CODE: i32 = 0x57;
+
+/// A POSIX timer raising `SIGALRM` after `timeout`, unless it's reset before.
+/// On expiry the app exits with `EXIT_CODE` right away, use `warn_on` to get
+/// a chance to flush logs or report the problem before.
+///
+/// There's only one `SIGALRM` handler, so an app should create just one
+/// `Watchdog`. Software watchdogs created with `register` keep the timer
+/// alive after the `Watchdog` is dropped.
 pub struct Watchdog {
-    timer: *mut timer_t,
-    timeout: usize,
+    inner: Rc<Inner>,
+}
+
+struct Inner {
+    timer: timer_t,
+    timeout: Duration,
+    tasks: RefCell<Vec<Rc<Task>>>,
+    warning: RefCell<Option<Warning>>,
+}
+
+// a timerfd expiring `margin` before the watchdog, re-armed with it
+struct Warning {
+    timer: Rc<Timer>,
+    margin: Duration,
+}
+
+struct Task {
+    name: &'static str,
+    timeout: Duration,
+    last_fed: Cell<Instant>,
+}
+
+/// Watches a single task. The timer is only reset while every registered
+/// software watchdog has been fed within its own timeout.
+pub struct SoftwareWatchdog {
+    watchdog: Rc<Inner>,
+    task: Rc<Task>,
 }
 
 impl Watchdog {
-    pub fn create(seconds: usize) -> Watchdog {
+    pub fn create(timeout: Duration) -> Result<Watchdog, Error> {
         unsafe {
-            let watchdog_timer = Box::<timer_t>::new_uninit().assume_init();
-            let watchdog_timer = Box::into_raw(watchdog_timer);
-
-            let watchdog_interval = itimerspec {
-                it_interval: timespec {
-                    tv_sec: seconds as i32,
-                    tv_nsec: 0,
-                },
-                it_value: timespec {
-                    tv_sec: seconds as i32,
-                    tv_nsec: 0,
-                },
+            let mut timer: timer_t = ptr::null_mut();
+            let mut alarm_event = MaybeUninit::<sigevent>::zeroed().assume_init();
+            alarm_event.sigev_notify = SIGEV_SIGNAL as i32;
+            alarm_event.sigev_signo = SIGALRM as i32;
+
+            check(timer_create(
+                CLOCK_MONOTONIC as i32,
+                &mut alarm_event,
+                &mut timer,
+            ))?;
+
+            signal(SIGALRM as i32, Some(on_expiry));
+
+            // from here on Drop deletes the timer
+            let watchdog = Watchdog {
+                inner: Rc::new(Inner {
+                    timer,
+                    timeout,
+                    tasks: RefCell::new(Vec::new()),
+                    warning: RefCell::new(None),
+                }),
             };
-            let mut alarm_event = MaybeUninit::<sigevent>::zeroed().as_mut_ptr();
-            (*alarm_event).sigev_notify = SIGEV_SIGNAL as i32;
-            (*alarm_event).sigev_signo = SIGALRM as i32;
-            (*alarm_event).sigev_value.sival_ptr = *watchdog_timer;
-
-            timer_create(CLOCK_MONOTONIC as i32, alarm_event, watchdog_timer);
-            timer_settime(
-                *watchdog_timer,
-                0,
-                &watchdog_interval,
-                core::ptr::null_mut(),
-            );
-
-            Watchdog {
-                timer: watchdog_timer,
-                timeout: seconds,
+
+            watchdog.inner.arm()?;
+
+            Ok(watchdog)
+        }
+    }
+
+    /// Restarts the timer, if all software watchdogs have been fed in time.
+    pub fn reset(&self) -> Result<(), Error> {
+        self.inner.reset()
+    }
+
+    /// Calls `callback` from `event_loop` once the watchdog wasn't reset for
+    /// `timeout - margin`, i.e. `margin` before it expires, with the name of
+    /// a starving software watchdog if there is one. Replaces an earlier
+    /// warning, which is unregistered from `event_loop` and never fires
+    /// again. Unregister the returned fd to stop.
+    ///
+    /// Only runs if the event loop still does, a blocked main thread is
+    /// ended without a warning.
+    pub fn warn_on<'a, F>(
+        &self,
+        event_loop: &EventLoop<'a>,
+        margin: Duration,
+        mut callback: F,
+    ) -> Result<RawFd, Error>
+    where
+        F: FnMut(Option<&'static str>) + 'a,
+    {
+        if margin >= self.inner.timeout {
+            return Err(Error::Os(EINVAL as i32));
+        }
+
+        let timer = Rc::new(Timer::one_shot(self.inner.timeout - margin)?);
+        let fd = timer.as_raw_fd();
+        // owns the timer, so it stays open while registered
+        let on_warning = {
+            let timer = timer.clone();
+            let inner = Rc::downgrade(&self.inner);
+            move |_: &EventLoop<'a>, _events: IoEvents| {
+                if let Ok(expirations) = timer.expirations() {
+                    if expirations > 0 {
+                        callback(inner.upgrade().and_then(|inner| inner.starving()));
+                    }
+                }
             }
+        };
+
+        event_loop.register_io(&fd, IoEvents::INPUT, on_warning)?;
+        let previous = self
+            .inner
+            .warning
+            .borrow_mut()
+            .replace(Warning { timer, margin });
+
+        // disarmed as well, in case the app already unregistered it or it
+        // was registered with another event loop
+        if let Some(previous) = previous {
+            let _ = previous.timer.disarm();
+            let _ = event_loop.unregister(previous.timer.as_raw_fd());
+        }
+        Ok(fd)
+    }
+
+    /// Adds a software watchdog that has to be fed at least every `timeout`.
+    /// It starts out fed.
+    pub fn register(&self, name: &'static str, timeout: Duration) -> SoftwareWatchdog {
+        let task = Rc::new(Task {
+            name,
+            timeout,
+            last_fed: Cell::new(Instant::now()),
+        });
+        self.inner.tasks.borrow_mut().push(task.clone());
+
+        SoftwareWatchdog {
+            watchdog: self.inner.clone(),
+            task,
+        }
+    }
+}
+
+impl SoftwareWatchdog {
+    pub fn name(&self) -> &'static str {
+        self.task.name
+    }
+
+    /// Marks the task as alive and resets the timer if no other task is
+    /// starving.
+    pub fn feed(&self) -> Result<(), Error> {
+        self.task.last_fed.set(Instant::now());
+        self.watchdog.reset()
+    }
+}
+
+impl Drop for SoftwareWatchdog {
+    fn drop(&mut self) {
+        self.watchdog
+            .tasks
+            .borrow_mut()
+            .retain(|other| !Rc::ptr_eq(other, &self.task));
+    }
+}
+
+impl Inner {
+    fn reset(&self) -> Result<(), Error> {
+        match self.starving() {
+            Some(_) => Ok(()),
+            None => self.arm(),
         }
     }
 
-    pub fn reset(&self) {
+    // the first software watchdog that wasn't fed within its timeout
+    fn starving(&self) -> Option<&'static str> {
+        let now = Instant::now();
+        self.tasks
+            .borrow()
+            .iter()
+            .find(|task| now.duration_since(task.last_fed.get()) > task.timeout)
+            .map(|task| task.name)
+    }
+
+    fn arm(&self) -> Result<(), Error> {
         let watchdog_interval = itimerspec {
-            it_interval: timespec {
-                tv_sec: self.timeout as i32,
-                tv_nsec: 0,
-            },
-            it_value: timespec {
-                tv_sec: self.timeout as i32,
-                tv_nsec: 0,
-            },
+            it_interval: to_timespec(self.timeout),
+            it_value: to_timespec(self.timeout),
         };
-        unsafe { timer_settime(*(self.timer), 0, &watchdog_interval, core::ptr::null_mut()) };
+
+        check(unsafe { timer_settime(self.timer, 0, &watchdog_interval, ptr::null_mut()) })?;
+
+        match &*self.warning.borrow() {
+            Some(warning) => warning.timer.set_one_shot(self.timeout - warning.margin),
+            None => Ok(()),
+        }
     }
 }
+
+impl Drop for Inner {
+    fn drop(&mut self) {
+        unsafe {
+            timer_delete(self.timer);
+            signal(SIGALRM as i32, None);
+        }
+    }
+}
+
+// nothing that isn't async-signal-safe, the app may be anywhere
+unsafe extern "C" fn on_expiry(_signo: c_int) {
+    _exit(EXIT_CODE);
+}
+
+#[cfg(all(test, feature = "mock"))]
+mod tests;
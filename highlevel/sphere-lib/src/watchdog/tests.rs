@@ -0,0 +1,122 @@
+use alloc::rc::Rc;
+use alloc::vec::Vec;
+use core::cell::RefCell;
+use core::time::Duration;
+
+use sphere_sys::mock;
+use sphere_sys::mock::errno::EINVAL;
+
+use super::Watchdog;
+use crate::eventloop::EventLoop;
+use crate::time::sleep;
+use crate::Error;
+
+const TIMEOUT: Duration = Duration::from_secs(10);
+
+// seconds until the POSIX timers expire
+fn armed() -> Vec<i64> {
+    mock::armed_timers()
+        .iter()
+        .map(|timer| timer.it_value.tv_sec as i64)
+        .collect()
+}
+
+#[test]
+fn arms_the_timer_until_dropped() {
+    let watchdog = Watchdog::create(TIMEOUT).unwrap();
+    assert_eq!(armed(), [10]);
+    assert_eq!(mock::call_count("signal"), 1);
+
+    drop(watchdog);
+    assert_eq!(armed(), []);
+    assert_eq!(mock::call_count("timer_delete"), 1);
+}
+
+#[test]
+fn rearms_on_reset() {
+    let watchdog = Watchdog::create(TIMEOUT).unwrap();
+    mock::clear_calls();
+
+    watchdog.reset().unwrap();
+    assert_eq!(mock::call_count("timer_settime"), 1);
+}
+
+#[test]
+fn holds_off_resets_while_a_task_starves() {
+    let watchdog = Watchdog::create(TIMEOUT).unwrap();
+    let sensor = watchdog.register("sensor", Duration::from_secs(1));
+    let network = watchdog.register("network", Duration::from_secs(5));
+
+    sleep(Duration::from_secs(2));
+    mock::clear_calls();
+    network.feed().unwrap();
+    watchdog.reset().unwrap();
+    assert_eq!(mock::call_count("timer_settime"), 0);
+
+    sensor.feed().unwrap();
+    assert_eq!(mock::call_count("timer_settime"), 1);
+
+    // a dropped task doesn't starve
+    sleep(Duration::from_secs(2));
+    drop(sensor);
+    watchdog.reset().unwrap();
+    assert_eq!(mock::call_count("timer_settime"), 2);
+}
+
+#[test]
+fn warns_with_the_starving_task() {
+    let event_loop = EventLoop::new().unwrap();
+    let watchdog = Watchdog::create(TIMEOUT).unwrap();
+    let _sensor = watchdog.register("sensor", Duration::from_secs(1));
+    let warnings = Rc::new(RefCell::new(Vec::new()));
+
+    let received = warnings.clone();
+    watchdog
+        .warn_on(&event_loop, Duration::from_secs(2), move |starving| {
+            received.borrow_mut().push(starving)
+        })
+        .unwrap();
+
+    assert_eq!(event_loop.run_once(None), Ok(1));
+    assert_eq!(mock::monotonic_ns(), 8_000_000_000);
+    assert_eq!(*warnings.borrow(), [Some("sensor")]);
+}
+
+#[test]
+fn replaces_an_earlier_warning() {
+    let event_loop = EventLoop::new().unwrap();
+    let watchdog = Watchdog::create(TIMEOUT).unwrap();
+    let warnings = Rc::new(RefCell::new(Vec::new()));
+
+    let received = warnings.clone();
+    watchdog
+        .warn_on(&event_loop, Duration::from_secs(1), move |_| {
+            received.borrow_mut().push("replaced")
+        })
+        .unwrap();
+    let received = warnings.clone();
+    watchdog
+        .warn_on(&event_loop, Duration::from_secs(3), move |_| {
+            received.borrow_mut().push("current")
+        })
+        .unwrap();
+    assert_eq!(mock::armed_timerfds().len(), 1);
+
+    // past the expiry of both timers
+    assert_eq!(event_loop.run_once(None), Ok(1));
+    assert_eq!(event_loop.run_once(Some(Duration::from_secs(5))), Ok(0));
+    assert_eq!(*warnings.borrow(), ["current"]);
+
+    // the current warning is re-armed on reset
+    watchdog.reset().unwrap();
+    assert_eq!(mock::armed_timerfds().len(), 1);
+}
+
+#[test]
+fn rejects_margins_beyond_the_timeout() {
+    let event_loop = EventLoop::new().unwrap();
+    let watchdog = Watchdog::create(TIMEOUT).unwrap();
+
+    let result = watchdog.warn_on(&event_loop, TIMEOUT, |_| {});
+    assert_eq!(result, Err(Error::Os(EINVAL)));
+}
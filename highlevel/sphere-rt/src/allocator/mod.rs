@@ -1,8 +1,25 @@
 #![allow(non_camel_case_types)]
 
-use core::alloc::{GlobalAlloc, Layout};
+#[cfg(not(feature = "pool-alloc"))]
+use core::alloc::GlobalAlloc;
+use core::alloc::Layout;
+use core::fmt::Write;
 use core::ptr;
 
+extern crate sphere_sys;
+use sphere_sys::exit;
+
+use sphere_lib::logging::DebugWriter;
+
+#[cfg(feature = "alloc-stats")]
+use crate::memory;
+
+#[cfg(feature = "pool-alloc")]
+pub(crate) mod pool;
+
+/// Exit code of an app that ran out of memory ('M').
+pub const EXIT_CODE: i32 = 0x4d;
+
 #[repr(u8)]
 pub enum c_void {
     // Two dummy variants so the #[repr] attribute can be used.
@@ -21,24 +38,53 @@ extern "C" {
     pub fn posix_memalign(memptr: *mut *mut c_void, align: size_t, size: size_t) -> c_int;
 }
 
+#[cfg(not(feature = "pool-alloc"))]
 pub struct MyAllocator;
 
 const MIN_ALIGN: usize = 8;
 
+#[cfg(not(feature = "pool-alloc"))]
 unsafe impl GlobalAlloc for MyAllocator {
     unsafe fn alloc(&self, layout: Layout) -> *mut u8 {
-        if layout.align() <= MIN_ALIGN && layout.align() <= layout.size() {
-            malloc(layout.size()) as *mut u8
-        } else {
-            aligned_malloc(&layout)
-        }
+        let ptr = sys_alloc(&layout);
+        record_alloc(ptr, &layout);
+        ptr
     }
 
-    unsafe fn dealloc(&self, ptr: *mut u8, _layout: Layout) {
+    unsafe fn dealloc(&self, ptr: *mut u8, layout: Layout) {
         free(ptr as *mut c_void);
+        record_dealloc(&layout);
+    }
+}
+
+unsafe fn sys_alloc(layout: &Layout) -> *mut u8 {
+    if layout.align() <= MIN_ALIGN && layout.align() <= layout.size() {
+        malloc(layout.size()) as *mut u8
+    } else {
+        aligned_malloc(layout)
     }
 }
 
+#[cfg(feature = "alloc-stats")]
+fn record_alloc(ptr: *mut u8, layout: &Layout) {
+    if ptr.is_null() {
+        memory::record_failure();
+    } else {
+        memory::record_alloc(layout.size());
+    }
+}
+
+#[cfg(not(feature = "alloc-stats"))]
+fn record_alloc(_ptr: *mut u8, _layout: &Layout) {}
+
+#[cfg(feature = "alloc-stats")]
+fn record_dealloc(layout: &Layout) {
+    memory::record_dealloc(layout.size());
+}
+
+#[cfg(not(feature = "alloc-stats"))]
+fn record_dealloc(_layout: &Layout) {}
+
 unsafe fn aligned_malloc(layout: &Layout) -> *mut u8 {
     let mut out = ptr::null_mut();
     let ret = posix_memalign(&mut out, layout.align(), layout.size());
@@ -49,7 +95,41 @@ unsafe fn aligned_malloc(layout: &Layout) -> *mut u8 {
     }
 }
 
+// must not allocate, like everything it calls
 #[alloc_error_handler]
-fn alloc_error(_layout: Layout) -> ! {
-    loop {}
+fn alloc_error(layout: Layout) -> ! {
+    let mut writer = DebugWriter::new();
+    let _ = write!(
+        writer,
+        "memory allocation of {} bytes (align {}) failed",
+        layout.size(),
+        layout.align()
+    );
+
+    #[cfg(feature = "pool-alloc")]
+    {
+        let stats = pool::pool_stats();
+        let _ = write!(
+            writer,
+            ", pools {} of {} bytes, {} from malloc, budget {}",
+            stats.carved, stats.region, stats.fallback, stats.budget
+        );
+    }
+
+    #[cfg(feature = "alloc-stats")]
+    {
+        let stats = memory::stats();
+        let _ = write!(
+            writer,
+            ", {} bytes in {} allocations, peak {}",
+            stats.current,
+            stats.live(),
+            stats.peak
+        );
+    }
+
+    let _ = writeln!(writer);
+    drop(writer);
+
+    unsafe { exit(EXIT_CODE) }
 }
@@ -0,0 +1,230 @@
+//! Size-class pools carved from a static region, see `memory::init_pool`.
+//!
+//! The region is handed out in pages, each split into blocks of one size
+//! class. Freed blocks go back to the free list of their class and are never
+//! returned to the region, so the heap can't fragment. What doesn't fit a
+//! class, or no longer fits the region, comes from `malloc`.
+//!
+//! Blocks freed while the pools are locked, i.e. by a signal handler
+//! interrupting an allocation, are kept on a separate lock-free list and
+//! handed back to their class by a later allocation.
+
+use core::alloc::{GlobalAlloc, Layout};
+use core::ptr;
+use core::sync::atomic::{AtomicBool, AtomicPtr, AtomicUsize, Ordering};
+
+use super::{c_void, free, record_alloc, record_dealloc, sys_alloc};
+
+const PAGE_SIZE: usize = 4096;
+const CLASSES: [usize; 8] = [16, 32, 64, 128, 256, 512, 1024, 2048];
+// blocks are aligned to their size if pages are aligned to the largest one
+const REGION_ALIGN: usize = 2048;
+
+/// Uses the pools once `memory::init_pool` was called, `malloc` before.
+pub struct PoolAllocator;
+
+/// See `memory::pool_stats`.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub struct PoolStats {
+    /// Usable bytes of the region passed to `init_pool`.
+    pub region: usize,
+    /// Bytes of the region handed to the size classes so far.
+    pub carved: usize,
+    /// Bytes currently allocated with `malloc`.
+    pub fallback: usize,
+    /// Limit of `carved` plus `fallback`.
+    pub budget: usize,
+}
+
+struct Block {
+    next: *mut Block,
+}
+
+// a block freed while `POOLS` was locked, fits the smallest class
+struct Deferred {
+    next: *mut Deferred,
+    class: usize,
+}
+
+struct Pools {
+    next_page: usize,
+    free: [*mut Block; CLASSES.len()],
+}
+
+// guards `POOLS`, which isn't waited for: a signal handler allocating while
+// the main thread holds it would deadlock
+static LOCKED: AtomicBool = AtomicBool::new(false);
+static mut POOLS: Pools = Pools {
+    next_page: 0,
+    free: [ptr::null_mut(); CLASSES.len()],
+};
+static DEFERRED: AtomicPtr<Deferred> = AtomicPtr::new(ptr::null_mut());
+
+// the usable part of the region, empty until `init_pool`
+static START: AtomicUsize = AtomicUsize::new(0);
+static END: AtomicUsize = AtomicUsize::new(0);
+
+static REGION: AtomicUsize = AtomicUsize::new(0);
+static CARVED: AtomicUsize = AtomicUsize::new(0);
+static FALLBACK: AtomicUsize = AtomicUsize::new(0);
+static BUDGET: AtomicUsize = AtomicUsize::new(usize::MAX);
+
+pub(crate) fn init_pool(region: &'static mut [u8], budget: usize) {
+    let start = region.as_mut_ptr() as usize;
+    let aligned = (start + REGION_ALIGN - 1) & !(REGION_ALIGN - 1);
+    let end = aligned + (start + region.len()).saturating_sub(aligned) / PAGE_SIZE * PAGE_SIZE;
+
+    let initialized = with_pools(|pools| {
+        if START.load(Ordering::Relaxed) != 0 {
+            return false;
+        }
+        pools.next_page = aligned;
+        END.store(end, Ordering::Relaxed);
+        START.store(aligned, Ordering::Relaxed);
+        true
+    });
+    assert!(initialized == Some(true), "pool already initialized");
+
+    REGION.store(end - aligned, Ordering::Relaxed);
+    BUDGET.store(budget, Ordering::Relaxed);
+}
+
+pub(crate) fn pool_stats() -> PoolStats {
+    PoolStats {
+        region: REGION.load(Ordering::Relaxed),
+        carved: CARVED.load(Ordering::Relaxed),
+        fallback: FALLBACK.load(Ordering::Relaxed),
+        budget: BUDGET.load(Ordering::Relaxed),
+    }
+}
+
+fn with_pools<R>(f: impl FnOnce(&mut Pools) -> R) -> Option<R> {
+    if LOCKED.swap(true, Ordering::Acquire) {
+        return None;
+    }
+    let result = f(unsafe { &mut POOLS });
+    LOCKED.store(false, Ordering::Release);
+    Some(result)
+}
+
+fn class_of(layout: &Layout) -> Option<usize> {
+    let size = layout.size().max(layout.align());
+    CLASSES.iter().position(|&class| size <= class)
+}
+
+fn in_region(ptr: *mut u8) -> bool {
+    (START.load(Ordering::Relaxed)..END.load(Ordering::Relaxed)).contains(&(ptr as usize))
+}
+
+fn within_budget(size: usize) -> bool {
+    let used = CARVED.load(Ordering::Relaxed) + FALLBACK.load(Ordering::Relaxed);
+    used.saturating_add(size) <= BUDGET.load(Ordering::Relaxed)
+}
+
+impl Pools {
+    unsafe fn take(&mut self, class: usize) -> *mut u8 {
+        if self.free[class].is_null() {
+            self.reclaim();
+        }
+        if self.free[class].is_null() && !self.carve(class) {
+            return ptr::null_mut();
+        }
+
+        let block = self.free[class];
+        self.free[class] = (*block).next;
+        block as *mut u8
+    }
+
+    unsafe fn give_back(&mut self, ptr: *mut u8, class: usize) {
+        let block = ptr as *mut Block;
+        (*block).next = self.free[class];
+        self.free[class] = block;
+    }
+
+    // gives back the blocks `defer` kept while the pools were locked
+    unsafe fn reclaim(&mut self) {
+        let mut block = DEFERRED.swap(ptr::null_mut(), Ordering::Acquire);
+        while !block.is_null() {
+            let Deferred { next, class } = ptr::read(block);
+            self.give_back(block as *mut u8, class);
+            block = next;
+        }
+    }
+
+    // splits the next page of the region into blocks of `class`
+    unsafe fn carve(&mut self, class: usize) -> bool {
+        if self.next_page + PAGE_SIZE > END.load(Ordering::Relaxed) || !within_budget(PAGE_SIZE) {
+            return false;
+        }
+
+        let page = self.next_page;
+        self.next_page += PAGE_SIZE;
+        CARVED.fetch_add(PAGE_SIZE, Ordering::Relaxed);
+
+        let size = CLASSES[class];
+        for block in (page..page + PAGE_SIZE).step_by(size).rev() {
+            self.give_back(block as *mut u8, class);
+        }
+        true
+    }
+}
+
+// lock-free, for frees that find the pools locked
+unsafe fn defer(ptr: *mut u8, class: usize) {
+    let block = ptr as *mut Deferred;
+    let mut next = DEFERRED.load(Ordering::Relaxed);
+    loop {
+        ptr::write(block, Deferred { next, class });
+        match DEFERRED.compare_exchange_weak(next, block, Ordering::Release, Ordering::Relaxed) {
+            Ok(_) => break,
+            Err(current) => next = current,
+        }
+    }
+}
+
+unsafe fn fallback_alloc(layout: &Layout) -> *mut u8 {
+    if !within_budget(layout.size()) {
+        return ptr::null_mut();
+    }
+
+    let ptr = sys_alloc(layout);
+    if !ptr.is_null() {
+        FALLBACK.fetch_add(layout.size(), Ordering::Relaxed);
+    }
+    ptr
+}
+
+unsafe impl GlobalAlloc for PoolAllocator {
+    unsafe fn alloc(&self, layout: Layout) -> *mut u8 {
+        let pooled = match class_of(&layout) {
+            Some(class) => with_pools(|pools| pools.take(class)).unwrap_or(ptr::null_mut()),
+            None => ptr::null_mut(),
+        };
+
+        let ptr = if pooled.is_null() {
+            fallback_alloc(&layout)
+        } else {
+            pooled
+        };
+        record_alloc(ptr, &layout);
+        ptr
+    }
+
+    unsafe fn dealloc(&self, ptr: *mut u8, layout: Layout) {
+        record_dealloc(&layout);
+
+        if in_region(ptr) {
+            // the class `alloc` picked
+            let class = class_of(&layout).unwrap();
+            if with_pools(|pools| pools.give_back(ptr, class)).is_none() {
+                defer(ptr, class);
+            }
+        } else {
+            free(ptr as *mut c_void);
+            FALLBACK.fetch_sub(layout.size(), Ordering::Relaxed);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests;
@@ -0,0 +1,82 @@
+use core::alloc::{GlobalAlloc, Layout};
+use core::sync::atomic::Ordering;
+
+use super::{class_of, in_region, init_pool, pool_stats, PoolAllocator, LOCKED, PAGE_SIZE};
+
+fn layout(size: usize, align: usize) -> Layout {
+    Layout::from_size_align(size, align).unwrap()
+}
+
+#[test]
+fn picks_the_smallest_fitting_class() {
+    assert_eq!(class_of(&layout(1, 1)), Some(0));
+    assert_eq!(class_of(&layout(16, 8)), Some(0));
+    assert_eq!(class_of(&layout(17, 8)), Some(1));
+    assert_eq!(class_of(&layout(2048, 8)), Some(7));
+    assert_eq!(class_of(&layout(2049, 8)), None);
+    // blocks are aligned to their size
+    assert_eq!(class_of(&layout(8, 256)), Some(4));
+    assert_eq!(class_of(&layout(8, 4096)), None);
+}
+
+// the pools are global and only initialized once, so this is the only test
+// allocating from them
+#[test]
+fn carves_pages_within_the_budget() {
+    static mut REGION: [u8; 5 * PAGE_SIZE] = [0; 5 * PAGE_SIZE];
+    let pools = PoolAllocator;
+    unsafe {
+        let fallback = pools.alloc(layout(16, 8));
+        assert!(!fallback.is_null() && !in_region(fallback));
+        assert_eq!(pool_stats().fallback, 16);
+        pools.dealloc(fallback, layout(16, 8));
+
+        // room for three pages from the pools and 3500 bytes from malloc
+        init_pool(&mut REGION, 3 * PAGE_SIZE + 3500);
+        let stats = pool_stats();
+        assert_eq!(stats.region, 4 * PAGE_SIZE);
+        assert_eq!((stats.carved, stats.fallback), (0, 0));
+
+        let first = pools.alloc(layout(16, 8));
+        let second = pools.alloc(layout(16, 8));
+        assert!(in_region(first));
+        assert_eq!(second as usize, first as usize + 16);
+        assert_eq!(pool_stats().carved, PAGE_SIZE);
+
+        // two blocks per page
+        let large = [pools.alloc(layout(2048, 8)), pools.alloc(layout(2048, 8))];
+        assert!(large.iter().all(|&block| block as usize % 2048 == 0));
+        assert_eq!(pool_stats().carved, 2 * PAGE_SIZE);
+
+        // freed while a signal handler interrupted an allocation
+        LOCKED.store(true, Ordering::Relaxed);
+        pools.dealloc(large[0], layout(2048, 8));
+        LOCKED.store(false, Ordering::Release);
+        assert_eq!(pools.alloc(layout(2048, 8)), large[0]);
+        assert_eq!(pool_stats().carved, 2 * PAGE_SIZE);
+
+        // freed blocks are reused before carving another page
+        pools.dealloc(first, layout(16, 8));
+        assert_eq!(pools.alloc(layout(16, 8)), first);
+        assert_eq!(pool_stats().carved, 2 * PAGE_SIZE);
+
+        let medium = pools.alloc(layout(1024, 8));
+        assert!(in_region(medium));
+        assert_eq!(pool_stats().carved, 3 * PAGE_SIZE);
+
+        let unpooled = pools.alloc(layout(3000, 8));
+        assert!(!unpooled.is_null() && !in_region(unpooled));
+        assert_eq!(pool_stats().fallback, 3000);
+        assert!(pools.alloc(layout(3000, 8)).is_null());
+        pools.dealloc(unpooled, layout(3000, 8));
+        assert_eq!(pool_stats().fallback, 0);
+
+        // a fourth page would exceed the budget
+        let small = pools.alloc(layout(512, 8));
+        assert!(!small.is_null() && !in_region(small));
+        assert_eq!(pool_stats().carved, 3 * PAGE_SIZE);
+        assert_eq!(pool_stats().fallback, 512);
+        pools.dealloc(small, layout(512, 8));
+        assert_eq!(pool_stats().fallback, 0);
+    }
+}
@@ -0,0 +1,267 @@
+//! Crash records in mutable storage.
+//!
+//! Before it exits, the panic handler stores a `CrashRecord` in the first
+//! `RECORD_SIZE` bytes of the app's mutable storage file, the region sphere-lib
+//! reserves before `storage::APP_DATA_OFFSET`. The OS then restarts the app,
+//! which can fetch the record with `last_crash`, upload it and `clear` it. The
+//! rest of the file is free for the app.
+//!
+//! Needs the `MutableStorage` capability in the app manifest, without it
+//! crashes are only logged.
+
+use alloc::boxed::Box;
+use alloc::string::String;
+use core::convert::TryInto;
+use core::fmt::{self, Write};
+use core::panic::{Location, PanicInfo};
+use core::ptr;
+use core::sync::atomic::{AtomicPtr, Ordering};
+use core::time::Duration;
+
+use sphere_sys::std::os::raw::c_int;
+use sphere_sys::{close, fsync, write, Storage_OpenMutableFile};
+
+use sphere_lib::storage::{MutableFile, APP_DATA_OFFSET};
+use sphere_lib::time::Instant;
+use sphere_lib::util::{crc32, push_json_string};
+use sphere_lib::Error;
+
+/// Bytes at the start of mutable storage reserved for the crash record.
+pub const RECORD_SIZE: usize = APP_DATA_OFFSET as usize;
+
+/// Exit code of an app ended by a panic ('P').
+pub const EXIT_CODE: i32 = 0x50;
+
+const MAGIC: [u8; 4] = *b"CRSH";
+const VERSION: u16 = 1;
+const HEADER_SIZE: usize = 32;
+const CRC_RANGE: core::ops::Range<usize> = 28..32;
+const MAX_BUILD_ID: usize = 64;
+const MAX_FILE: usize = 128;
+
+// leaked by `set_build_id`, null until then
+static BUILD_ID: AtomicPtr<&'static str> = AtomicPtr::new(ptr::null_mut());
+
+/// What panicked, where and when.
+#[derive(Clone, Debug, PartialEq)]
+pub struct CrashRecord {
+    /// Truncated to fit the record.
+    pub message: String,
+    pub file: String,
+    pub line: u32,
+    pub column: u32,
+    /// Time since boot.
+    pub uptime: Duration,
+    /// As set with `set_build_id` in the crashed run.
+    pub build_id: String,
+}
+
+/// Identifies the firmware in crash records, e.g. a version or commit hash.
+/// Truncated to 64 bytes. Meant to be called once, at startup.
+pub fn set_build_id(build_id: &'static str) {
+    BUILD_ID.store(Box::leak(Box::new(build_id)), Ordering::Release);
+}
+
+fn build_id() -> &'static str {
+    let build_id = BUILD_ID.load(Ordering::Acquire);
+    if build_id.is_null() {
+        ""
+    } else {
+        unsafe { *build_id }
+    }
+}
+
+/// The record of the last crash, if there is one.
+pub fn last_crash() -> Result<Option<CrashRecord>, Error> {
+    let mut record = [0u8; RECORD_SIZE];
+    match MutableFile::open()?.read_exact(&mut record) {
+        Err(Error::Incomplete(_)) => Ok(None),
+        result => result.map(|_| decode(&record)),
+    }
+}
+
+/// Removes the crash record, e.g. once it's uploaded.
+pub fn clear() -> Result<(), Error> {
+    match last_crash()? {
+        // invalidating the magic is enough, the rest of the file stays as is
+        Some(_) => MutableFile::open()?.write_all(&[0; 4]),
+        None => Ok(()),
+    }
+}
+
+impl CrashRecord {
+    /// A JSON object, e.g. for `send_telemetry`.
+    pub fn to_json(&self) -> String {
+        let mut json = String::new();
+        json.push_str("{\"message\":");
+        push_json_string(&mut json, &self.message);
+        json.push_str(",\"file\":");
+        push_json_string(&mut json, &self.file);
+        let _ = write!(
+            json,
+            ",\"line\":{},\"column\":{},\"uptimeMs\":{},\"buildId\":",
+            self.line,
+            self.column,
+            self.uptime.as_millis()
+        );
+        push_json_string(&mut json, &self.build_id);
+        json.push('}');
+        json
+    }
+}
+
+impl fmt::Display for CrashRecord {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(
+            f,
+            "panicked at '{}', {}:{}:{} after {}.{:03}s (build {})",
+            self.message,
+            self.file,
+            self.line,
+            self.column,
+            self.uptime.as_secs(),
+            self.uptime.subsec_millis(),
+            self.build_id
+        )
+    }
+}
+
+/// Called by the panic handler. Doesn't allocate, unless the panic message
+/// formats a value that does.
+pub(crate) fn store(info: &PanicInfo) -> Result<(), Error> {
+    let mut record = [0u8; RECORD_SIZE];
+    let uptime = Instant::now().uptime();
+    encode(info.location(), info.message(), uptime, &mut record);
+    write_record(&record)
+}
+
+// Straight to the fd rather than through `MutableFile`, which registers a
+// shutdown hook, i.e. allocates.
+fn write_record(record: &[u8; RECORD_SIZE]) -> Result<(), Error> {
+    let fd = unsafe { Storage_OpenMutableFile() };
+    if fd < 0 {
+        return Err(Error::last_os_error());
+    }
+
+    // a fresh fd starts at the beginning, where the record goes
+    let result = write_all(fd, record).and_then(|_| match unsafe { fsync(fd) } {
+        0 => Ok(()),
+        _ => Err(Error::last_os_error()),
+    });
+    unsafe { close(fd) };
+    result
+}
+
+fn write_all(fd: c_int, data: &[u8]) -> Result<(), Error> {
+    let mut written = 0;
+    while written < data.len() {
+        let rest = &data[written..];
+        match unsafe { write(fd, rest.as_ptr() as *const _, rest.len()) } {
+            count if count < 0 => return Err(Error::last_os_error()),
+            0 => return Err(Error::Incomplete(written)),
+            count => written += count as usize,
+        }
+    }
+    Ok(())
+}
+
+fn encode(
+    location: Option<&Location>,
+    message: Option<&fmt::Arguments>,
+    uptime: Duration,
+    record: &mut [u8; RECORD_SIZE],
+) {
+    let (file, line, column) = match location {
+        Some(location) => (location.file(), location.line(), location.column()),
+        None => ("", 0, 0),
+    };
+
+    let (header, data) = record.split_at_mut(HEADER_SIZE);
+    let mut data = Truncating { buf: data, len: 0 };
+
+    let build_id_len = data.put(build_id(), MAX_BUILD_ID);
+    let file_len = data.put(file, MAX_FILE);
+    let message_start = data.len;
+    if let Some(message) = message {
+        let _ = data.write_fmt(*message);
+    }
+    let message_len = data.len - message_start;
+
+    header[0..4].copy_from_slice(&MAGIC);
+    header[4..6].copy_from_slice(&VERSION.to_le_bytes());
+    header[6..8].copy_from_slice(&(message_len as u16).to_le_bytes());
+    header[8..16].copy_from_slice(&(uptime.as_millis() as u64).to_le_bytes());
+    header[16..20].copy_from_slice(&line.to_le_bytes());
+    header[20..24].copy_from_slice(&column.to_le_bytes());
+    header[24..26].copy_from_slice(&(build_id_len as u16).to_le_bytes());
+    header[26..28].copy_from_slice(&(file_len as u16).to_le_bytes());
+
+    let crc = crc32(&record[..]);
+    record[CRC_RANGE].copy_from_slice(&crc.to_le_bytes());
+}
+
+fn decode(record: &[u8; RECORD_SIZE]) -> Option<CrashRecord> {
+    if record[0..4] != MAGIC || u16_at(record, 4) != VERSION {
+        return None;
+    }
+
+    let mut unchecked = *record;
+    unchecked[CRC_RANGE].copy_from_slice(&[0; 4]);
+    if crc32(&unchecked) != u32_at(record, CRC_RANGE.start) {
+        return None;
+    }
+
+    let message_len = u16_at(record, 6) as usize;
+    let build_id_len = u16_at(record, 24) as usize;
+    let file_len = u16_at(record, 26) as usize;
+
+    let build_id = HEADER_SIZE..HEADER_SIZE + build_id_len;
+    let file = build_id.end..build_id.end + file_len;
+    let message = file.end..file.end + message_len;
+    if message.end > RECORD_SIZE {
+        return None;
+    }
+
+    Some(CrashRecord {
+        message: String::from_utf8_lossy(&record[message]).into_owned(),
+        file: String::from_utf8_lossy(&record[file]).into_owned(),
+        line: u32_at(record, 16),
+        column: u32_at(record, 20),
+        uptime: Duration::from_millis(u64::from_le_bytes(record[8..16].try_into().unwrap())),
+        build_id: String::from_utf8_lossy(&record[build_id]).into_owned(),
+    })
+}
+
+fn u16_at(record: &[u8], offset: usize) -> u16 {
+    u16::from_le_bytes(record[offset..offset + 2].try_into().unwrap())
+}
+
+fn u32_at(record: &[u8], offset: usize) -> u32 {
+    u32::from_le_bytes(record[offset..offset + 4].try_into().unwrap())
+}
+
+// cuts off what doesn't fit, possibly in the middle of a character
+struct Truncating<'a> {
+    buf: &'a mut [u8],
+    len: usize,
+}
+
+impl Truncating<'_> {
+    /// Appends at most `limit` bytes of `s`, returns how many.
+    fn put(&mut self, s: &str, limit: usize) -> usize {
+        let count = s.len().min(limit).min(self.buf.len() - self.len);
+        self.buf[self.len..self.len + count].copy_from_slice(&s.as_bytes()[..count]);
+        self.len += count;
+        count
+    }
+}
+
+impl fmt::Write for Truncating<'_> {
+    fn write_str(&mut self, s: &str) -> fmt::Result {
+        self.put(s, s.len());
+        Ok(())
+    }
+}
+
+#[cfg(all(test, feature = "mock"))]
+mod tests;
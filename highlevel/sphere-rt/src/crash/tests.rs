@@ -0,0 +1,114 @@
+use alloc::string::String;
+use alloc::vec::Vec;
+use core::fmt;
+use core::panic::Location;
+use core::time::Duration;
+
+use sphere_lib::Error;
+use sphere_sys::mock;
+use sphere_sys::mock::errno::ENOSPC;
+
+use super::{
+    clear, decode, encode, last_crash, set_build_id, write_record, CrashRecord, HEADER_SIZE,
+    MAX_FILE, RECORD_SIZE,
+};
+
+const UPTIME: Duration = Duration::from_millis(61_250);
+
+fn encoded(location: &Location, message: fmt::Arguments) -> [u8; RECORD_SIZE] {
+    let mut record = [0; RECORD_SIZE];
+    encode(Some(location), Some(&message), UPTIME, &mut record);
+    record
+}
+
+#[test]
+fn round_trips_through_mutable_storage() {
+    set_build_id("1.2.3");
+    let location = Location::caller();
+    let record = encoded(location, format_args!("index {} out of range", 7));
+
+    write_record(&record).unwrap();
+    let names: Vec<_> = mock::calls().into_iter().map(|call| call.name).collect();
+    assert_eq!(
+        names,
+        ["Storage_OpenMutableFile", "write", "fsync", "close"]
+    );
+
+    let crash = last_crash().unwrap().unwrap();
+    assert_eq!(
+        crash,
+        CrashRecord {
+            message: String::from("index 7 out of range"),
+            file: String::from(location.file()),
+            line: location.line(),
+            column: location.column(),
+            uptime: UPTIME,
+            build_id: String::from("1.2.3"),
+        }
+    );
+    assert_eq!(crash.to_json().matches("\"uptimeMs\":61250").count(), 1);
+}
+
+#[test]
+fn leaves_the_app_data_alone() {
+    mock::set_mutable_storage(&[0xaa; 2 * RECORD_SIZE]);
+
+    let record = encoded(Location::caller(), format_args!("boom"));
+    write_record(&record).unwrap();
+    assert!(decode(&record).is_some());
+
+    let storage = mock::mutable_storage();
+    assert_eq!(storage[..RECORD_SIZE], record[..]);
+    assert!(storage[RECORD_SIZE..].iter().all(|&byte| byte == 0xaa));
+}
+
+#[test]
+fn truncates_what_doesnt_fit() {
+    let long = [b'x'; 2 * RECORD_SIZE];
+    let long = core::str::from_utf8(&long).unwrap();
+    let record = encoded(Location::caller(), format_args!("{}", long));
+
+    let crash = decode(&record).unwrap();
+    let file = Location::caller().file().len().min(MAX_FILE);
+    let available = RECORD_SIZE - HEADER_SIZE - crash.build_id.len() - file;
+    assert_eq!(crash.message.len(), available);
+}
+
+#[test]
+fn rejects_a_corrupt_record() {
+    let mut record = encoded(Location::caller(), format_args!("boom"));
+    record[HEADER_SIZE] ^= 0xff;
+    assert_eq!(decode(&record), None);
+
+    write_record(&record).unwrap();
+    assert_eq!(last_crash(), Ok(None));
+}
+
+#[test]
+fn rejects_other_versions() {
+    let mut record = encoded(Location::caller(), format_args!("boom"));
+    record[4] = 2;
+    assert_eq!(decode(&record), None);
+}
+
+#[test]
+fn clears_the_record() {
+    assert_eq!(last_crash(), Ok(None));
+
+    let record = encoded(Location::caller(), format_args!("boom"));
+    write_record(&record).unwrap();
+    clear().unwrap();
+    assert_eq!(last_crash(), Ok(None));
+    // nothing to clear
+    clear().unwrap();
+}
+
+#[test]
+fn reports_failed_writes() {
+    let record = encoded(Location::caller(), format_args!("boom"));
+
+    mock::fail_next("fsync", ENOSPC);
+    assert_eq!(write_record(&record), Err(Error::Os(ENOSPC)));
+    // closed anyway
+    assert_eq!(mock::call_count("close"), 1);
+}
@@ -0,0 +1,65 @@
+//! The command line of the app.
+//!
+//! Azure Sphere passes the `CmdArgs` of the app manifest, e.g. to configure
+//! scope IDs or hostnames per deployment without rebuilding the app.
+
+use alloc::string::String;
+use core::slice;
+
+static mut ARGC: isize = 0;
+static mut ARGV: *const *const u8 = core::ptr::null();
+
+/// Called by `main` before the app starts.
+pub(crate) unsafe fn init(argc: isize, argv: *const *const u8) {
+    ARGC = argc;
+    ARGV = argv;
+}
+
+/// The path of the executable, then the `CmdArgs` of the app manifest.
+/// Arguments that aren't valid UTF-8 are converted lossily.
+pub fn args() -> Args {
+    let len = unsafe {
+        if ARGV.is_null() {
+            0
+        } else {
+            ARGC.max(0) as usize
+        }
+    };
+    Args { next: 0, len }
+}
+
+/// See `args`.
+#[derive(Debug)]
+pub struct Args {
+    next: usize,
+    len: usize,
+}
+
+impl Iterator for Args {
+    type Item = String;
+
+    fn next(&mut self) -> Option<String> {
+        if self.next == self.len {
+            return None;
+        }
+
+        let arg = unsafe { c_str(*ARGV.add(self.next)) };
+        self.next += 1;
+        Some(String::from_utf8_lossy(arg).into_owned())
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        let remaining = self.len - self.next;
+        (remaining, Some(remaining))
+    }
+}
+
+impl ExactSizeIterator for Args {}
+
+unsafe fn c_str<'a>(ptr: *const u8) -> &'a [u8] {
+    let mut len = 0;
+    while *ptr.add(len) != 0 {
+        len += 1;
+    }
+    slice::from_raw_parts(ptr, len)
+}
@@ -0,0 +1,103 @@
+use sphere_lib::eventloop::IoEvents;
+use sphere_lib::fd::AsRawFd;
+use sphere_lib::Error;
+
+#[cfg(feature = "application")]
+use sphere_lib::application::ApplicationSocket;
+#[cfg(feature = "uart")]
+use sphere_lib::uart::Uart;
+
+use super::ready;
+
+const EAGAIN: i32 = sphere_sys::EAGAIN as i32;
+
+/// Makes a non-blocking fd-based handle usable from async code: operations
+/// failing with `EAGAIN` are retried once the fd is ready.
+pub struct Async<T: AsRawFd> {
+    inner: T,
+}
+
+impl<T: AsRawFd> Async<T> {
+    pub fn new(inner: T) -> Async<T> {
+        Async { inner }
+    }
+
+    pub fn get_ref(&self) -> &T {
+        &self.inner
+    }
+
+    pub fn into_inner(self) -> T {
+        self.inner
+    }
+
+    pub async fn readable(&self) -> Result<(), Error> {
+        ready(&self.inner, IoEvents::INPUT).await
+    }
+
+    pub async fn writable(&self) -> Result<(), Error> {
+        ready(&self.inner, IoEvents::OUTPUT).await
+    }
+
+    /// Runs `op` until it doesn't fail with `EAGAIN`, waiting for input in
+    /// between.
+    pub async fn read_with<R, F>(&self, mut op: F) -> Result<R, Error>
+    where
+        F: FnMut(&T) -> Result<R, Error>,
+    {
+        loop {
+            match op(&self.inner) {
+                Err(Error::Os(EAGAIN)) => self.readable().await?,
+                result => break result,
+            }
+        }
+    }
+
+    /// Like `read_with`, waiting until the fd is writable.
+    pub async fn write_with<R, F>(&self, mut op: F) -> Result<R, Error>
+    where
+        F: FnMut(&T) -> Result<R, Error>,
+    {
+        loop {
+            match op(&self.inner) {
+                Err(Error::Os(EAGAIN)) => self.writable().await?,
+                result => break result,
+            }
+        }
+    }
+}
+
+/// The UART has to be opened in non-blocking mode.
+#[cfg(feature = "uart")]
+impl Async<Uart> {
+    pub async fn read(&self, buffer: &mut [u8]) -> Result<usize, Error> {
+        self.read_with(|uart| uart.read(buffer)).await
+    }
+
+    pub async fn write(&self, data: &[u8]) -> Result<usize, Error> {
+        self.write_with(|uart| uart.write(data)).await
+    }
+
+    /// Fails with `Error::Incomplete` carrying the bytes written so far if
+    /// the UART stops taking data.
+    pub async fn write_all(&self, data: &[u8]) -> Result<(), Error> {
+        let mut written = 0;
+        while written < data.len() {
+            match self.write(&data[written..]).await? {
+                0 => return Err(Error::Incomplete(written)),
+                count => written += count,
+            }
+        }
+        Ok(())
+    }
+}
+
+#[cfg(feature = "application")]
+impl Async<ApplicationSocket> {
+    pub async fn read(&self, buffer: &mut [u8]) -> Result<usize, Error> {
+        self.read_with(|socket| socket.read(buffer)).await
+    }
+
+    pub async fn write(&self, data: &[u8]) -> Result<usize, Error> {
+        self.write_with(|socket| socket.write(data)).await
+    }
+}
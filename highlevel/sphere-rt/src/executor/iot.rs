@@ -0,0 +1,61 @@
+use alloc::rc::Rc;
+use core::cell::Cell;
+use core::time::Duration;
+
+use sphere_lib::azureiot::AzureProvisioning;
+use sphere_lib::Error;
+
+use super::Interval;
+
+const CONFIRMATION_OK: u32 =
+    sphere_sys::IOTHUB_CLIENT_CONFIRMATION_RESULT_TAG_IOTHUB_CLIENT_CONFIRMATION_OK;
+
+// how often `send_telemetry` pumps `do_work` while waiting
+const CONFIRMATION_POLL_INTERVAL: Duration = Duration::from_millis(100);
+
+/// Pumps `do_work` every `interval`, for the device twin and method callbacks.
+/// Only returns on errors.
+pub async fn do_work(
+    provisioning: &AzureProvisioning<'_>,
+    interval: Duration,
+) -> Result<(), Error> {
+    let interval = Interval::new(interval)?;
+    loop {
+        interval.tick().await?;
+        provisioning.do_work();
+    }
+}
+
+/// Sends `payload` and resolves once IoT Hub confirmed it. Fails with
+/// `Error::IotHub` carrying the `IOTHUB_CLIENT_CONFIRMATION_RESULT` if it
+/// wasn't delivered.
+///
+/// Pumps `do_work` itself until then, so it doesn't need a `do_work` task
+/// running alongside.
+pub async fn send_telemetry(
+    provisioning: &AzureProvisioning<'_>,
+    payload: &str,
+) -> Result<(), Error> {
+    let confirmation = Rc::new(Cell::new(None));
+
+    let on_confirmation = {
+        let confirmation = confirmation.clone();
+        move |result: u32| confirmation.set(Some(result))
+    };
+    provisioning.send_telemetry_confirmed(payload, on_confirmation)?;
+
+    // the SDK confirms from within `do_work`
+    let interval = Interval::new(CONFIRMATION_POLL_INTERVAL)?;
+    let result = loop {
+        provisioning.do_work();
+        if let Some(result) = confirmation.get() {
+            break result;
+        }
+        interval.tick().await?;
+    };
+
+    match result {
+        CONFIRMATION_OK => Ok(()),
+        result => Err(Error::IotHub(result)),
+    }
+}
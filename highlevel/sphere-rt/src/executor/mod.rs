@@ -0,0 +1,234 @@
+//! A single-threaded executor for `async` code.
+//!
+//! Woken tasks are polled in turn; while none is, the thread sleeps in
+//! `epoll_wait` until an fd a task waits on becomes ready. The executor lives
+//! in a global and must only be used from the main thread.
+
+use alloc::boxed::Box;
+use alloc::rc::Rc;
+use alloc::sync::Arc;
+use alloc::task::Wake;
+use alloc::vec::Vec;
+use core::cell::{Cell, RefCell};
+use core::future::Future;
+use core::pin::Pin;
+use core::sync::atomic::{AtomicBool, Ordering};
+use core::task::{Context, Poll, Waker};
+
+use sphere_lib::eventloop::{EventLoop, IoEvents};
+use sphere_lib::fd::{AsRawFd, RawFd};
+use sphere_lib::Error;
+
+mod io;
+#[cfg(feature = "iot")]
+pub mod iot;
+mod timer;
+
+pub use io::Async;
+pub use timer::{sleep, Interval};
+
+struct TaskWaker {
+    woken: AtomicBool,
+}
+
+impl TaskWaker {
+    // new tasks are polled right away
+    fn new() -> Arc<TaskWaker> {
+        Arc::new(TaskWaker {
+            woken: AtomicBool::new(true),
+        })
+    }
+
+    fn is_woken(&self) -> bool {
+        self.woken.load(Ordering::SeqCst)
+    }
+
+    fn take(&self) -> bool {
+        self.woken.swap(false, Ordering::SeqCst)
+    }
+}
+
+impl Wake for TaskWaker {
+    fn wake(self: Arc<Self>) {
+        self.wake_by_ref();
+    }
+
+    fn wake_by_ref(self: &Arc<Self>) {
+        self.woken.store(true, Ordering::SeqCst);
+    }
+}
+
+struct Task {
+    future: Pin<Box<dyn Future<Output = ()>>>,
+    waker: Arc<TaskWaker>,
+}
+
+struct Runtime {
+    event_loop: EventLoop<'static>,
+    tasks: RefCell<Vec<Task>>,
+}
+
+impl Runtime {
+    fn new() -> Runtime {
+        Runtime {
+            event_loop: EventLoop::new().expect("Couldn't create the epoll fd"),
+            tasks: RefCell::new(Vec::new()),
+        }
+    }
+}
+
+#[cfg(not(test))]
+static mut RUNTIME: Option<Runtime> = None;
+
+#[cfg(not(test))]
+fn runtime() -> &'static Runtime {
+    // only used from the main thread, see above
+    unsafe {
+        if RUNTIME.is_none() {
+            RUNTIME = Some(Runtime::new());
+        }
+        RUNTIME.as_ref().unwrap()
+    }
+}
+
+// every test runs on its own thread, with its own simulated devices
+#[cfg(test)]
+fn runtime() -> &'static Runtime {
+    extern crate std;
+
+    std::thread_local! {
+        static RUNTIME: &'static Runtime = Box::leak(Box::new(Runtime::new()));
+    }
+    RUNTIME.with(|runtime| *runtime)
+}
+
+/// Runs `future` in the background. It makes progress while the main thread
+/// is in `block_on`.
+pub fn spawn<F>(future: F)
+where
+    F: Future<Output = ()> + 'static,
+{
+    runtime().tasks.borrow_mut().push(Task {
+        future: Box::pin(future),
+        waker: TaskWaker::new(),
+    });
+}
+
+/// Runs `future` and the spawned tasks until `future` completes.
+///
+/// Panics if `epoll_wait` fails, which only happens for bugs in the runtime.
+pub fn block_on<F: Future>(future: F) -> F::Output {
+    let runtime = runtime();
+
+    let mut future = Box::pin(future);
+    let main_waker = TaskWaker::new();
+    let waker = Waker::from(main_waker.clone());
+
+    loop {
+        if main_waker.take() {
+            if let Poll::Ready(output) = future.as_mut().poll(&mut Context::from_waker(&waker)) {
+                return output;
+            }
+        }
+
+        // taken out while polling, tasks may spawn more
+        let tasks = core::mem::take(&mut *runtime.tasks.borrow_mut());
+        let mut pending = Vec::with_capacity(tasks.len());
+        for mut task in tasks {
+            if task.waker.take() {
+                let waker = Waker::from(task.waker.clone());
+                let poll = task.future.as_mut().poll(&mut Context::from_waker(&waker));
+                if poll.is_ready() {
+                    continue;
+                }
+            }
+            pending.push(task);
+        }
+
+        let mut tasks = runtime.tasks.borrow_mut();
+        let spawned = core::mem::replace(&mut *tasks, pending);
+        tasks.extend(spawned);
+
+        let woken = main_waker.is_woken() || tasks.iter().any(|task| task.waker.is_woken());
+        drop(tasks);
+
+        if !woken {
+            runtime
+                .event_loop
+                .run_once(None)
+                .expect("epoll_wait failed");
+        }
+    }
+}
+
+struct Shared {
+    fired: Cell<bool>,
+    waker: RefCell<Option<Waker>>,
+}
+
+/// Future of `ready`.
+pub struct Readiness {
+    fd: RawFd,
+    events: IoEvents,
+    shared: Option<Rc<Shared>>,
+}
+
+/// Resolves once `source` is ready for `events`. Only one task at a time may
+/// wait for the same fd.
+pub fn ready(source: &dyn AsRawFd, events: IoEvents) -> Readiness {
+    Readiness {
+        fd: source.as_raw_fd(),
+        events,
+        shared: None,
+    }
+}
+
+impl Future for Readiness {
+    type Output = Result<(), Error>;
+
+    fn poll(mut self: Pin<&mut Self>, cx: &mut Context) -> Poll<Self::Output> {
+        if let Some(shared) = &self.shared {
+            if shared.fired.get() {
+                return Poll::Ready(Ok(()));
+            }
+            *shared.waker.borrow_mut() = Some(cx.waker().clone());
+            return Poll::Pending;
+        }
+
+        let shared = Rc::new(Shared {
+            fired: Cell::new(false),
+            waker: RefCell::new(Some(cx.waker().clone())),
+        });
+        let fd = self.fd;
+        let on_ready = {
+            let shared = shared.clone();
+            move |event_loop: &EventLoop<'static>, _events: IoEvents| {
+                // one-shot, the fd stays ready until the task reads or writes
+                let _ = event_loop.unregister(fd);
+                shared.fired.set(true);
+                if let Some(waker) = shared.waker.borrow_mut().take() {
+                    waker.wake();
+                }
+            }
+        };
+
+        if let Err(error) = runtime().event_loop.register_io(&fd, self.events, on_ready) {
+            return Poll::Ready(Err(error));
+        }
+        self.shared = Some(shared);
+        Poll::Pending
+    }
+}
+
+impl Drop for Readiness {
+    fn drop(&mut self) {
+        if let Some(shared) = &self.shared {
+            if !shared.fired.get() {
+                let _ = runtime().event_loop.unregister(self.fd);
+            }
+        }
+    }
+}
+
+#[cfg(all(test, feature = "mock"))]
+mod tests;
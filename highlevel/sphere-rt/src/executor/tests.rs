@@ -0,0 +1,123 @@
+use alloc::rc::Rc;
+use core::cell::Cell;
+use core::time::Duration;
+
+use sphere_lib::eventloop::IoEvents;
+use sphere_lib::time::Timer;
+use sphere_sys::mock;
+
+#[cfg(feature = "uart")]
+use super::Async;
+use super::{block_on, ready, sleep, spawn, Interval};
+#[cfg(feature = "uart")]
+use sphere_lib::{isu::Isu, uart::Uart, uart::UartConfig, Error};
+
+// ISU0 is UART 4
+#[cfg(feature = "uart")]
+const UART: i32 = 4;
+
+#[cfg(feature = "uart")]
+fn open_uart() -> Async<Uart> {
+    Async::new(Uart::open(Isu::Isu0, UartConfig::default()).unwrap())
+}
+
+#[test]
+fn wakes_a_task_once_its_fd_is_ready() {
+    let timer = Timer::one_shot(Duration::from_millis(20)).unwrap();
+
+    block_on(ready(&timer, IoEvents::INPUT)).unwrap();
+    assert_eq!(mock::monotonic_ns(), 20_000_000);
+    assert_eq!(timer.expirations(), Ok(1));
+}
+
+#[test]
+fn runs_spawned_tasks_while_blocking() {
+    let ticks = Rc::new(Cell::new(0));
+
+    let counter = ticks.clone();
+    spawn(async move {
+        let interval = Interval::new(Duration::from_millis(100)).unwrap();
+        loop {
+            let expirations = interval.tick().await.unwrap();
+            counter.set(counter.get() + expirations);
+        }
+    });
+
+    block_on(sleep(Duration::from_millis(550))).unwrap();
+    assert_eq!(ticks.get(), 5);
+    assert_eq!(mock::monotonic_ns(), 550_000_000);
+}
+
+#[test]
+fn returns_the_output_of_the_future() {
+    let value = block_on(async {
+        sleep(Duration::from_millis(1)).await?;
+        Ok::<_, sphere_lib::Error>(42)
+    });
+    assert_eq!(value, Ok(42));
+}
+
+#[cfg(feature = "uart")]
+#[test]
+fn reads_once_input_arrives() {
+    let uart = open_uart();
+
+    spawn(async {
+        sleep(Duration::from_millis(10)).await.unwrap();
+        mock::uart_inject(UART, b"ping");
+    });
+
+    let mut buffer = [0; 8];
+    let count = block_on(uart.read(&mut buffer)).unwrap();
+    assert_eq!(&buffer[..count], b"ping");
+    assert_eq!(mock::monotonic_ns(), 10_000_000);
+}
+
+#[cfg(feature = "uart")]
+#[test]
+fn retries_writes_once_writable() {
+    let uart = open_uart();
+
+    mock::fail_next("write", mock::errno::EAGAIN);
+    block_on(uart.write_all(b"pong")).unwrap();
+    assert_eq!(mock::uart_take_written(UART), b"pong");
+    assert_eq!(mock::call_count("write"), 2);
+}
+
+#[cfg(feature = "uart")]
+#[test]
+fn allows_one_waiting_task_per_fd() {
+    let uart = Rc::new(open_uart());
+    let first = Rc::new(Cell::new(None));
+
+    let waiting = uart.clone();
+    let result = first.clone();
+    spawn(async move {
+        result.set(Some(waiting.readable().await));
+    });
+
+    // the spawned task registers first, this one is polled after the sleep
+    let second = block_on(async {
+        sleep(Duration::from_millis(1)).await?;
+        uart.readable().await
+    });
+    assert_eq!(second, Err(Error::Os(mock::errno::EEXIST)));
+
+    mock::uart_inject(UART, b"x");
+    block_on(sleep(Duration::from_millis(1))).unwrap();
+    assert_eq!(first.take(), Some(Ok(())));
+}
+
+#[cfg(feature = "iot")]
+#[test]
+fn sends_telemetry_until_confirmed() {
+    use sphere_lib::azureiot::AzureProvisioning;
+
+    let provisioning =
+        AzureProvisioning::azure_create_from_connection_string("HostName=example").unwrap();
+
+    let payload = "{\"temperature\":21}";
+    block_on(super::iot::send_telemetry(&provisioning, payload)).unwrap();
+    assert_eq!(mock::iothub_sent_messages(), [payload]);
+    assert_eq!(mock::call_count("IoTHubDeviceClient_LL_DoWork"), 1);
+}
@@ -0,0 +1,41 @@
+use core::time::Duration;
+
+use sphere_lib::eventloop::IoEvents;
+use sphere_lib::time::Timer;
+use sphere_lib::Error;
+
+use super::ready;
+
+/// Resolves after `duration`, without blocking other tasks.
+pub async fn sleep(duration: Duration) -> Result<(), Error> {
+    let timer = Timer::one_shot(duration)?;
+    while timer.expirations()? == 0 {
+        ready(&timer, IoEvents::INPUT).await?;
+    }
+    Ok(())
+}
+
+/// Ticks every `period`, e.g. to drive a state machine.
+pub struct Interval {
+    timer: Timer,
+}
+
+impl Interval {
+    pub fn new(period: Duration) -> Result<Interval, Error> {
+        Ok(Interval {
+            timer: Timer::periodic(period)?,
+        })
+    }
+
+    /// Resolves on the next tick, with the number of periods since the last
+    /// one (more than 1 if ticks were missed).
+    pub async fn tick(&self) -> Result<u64, Error> {
+        loop {
+            let expirations = self.timer.expirations()?;
+            if expirations > 0 {
+                break Ok(expirations);
+            }
+            ready(&self.timer, IoEvents::INPUT).await?;
+        }
+    }
+}
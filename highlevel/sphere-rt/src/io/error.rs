@@ -0,0 +1,168 @@
+use core::fmt;
+
+use sphere_lib::util::errno;
+
+pub type Result<T> = core::result::Result<T, Error>;
+
+/// The error type of `io`, usually an errno. Converts from the sphere-lib
+/// error, so `?` works on sphere-lib calls.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Error {
+    repr: Repr,
+}
+
+#[derive(Clone, Debug, PartialEq)]
+enum Repr {
+    Os(i32),
+    Simple(ErrorKind, &'static str),
+    Lib(sphere_lib::Error),
+}
+
+/// A rough category of `Error`, like `std::io::ErrorKind`.
+#[derive(Copy, Clone, Debug, PartialEq, Eq, Hash)]
+#[non_exhaustive]
+pub enum ErrorKind {
+    NotFound,
+    PermissionDenied,
+    ConnectionRefused,
+    ConnectionReset,
+    ConnectionAborted,
+    NotConnected,
+    AddrInUse,
+    AddrNotAvailable,
+    BrokenPipe,
+    AlreadyExists,
+    /// `EAGAIN` on a non-blocking fd.
+    WouldBlock,
+    InvalidInput,
+    InvalidData,
+    TimedOut,
+    /// `write` returned 0.
+    WriteZero,
+    /// `EINTR`, the operation can be retried.
+    Interrupted,
+    /// The storage is full.
+    StorageFull,
+    Other,
+    /// Ended before the expected number of bytes.
+    UnexpectedEof,
+}
+
+impl ErrorKind {
+    fn as_str(self) -> &'static str {
+        match self {
+            ErrorKind::NotFound => "entity not found",
+            ErrorKind::PermissionDenied => "permission denied",
+            ErrorKind::ConnectionRefused => "connection refused",
+            ErrorKind::ConnectionReset => "connection reset",
+            ErrorKind::ConnectionAborted => "connection aborted",
+            ErrorKind::NotConnected => "not connected",
+            ErrorKind::AddrInUse => "address in use",
+            ErrorKind::AddrNotAvailable => "address not available",
+            ErrorKind::BrokenPipe => "broken pipe",
+            ErrorKind::AlreadyExists => "entity already exists",
+            ErrorKind::WouldBlock => "operation would block",
+            ErrorKind::InvalidInput => "invalid input parameter",
+            ErrorKind::InvalidData => "invalid data",
+            ErrorKind::TimedOut => "timed out",
+            ErrorKind::WriteZero => "write zero",
+            ErrorKind::Interrupted => "operation interrupted",
+            ErrorKind::StorageFull => "no storage space",
+            ErrorKind::Other => "other error",
+            ErrorKind::UnexpectedEof => "unexpected end of file",
+        }
+    }
+}
+
+impl Error {
+    pub fn new(kind: ErrorKind, description: &'static str) -> Error {
+        Error {
+            repr: Repr::Simple(kind, description),
+        }
+    }
+
+    pub fn from_raw_os_error(code: i32) -> Error {
+        Error {
+            repr: Repr::Os(code),
+        }
+    }
+
+    /// Wraps errno as left by the last failing call.
+    pub fn last_os_error() -> Error {
+        Error::from_raw_os_error(errno())
+    }
+
+    pub fn raw_os_error(&self) -> Option<i32> {
+        match self.repr {
+            Repr::Os(code) => Some(code),
+            _ => None,
+        }
+    }
+
+    /// The sphere-lib error this was converted from, unless that was an
+    /// errno.
+    pub fn lib_error(&self) -> Option<sphere_lib::Error> {
+        match self.repr {
+            Repr::Lib(error) => Some(error),
+            _ => None,
+        }
+    }
+
+    pub fn kind(&self) -> ErrorKind {
+        match self.repr {
+            Repr::Os(code) => decode_error_kind(code),
+            Repr::Simple(kind, _) => kind,
+            Repr::Lib(sphere_lib::Error::Utf8(_)) => ErrorKind::InvalidData,
+            Repr::Lib(_) => ErrorKind::Other,
+        }
+    }
+}
+
+impl From<sphere_lib::Error> for Error {
+    fn from(error: sphere_lib::Error) -> Error {
+        match error {
+            sphere_lib::Error::Os(code) => Error::from_raw_os_error(code),
+            error => Error {
+                repr: Repr::Lib(error),
+            },
+        }
+    }
+}
+
+impl From<ErrorKind> for Error {
+    fn from(kind: ErrorKind) -> Error {
+        Error::new(kind, kind.as_str())
+    }
+}
+
+impl fmt::Display for Error {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match &self.repr {
+            Repr::Os(code) => write!(f, "{} (os error {})", self.kind().as_str(), code),
+            Repr::Simple(_, description) => f.write_str(description),
+            Repr::Lib(error) => write!(f, "{}", error),
+        }
+    }
+}
+
+// Linux values, as used by musl
+fn decode_error_kind(errno: i32) -> ErrorKind {
+    match errno {
+        1 | 13 => ErrorKind::PermissionDenied, // EPERM, EACCES
+        2 => ErrorKind::NotFound,              // ENOENT
+        4 => ErrorKind::Interrupted,           // EINTR
+        11 => ErrorKind::WouldBlock,           // EAGAIN
+        17 => ErrorKind::AlreadyExists,        // EEXIST
+        22 => ErrorKind::InvalidInput,         // EINVAL
+        28 => ErrorKind::StorageFull,          // ENOSPC
+        32 => ErrorKind::BrokenPipe,           // EPIPE
+        98 => ErrorKind::AddrInUse,            // EADDRINUSE
+        99 => ErrorKind::AddrNotAvailable,     // EADDRNOTAVAIL
+        103 => ErrorKind::ConnectionAborted,   // ECONNABORTED
+        104 => ErrorKind::ConnectionReset,     // ECONNRESET
+        107 => ErrorKind::NotConnected,        // ENOTCONN
+        110 => ErrorKind::TimedOut,            // ETIMEDOUT
+        111 => ErrorKind::ConnectionRefused,   // ECONNREFUSED
+        _ => ErrorKind::Other,
+    }
+}
@@ -0,0 +1,114 @@
+use alloc::vec::Vec;
+
+#[cfg(feature = "application")]
+use sphere_lib::application::ApplicationSocket;
+use sphere_lib::storage::{ImageFile, MutableFile};
+#[cfg(feature = "uart")]
+use sphere_lib::uart::Uart;
+
+use super::{BufRead, Read, Result, Seek, SeekFrom, Write};
+
+/// Reading takes the bytes off the front of the slice.
+impl Read for &[u8] {
+    fn read(&mut self, buf: &mut [u8]) -> Result<usize> {
+        let count = self.len().min(buf.len());
+        let (read, rest) = self.split_at(count);
+        buf[..count].copy_from_slice(read);
+        *self = rest;
+        Ok(count)
+    }
+}
+
+impl BufRead for &[u8] {
+    fn fill_buf(&mut self) -> Result<&[u8]> {
+        Ok(*self)
+    }
+
+    fn consume(&mut self, amount: usize) {
+        *self = &self[amount.min(self.len())..];
+    }
+}
+
+/// Appends to the vector.
+impl Write for Vec<u8> {
+    fn write(&mut self, buf: &[u8]) -> Result<usize> {
+        self.extend_from_slice(buf);
+        Ok(buf.len())
+    }
+
+    fn flush(&mut self) -> Result<()> {
+        Ok(())
+    }
+}
+
+// The sphere-lib types read and write through `&self`, so they implement the
+// traits for references as well, like `std::fs::File`.
+
+macro_rules! impl_read {
+    ($ty:ty) => {
+        impl Read for $ty {
+            fn read(&mut self, buf: &mut [u8]) -> Result<usize> {
+                Ok(<$ty>::read(self, buf)?)
+            }
+        }
+
+        impl Read for &$ty {
+            fn read(&mut self, buf: &mut [u8]) -> Result<usize> {
+                Ok(<$ty>::read(self, buf)?)
+            }
+        }
+    };
+}
+
+// with a no-op `flush`, writes aren't buffered
+macro_rules! impl_write {
+    ($ty:ty) => {
+        impl Write for $ty {
+            fn write(&mut self, buf: &[u8]) -> Result<usize> {
+                Ok(<$ty>::write(self, buf)?)
+            }
+
+            fn flush(&mut self) -> Result<()> {
+                Ok(())
+            }
+        }
+
+        impl Write for &$ty {
+            fn write(&mut self, buf: &[u8]) -> Result<usize> {
+                Ok(<$ty>::write(self, buf)?)
+            }
+
+            fn flush(&mut self) -> Result<()> {
+                Ok(())
+            }
+        }
+    };
+}
+
+#[cfg(feature = "uart")]
+impl_read!(Uart);
+#[cfg(feature = "uart")]
+impl_write!(Uart);
+
+#[cfg(feature = "application")]
+impl_read!(ApplicationSocket);
+#[cfg(feature = "application")]
+impl_write!(ApplicationSocket);
+
+impl_read!(ImageFile);
+
+impl_read!(MutableFile);
+// `flush` doesn't `sync`, like `std::fs::File`
+impl_write!(MutableFile);
+
+impl Seek for MutableFile {
+    fn seek(&mut self, position: SeekFrom) -> Result<u64> {
+        Ok(MutableFile::seek(self, position)?)
+    }
+}
+
+impl Seek for &MutableFile {
+    fn seek(&mut self, position: SeekFrom) -> Result<u64> {
+        Ok(MutableFile::seek(self, position)?)
+    }
+}
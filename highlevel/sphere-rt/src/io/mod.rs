@@ -0,0 +1,299 @@
+//! Traits, helpers and the error type for I/O, like `std::io`.
+//!
+//! The fd-based sphere-lib types implement `Read`, `Write` and `Seek` where
+//! they support it, both owned and by reference, since they read and write
+//! through `&self`.
+
+use alloc::boxed::Box;
+use alloc::string::String;
+use alloc::vec::Vec;
+use core::fmt;
+use core::str;
+
+mod buffered;
+mod cursor;
+mod error;
+mod impls;
+
+pub use self::buffered::{BufReader, BufWriter};
+pub use self::cursor::Cursor;
+pub use self::error::{Error, ErrorKind, Result};
+pub use sphere_lib::storage::SeekFrom;
+
+// what `read_to_end` reads at once
+const CHUNK_SIZE: usize = 512;
+
+pub trait Read {
+    /// Returns the number of bytes read, 0 at the end of the stream.
+    fn read(&mut self, buf: &mut [u8]) -> Result<usize>;
+
+    /// Fills `buf`, or fails with `UnexpectedEof` if the stream ends before.
+    fn read_exact(&mut self, mut buf: &mut [u8]) -> Result<()> {
+        while !buf.is_empty() {
+            match self.read(buf) {
+                Ok(0) => return Err(ErrorKind::UnexpectedEof.into()),
+                Ok(count) => buf = &mut buf[count..],
+                Err(error) if error.kind() == ErrorKind::Interrupted => {}
+                Err(error) => return Err(error),
+            }
+        }
+        Ok(())
+    }
+
+    /// Appends the rest of the stream to `buf`, returns the number of bytes.
+    fn read_to_end(&mut self, buf: &mut Vec<u8>) -> Result<usize> {
+        let start = buf.len();
+        let mut chunk = [0u8; CHUNK_SIZE];
+        loop {
+            match self.read(&mut chunk) {
+                Ok(0) => return Ok(buf.len() - start),
+                Ok(count) => buf.extend_from_slice(&chunk[..count]),
+                Err(error) if error.kind() == ErrorKind::Interrupted => {}
+                Err(error) => return Err(error),
+            }
+        }
+    }
+
+    /// Like `read_to_end`, leaves `buf` as is unless the rest of the stream
+    /// is valid UTF-8.
+    fn read_to_string(&mut self, buf: &mut String) -> Result<usize> {
+        let mut bytes = Vec::new();
+        let count = self.read_to_end(&mut bytes)?;
+        buf.push_str(from_utf8(&bytes)?);
+        Ok(count)
+    }
+
+    fn by_ref(&mut self) -> &mut Self
+    where
+        Self: Sized,
+    {
+        self
+    }
+
+    /// Unbuffered, wrap `self` in a `BufReader` first.
+    fn bytes(self) -> Bytes<Self>
+    where
+        Self: Sized,
+    {
+        Bytes { inner: self }
+    }
+}
+
+pub trait Write {
+    /// Returns the number of bytes written, which may be less than
+    /// `buf.len()`.
+    fn write(&mut self, buf: &[u8]) -> Result<usize>;
+
+    fn flush(&mut self) -> Result<()>;
+
+    fn write_all(&mut self, mut buf: &[u8]) -> Result<()> {
+        while !buf.is_empty() {
+            match self.write(buf) {
+                Ok(0) => return Err(ErrorKind::WriteZero.into()),
+                Ok(count) => buf = &buf[count..],
+                Err(error) if error.kind() == ErrorKind::Interrupted => {}
+                Err(error) => return Err(error),
+            }
+        }
+        Ok(())
+    }
+
+    /// Used by `write!` and `writeln!`.
+    fn write_fmt(&mut self, args: fmt::Arguments) -> Result<()> {
+        // keeps the error, `fmt::Error` can't carry it
+        struct Adapter<'a, T: ?Sized> {
+            inner: &'a mut T,
+            error: Result<()>,
+        }
+
+        impl<T: Write + ?Sized> fmt::Write for Adapter<'_, T> {
+            fn write_str(&mut self, s: &str) -> fmt::Result {
+                self.inner.write_all(s.as_bytes()).map_err(|error| {
+                    self.error = Err(error);
+                    fmt::Error
+                })
+            }
+        }
+
+        let mut adapter = Adapter {
+            inner: self,
+            error: Ok(()),
+        };
+        match fmt::write(&mut adapter, args) {
+            Ok(()) => Ok(()),
+            Err(_) if adapter.error.is_err() => adapter.error,
+            Err(_) => Err(Error::new(ErrorKind::Other, "formatter error")),
+        }
+    }
+
+    fn by_ref(&mut self) -> &mut Self
+    where
+        Self: Sized,
+    {
+        self
+    }
+}
+
+/// A `Read` with an internal buffer, which allows reading lines.
+pub trait BufRead: Read {
+    /// Returns the buffered data, reading more if it's empty. Empty at the
+    /// end of the stream.
+    fn fill_buf(&mut self) -> Result<&[u8]>;
+
+    /// Marks `amount` bytes returned by `fill_buf` as read.
+    fn consume(&mut self, amount: usize);
+
+    /// Appends everything up to and including `byte` to `buf`, returns the
+    /// number of bytes.
+    fn read_until(&mut self, byte: u8, buf: &mut Vec<u8>) -> Result<usize> {
+        let mut read = 0;
+        loop {
+            let (found, used) = {
+                let available = match self.fill_buf() {
+                    Ok(available) => available,
+                    Err(error) if error.kind() == ErrorKind::Interrupted => continue,
+                    Err(error) => return Err(error),
+                };
+                match available.iter().position(|&b| b == byte) {
+                    Some(index) => {
+                        buf.extend_from_slice(&available[..=index]);
+                        (true, index + 1)
+                    }
+                    None => {
+                        buf.extend_from_slice(available);
+                        (false, available.len())
+                    }
+                }
+            };
+            self.consume(used);
+            read += used;
+            if found || used == 0 {
+                return Ok(read);
+            }
+        }
+    }
+
+    /// Appends the next line to `buf`, including the `\n`. Returns the
+    /// number of bytes, 0 at the end of the stream.
+    fn read_line(&mut self, buf: &mut String) -> Result<usize> {
+        let mut bytes = Vec::new();
+        let count = self.read_until(b'\n', &mut bytes)?;
+        buf.push_str(from_utf8(&bytes)?);
+        Ok(count)
+    }
+
+    /// The lines without `\n` or `\r\n`.
+    fn lines(self) -> Lines<Self>
+    where
+        Self: Sized,
+    {
+        Lines { inner: self }
+    }
+}
+
+pub trait Seek {
+    /// Returns the new position from the start of the stream.
+    fn seek(&mut self, position: SeekFrom) -> Result<u64>;
+}
+
+fn from_utf8(bytes: &[u8]) -> Result<&str> {
+    str::from_utf8(bytes).map_err(|error| sphere_lib::Error::Utf8(error).into())
+}
+
+/// See `Read::bytes`.
+pub struct Bytes<R> {
+    inner: R,
+}
+
+impl<R: Read> Iterator for Bytes<R> {
+    type Item = Result<u8>;
+
+    fn next(&mut self) -> Option<Result<u8>> {
+        let mut byte = 0;
+        loop {
+            match self.inner.read(core::slice::from_mut(&mut byte)) {
+                Ok(0) => return None,
+                Ok(_) => return Some(Ok(byte)),
+                Err(error) if error.kind() == ErrorKind::Interrupted => {}
+                Err(error) => return Some(Err(error)),
+            }
+        }
+    }
+}
+
+/// See `BufRead::lines`.
+pub struct Lines<B> {
+    inner: B,
+}
+
+impl<B: BufRead> Iterator for Lines<B> {
+    type Item = Result<String>;
+
+    fn next(&mut self) -> Option<Result<String>> {
+        let mut line = String::new();
+        match self.inner.read_line(&mut line) {
+            Ok(0) => None,
+            Ok(_) => {
+                if line.ends_with('\n') {
+                    line.pop();
+                    if line.ends_with('\r') {
+                        line.pop();
+                    }
+                }
+                Some(Ok(line))
+            }
+            Err(error) => Some(Err(error)),
+        }
+    }
+}
+
+impl<R: Read + ?Sized> Read for &mut R {
+    fn read(&mut self, buf: &mut [u8]) -> Result<usize> {
+        (**self).read(buf)
+    }
+}
+
+impl<R: Read + ?Sized> Read for Box<R> {
+    fn read(&mut self, buf: &mut [u8]) -> Result<usize> {
+        (**self).read(buf)
+    }
+}
+
+impl<W: Write + ?Sized> Write for &mut W {
+    fn write(&mut self, buf: &[u8]) -> Result<usize> {
+        (**self).write(buf)
+    }
+
+    fn flush(&mut self) -> Result<()> {
+        (**self).flush()
+    }
+}
+
+impl<W: Write + ?Sized> Write for Box<W> {
+    fn write(&mut self, buf: &[u8]) -> Result<usize> {
+        (**self).write(buf)
+    }
+
+    fn flush(&mut self) -> Result<()> {
+        (**self).flush()
+    }
+}
+
+impl<B: BufRead + ?Sized> BufRead for &mut B {
+    fn fill_buf(&mut self) -> Result<&[u8]> {
+        (**self).fill_buf()
+    }
+
+    fn consume(&mut self, amount: usize) {
+        (**self).consume(amount)
+    }
+}
+
+impl<S: Seek + ?Sized> Seek for &mut S {
+    fn seek(&mut self, position: SeekFrom) -> Result<u64> {
+        (**self).seek(position)
+    }
+}
+
+#[cfg(test)]
+mod tests;
@@ -0,0 +1,330 @@
+use alloc::string::String;
+use alloc::vec;
+use alloc::vec::Vec;
+use core::fmt;
+
+use super::{
+    BufRead, BufReader, BufWriter, Cursor, Error, ErrorKind, Read, Result, Seek, SeekFrom, Write,
+};
+
+// ENOSPC
+const STORAGE_FULL: i32 = 28;
+
+// accepts at most `chunk` bytes per write and `budget` bytes in total, then
+// fails with ENOSPC
+struct Trickle {
+    written: Vec<u8>,
+    writes: usize,
+    chunk: usize,
+    budget: usize,
+}
+
+impl Trickle {
+    fn new(chunk: usize, budget: usize) -> Trickle {
+        Trickle {
+            written: Vec::new(),
+            writes: 0,
+            chunk,
+            budget,
+        }
+    }
+}
+
+impl Write for Trickle {
+    fn write(&mut self, buf: &[u8]) -> Result<usize> {
+        if self.budget == 0 {
+            return Err(Error::from_raw_os_error(STORAGE_FULL));
+        }
+        let count = buf.len().min(self.chunk).min(self.budget);
+        self.written.extend_from_slice(&buf[..count]);
+        self.writes += 1;
+        self.budget -= count;
+        Ok(count)
+    }
+
+    fn flush(&mut self) -> Result<()> {
+        Ok(())
+    }
+}
+
+// fails every other read with EINTR, starting with the first
+struct Interrupting<R> {
+    inner: R,
+    interrupt: bool,
+}
+
+impl<R: Read> Read for Interrupting<R> {
+    fn read(&mut self, buf: &mut [u8]) -> Result<usize> {
+        self.interrupt = !self.interrupt;
+        if self.interrupt {
+            return Err(ErrorKind::Interrupted.into());
+        }
+        self.inner.read(buf)
+    }
+}
+
+#[test]
+fn cursor_reads_and_seeks() {
+    let mut cursor = Cursor::new(b"0123456789");
+    let mut buf = [0; 4];
+
+    assert_eq!(cursor.read(&mut buf), Ok(4));
+    assert_eq!(&buf, b"0123");
+    assert_eq!(cursor.seek(SeekFrom::End(-3)), Ok(7));
+    assert_eq!(cursor.read(&mut buf), Ok(3));
+    assert_eq!(&buf[..3], b"789");
+    assert_eq!(cursor.seek(SeekFrom::Current(-5)), Ok(5));
+    assert_eq!(cursor.fill_buf(), Ok(&b"56789"[..]));
+
+    // past the end reads nothing
+    assert_eq!(cursor.seek(SeekFrom::Start(20)), Ok(20));
+    assert_eq!(cursor.read(&mut buf), Ok(0));
+}
+
+#[test]
+fn cursor_rejects_negative_and_overflowing_seeks() {
+    let mut cursor = Cursor::new(b"0123");
+    cursor.set_position(2);
+
+    let error = cursor.seek(SeekFrom::Current(-3)).unwrap_err();
+    assert_eq!(error.kind(), ErrorKind::InvalidInput);
+    let error = cursor.seek(SeekFrom::End(i64::MIN)).unwrap_err();
+    assert_eq!(error.kind(), ErrorKind::InvalidInput);
+    // failed seeks don't move the cursor
+    assert_eq!(cursor.position(), 2);
+
+    assert_eq!(cursor.seek(SeekFrom::Current(-2)), Ok(0));
+    assert_eq!(cursor.seek(SeekFrom::Start(u64::MAX)), Ok(u64::MAX));
+    let error = cursor.seek(SeekFrom::Current(1)).unwrap_err();
+    assert_eq!(error.kind(), ErrorKind::InvalidInput);
+    assert_eq!(cursor.position(), u64::MAX);
+    assert_eq!(cursor.read(&mut [0; 4]), Ok(0));
+}
+
+#[test]
+fn cursor_writes_within_a_slice() {
+    let mut buf = [0; 4];
+    let mut cursor = Cursor::new(&mut buf[..]);
+
+    cursor.set_position(1);
+    assert_eq!(cursor.write(b"abcde"), Ok(3));
+    assert_eq!(cursor.write(b"f"), Ok(0));
+    let error = cursor.write_all(b"f").unwrap_err();
+    assert_eq!(error.kind(), ErrorKind::WriteZero);
+    assert_eq!(&buf, b"\0abc");
+}
+
+#[test]
+fn cursor_extends_a_vector() {
+    let mut cursor = Cursor::new(vec![b'x'; 2]);
+
+    cursor.set_position(4);
+    cursor.write_all(b"ab").unwrap();
+    assert_eq!(cursor.get_ref(), b"xx\0\0ab");
+
+    // overwrites, then extends
+    cursor.set_position(5);
+    cursor.write_all(b"cd").unwrap();
+    assert_eq!(cursor.position(), 7);
+    assert_eq!(cursor.into_inner(), b"xx\0\0acd");
+}
+
+#[test]
+fn buf_reader_reads_in_chunks() {
+    let mut reader = BufReader::with_capacity(4, &b"0123456789"[..]);
+    let mut buf = [0; 3];
+
+    assert_eq!(reader.read(&mut buf), Ok(3));
+    assert_eq!(&buf, b"012");
+    assert_eq!(reader.buffer(), b"3");
+    assert_eq!(reader.read(&mut buf), Ok(1));
+    assert_eq!(reader.get_ref(), b"456789");
+
+    // reads that fill the buffer bypass it once it's empty
+    let mut buf = [0; 4];
+    assert_eq!(reader.read(&mut buf), Ok(4));
+    assert_eq!(&buf, b"4567");
+    assert_eq!(reader.buffer(), b"");
+
+    reader.consume(10);
+    assert_eq!(reader.fill_buf(), Ok(&b"89"[..]));
+    reader.consume(10);
+    assert_eq!(reader.buffer(), b"");
+}
+
+#[test]
+fn read_until_spans_refills() {
+    let mut reader = BufReader::with_capacity(3, &b"AT\r\nOK"[..]);
+    let mut buf = Vec::new();
+
+    assert_eq!(reader.read_until(b'\n', &mut buf), Ok(4));
+    assert_eq!(buf, b"AT\r\n");
+    assert_eq!(reader.read_until(b'\n', &mut buf), Ok(2));
+    assert_eq!(buf, b"AT\r\nOK");
+    assert_eq!(reader.read_until(b'\n', &mut buf), Ok(0));
+}
+
+#[test]
+fn read_until_retries_interrupted_reads() {
+    let inner = Interrupting {
+        inner: &b"one\ntwo"[..],
+        interrupt: false,
+    };
+    let mut reader = BufReader::with_capacity(2, inner);
+    let mut line = String::new();
+
+    assert_eq!(reader.read_line(&mut line), Ok(4));
+    assert_eq!(line, "one\n");
+}
+
+#[test]
+fn lines_strip_line_endings() {
+    let input = &b"first\r\nsecond\n\r\n\nlone\r\rlast"[..];
+
+    let lines: Vec<_> = input.lines().map(|line| line.unwrap()).collect();
+    assert_eq!(lines, ["first", "second", "", "", "lone\r\rlast"]);
+}
+
+#[test]
+fn lines_report_invalid_utf8() {
+    let mut lines = BufReader::new(&b"ok\n\xff\n"[..]).lines();
+
+    assert_eq!(lines.next(), Some(Ok(String::from("ok"))));
+    let error = lines.next().unwrap().unwrap_err();
+    assert_eq!(error.kind(), ErrorKind::InvalidData);
+    assert!(error.lib_error().is_some());
+}
+
+#[test]
+fn buf_writer_collects_small_writes() {
+    let mut writer = BufWriter::with_capacity(8, Trickle::new(usize::MAX, usize::MAX));
+
+    writer.write_all(b"abc").unwrap();
+    writer.write_all(b"def").unwrap();
+    assert_eq!(writer.get_ref().writes, 0);
+
+    // doesn't fit, the buffer is written first
+    writer.write_all(b"gh!").unwrap();
+    assert_eq!(writer.get_ref().written, b"abcdef");
+    assert_eq!(writer.buffer(), b"gh!");
+
+    // as big as the buffer, written directly
+    writer.write_all(b"01234567").unwrap();
+    let inner = writer.into_inner().unwrap();
+    assert_eq!(inner.written, b"abcdefgh!01234567");
+    assert_eq!(inner.writes, 3);
+}
+
+#[test]
+fn buf_writer_drains_partial_flushes() {
+    let mut writer = BufWriter::with_capacity(8, Trickle::new(3, 5));
+
+    writer.write_all(b"abcdef").unwrap();
+    let error = writer.flush().unwrap_err();
+    assert_eq!(error.raw_os_error(), Some(STORAGE_FULL));
+    assert_eq!(error.kind(), ErrorKind::StorageFull);
+    // what made it out is dropped from the buffer
+    assert_eq!(writer.get_ref().written, b"abcde");
+    assert_eq!(writer.buffer(), b"f");
+
+    writer.get_mut().budget = usize::MAX;
+    writer.flush().unwrap();
+    assert_eq!(writer.get_ref().written, b"abcdef");
+    assert_eq!(writer.buffer(), b"");
+}
+
+#[test]
+fn buf_writer_flushes_when_dropped() {
+    let mut inner = Vec::new();
+    {
+        let mut writer = BufWriter::new(&mut inner);
+        writeln!(writer, "line {}", 1).unwrap();
+        assert_eq!(writer.buffer(), b"line 1\n");
+    }
+    assert_eq!(inner, b"line 1\n");
+}
+
+#[test]
+fn write_fmt_keeps_the_write_error() {
+    let mut buf = [0; 4];
+    let mut cursor = Cursor::new(&mut buf[..]);
+    let error = write!(cursor, "{}", 12345).unwrap_err();
+    assert_eq!(error.kind(), ErrorKind::WriteZero);
+    assert_eq!(&buf, b"1234");
+
+    let mut trickle = Trickle::new(1, 2);
+    let error = write!(trickle, "{}-{}", 1, 2).unwrap_err();
+    assert_eq!(error, Error::from_raw_os_error(STORAGE_FULL));
+    assert_eq!(trickle.written, b"1-");
+}
+
+#[test]
+fn write_fmt_reports_formatter_errors() {
+    struct Failing;
+
+    impl fmt::Display for Failing {
+        fn fmt(&self, _: &mut fmt::Formatter) -> fmt::Result {
+            Err(fmt::Error)
+        }
+    }
+
+    let error = write!(Vec::new(), "{}", Failing).unwrap_err();
+    assert_eq!(error.kind(), ErrorKind::Other);
+    assert_eq!(error.raw_os_error(), None);
+}
+
+#[test]
+fn maps_errnos_to_kinds() {
+    let kinds = [
+        (1, ErrorKind::PermissionDenied),
+        (2, ErrorKind::NotFound),
+        (4, ErrorKind::Interrupted),
+        (5, ErrorKind::Other),
+        (11, ErrorKind::WouldBlock),
+        (13, ErrorKind::PermissionDenied),
+        (17, ErrorKind::AlreadyExists),
+        (22, ErrorKind::InvalidInput),
+        (28, ErrorKind::StorageFull),
+        (32, ErrorKind::BrokenPipe),
+        (98, ErrorKind::AddrInUse),
+        (99, ErrorKind::AddrNotAvailable),
+        (103, ErrorKind::ConnectionAborted),
+        (104, ErrorKind::ConnectionReset),
+        (107, ErrorKind::NotConnected),
+        (110, ErrorKind::TimedOut),
+        (111, ErrorKind::ConnectionRefused),
+    ];
+    for &(errno, kind) in kinds.iter() {
+        let error = Error::from_raw_os_error(errno);
+        assert_eq!((errno, error.kind()), (errno, kind));
+        assert_eq!(error.raw_os_error(), Some(errno));
+    }
+}
+
+#[test]
+fn converts_sphere_lib_errors() {
+    let error = Error::from(sphere_lib::Error::Os(2));
+    assert_eq!(error, Error::from_raw_os_error(2));
+    assert_eq!(error.lib_error(), None);
+
+    let error = Error::from(sphere_lib::Error::Incomplete(3));
+    assert_eq!(error.kind(), ErrorKind::Other);
+    assert_eq!(error.raw_os_error(), None);
+    assert_eq!(error.lib_error(), Some(sphere_lib::Error::Incomplete(3)));
+}
+
+#[test]
+fn reads_exactly_or_fails() {
+    let mut input = &b"abc"[..];
+    let mut buf = [0; 2];
+
+    input.read_exact(&mut buf).unwrap();
+    assert_eq!(&buf, b"ab");
+    let error = input.read_exact(&mut buf).unwrap_err();
+    assert_eq!(error.kind(), ErrorKind::UnexpectedEof);
+
+    let mut text = String::from("kept");
+    let error = (&b"\xff"[..]).read_to_string(&mut text).unwrap_err();
+    assert_eq!(error.kind(), ErrorKind::InvalidData);
+    assert_eq!(text, "kept");
+}
@@ -10,20 +10,40 @@
 #![feature(global_asm)]
 #![feature(log_syntax)]
 #![feature(llvm_asm)]
+#![feature(panic_info_message)]
 #![feature(trace_macros)]
 
 extern crate alloc as alloc;
 extern crate alloc as alloc_crate;
 
+use core::fmt::Write;
 use core::panic::PanicInfo;
+use core::sync::atomic::{AtomicBool, Ordering};
 
 mod allocator;
+#[cfg(feature = "pool-alloc")]
+use allocator::pool::PoolAllocator;
+#[cfg(not(feature = "pool-alloc"))]
 use allocator::MyAllocator;
 
 extern crate sphere_sys;
-use sphere_sys::Log_Debug;
-
+use sphere_sys::exit;
+
+extern crate sphere_lib;
+use sphere_lib::logging::DebugWriter;
+
+pub mod crash;
+pub mod env;
+pub mod executor;
+pub mod io;
+#[cfg(any(
+    feature = "alloc-stats",
+    feature = "applications",
+    feature = "pool-alloc"
+))]
+pub mod memory;
 pub mod prelude;
+pub mod process;
 
 // re-exports - like libstd does
 pub use alloc_crate::borrow;
@@ -69,7 +89,12 @@ pub use core::u64;
 pub use core::u8;
 pub use core::usize;
 
+pub mod time {
+    pub use sphere_lib::time::*;
+}
+
 pub mod task {
+    pub use crate::executor::{block_on, spawn};
     pub use alloc::task::*;
     pub use core::task::*;
 }
@@ -91,35 +116,63 @@ pub use core::{
 
 pub use core::primitive;
 
-#[cfg(not(test))]
+#[cfg(all(not(test), not(feature = "pool-alloc")))]
 #[global_allocator]
 static GLOBAL: MyAllocator = MyAllocator;
 
+#[cfg(all(not(test), feature = "pool-alloc"))]
+#[global_allocator]
+static GLOBAL: PoolAllocator = PoolAllocator;
+
 extern "C" {
-    fn start();
+    // defined by `entry!`
+    fn __sphere_rt_start() -> i32;
+}
+
+/// Declares the function the app starts with, e.g. `sphere_rt::entry!(start)`.
+/// It may return `()` or `Result<(), E>` with `E: Debug`, see
+/// `process::Termination`. The app exits once it returns.
+#[macro_export]
+macro_rules! entry {
+    ($start:path) => {
+        #[doc(hidden)]
+        #[no_mangle]
+        pub extern "C" fn __sphere_rt_start() -> i32 {
+            $crate::process::Termination::report($start())
+        }
+    };
 }
 
 #[cfg(not(test))]
 #[start]
 #[no_mangle]
-#[allow(unreachable_code)]
-extern "C" fn main(_argc: isize, _argv: *const *const u8) -> ! {
+extern "C" fn main(argc: isize, argv: *const *const u8) -> ! {
     unsafe {
-        start();
+        env::init(argc, argv);
+        process::exit(__sphere_rt_start())
     }
-    loop {}
 }
 
+/// Logs the panic, stores a crash record and exits, so the OS restarts the app.
+/// Doesn't allocate, the heap may be exhausted, unless the panic message
+/// formats a value that does.
 #[cfg(not(test))]
 #[panic_handler]
 fn panic_handler(info: &PanicInfo) -> ! {
-    unsafe {
-        Log_Debug(b"PANIC!\0".as_ptr() as *const i8);
-        let ptr = format!("{:?}\0", info).as_ptr();
-        Log_Debug(ptr as *const i8);
+    static PANICKING: AtomicBool = AtomicBool::new(false);
+
+    // a panic while handling a panic
+    if PANICKING.swap(true, Ordering::SeqCst) {
+        unsafe { exit(crash::EXIT_CODE) };
+    }
+
+    let _ = writeln!(DebugWriter::new(), "PANIC! {}", info);
+
+    if crash::store(info).is_err() {
+        let _ = writeln!(DebugWriter::new(), "Crash record not stored");
     }
 
-    loop {}
+    unsafe { exit(crash::EXIT_CODE) }
 }
 
 #[macro_export]
@@ -154,15 +207,9 @@ macro_rules! dbg {
 }
 
 pub fn _print(msg: core::fmt::Arguments) {
-    unsafe {
-        let zmsg = format!("{}\0", msg);
-        Log_Debug(zmsg.as_ptr() as *const i8);
-    }
+    let _ = DebugWriter::new().write_fmt(msg);
 }
 
 pub fn _println(msg: core::fmt::Arguments) {
-    unsafe {
-        let zmsg = format!("{}\n\0", msg);
-        Log_Debug(zmsg.as_ptr() as *const i8);
-    }
+    let _ = writeln!(DebugWriter::new(), "{}", msg);
 }
@@ -0,0 +1,150 @@
+//! Memory usage of the app.
+//!
+//! Azure Sphere kills apps exceeding their memory limit, without a chance to
+//! clean up. With the `alloc-stats` feature the global allocator counts what
+//! it hands out, see `stats`. With the `applications` feature `os_usage`
+//! reports what the OS accounts to the app, on API sets that support it.
+//!
+//! With the `pool-alloc` feature small allocations come from size-class pools
+//! in a region set aside with `init_pool`, so memory use doesn't depend on
+//! how `malloc` fragments. Until then, and for anything larger than 2 KiB or
+//! once the region is used up, they come from `malloc`.
+
+#[cfg(feature = "alloc-stats")]
+use core::sync::atomic::{AtomicUsize, Ordering};
+
+#[cfg(feature = "pool-alloc")]
+pub use crate::allocator::pool::PoolStats;
+
+#[cfg(feature = "applications")]
+extern crate sphere_sys;
+#[cfg(feature = "applications")]
+use sphere_sys::Applications_GetPeakUserModeMemoryUsageInKB;
+#[cfg(feature = "applications")]
+use sphere_sys::Applications_GetTotalMemoryUsageInKB;
+#[cfg(feature = "applications")]
+use sphere_sys::Applications_GetUserModeMemoryUsageInKB;
+
+/// Heap usage as seen by the global allocator. Bytes are as requested, the
+/// allocator's own overhead isn't included.
+///
+/// To find leaks, compare `current` and `live` before and after something
+/// that should free everything it allocates.
+#[cfg(feature = "alloc-stats")]
+#[derive(Copy, Clone, Debug, Default, PartialEq, Eq)]
+pub struct Stats {
+    /// Bytes allocated and not freed yet.
+    pub current: usize,
+    /// Highest `current` since start or `reset_peak`.
+    pub peak: usize,
+    pub allocations: usize,
+    pub deallocations: usize,
+    /// Allocations the heap couldn't satisfy.
+    pub failures: usize,
+}
+
+#[cfg(feature = "alloc-stats")]
+impl Stats {
+    /// Allocations not freed yet.
+    pub fn live(&self) -> usize {
+        self.allocations - self.deallocations
+    }
+}
+
+#[cfg(feature = "alloc-stats")]
+static CURRENT: AtomicUsize = AtomicUsize::new(0);
+#[cfg(feature = "alloc-stats")]
+static PEAK: AtomicUsize = AtomicUsize::new(0);
+#[cfg(feature = "alloc-stats")]
+static ALLOCATIONS: AtomicUsize = AtomicUsize::new(0);
+#[cfg(feature = "alloc-stats")]
+static DEALLOCATIONS: AtomicUsize = AtomicUsize::new(0);
+#[cfg(feature = "alloc-stats")]
+static FAILURES: AtomicUsize = AtomicUsize::new(0);
+
+#[cfg(feature = "alloc-stats")]
+pub fn stats() -> Stats {
+    // deallocations first, so `live` can't underflow
+    let deallocations = DEALLOCATIONS.load(Ordering::Relaxed);
+    Stats {
+        current: CURRENT.load(Ordering::Relaxed),
+        peak: PEAK.load(Ordering::Relaxed),
+        allocations: ALLOCATIONS.load(Ordering::Relaxed),
+        deallocations,
+        failures: FAILURES.load(Ordering::Relaxed),
+    }
+}
+
+/// Starts tracking the peak anew from the current usage, e.g. to measure a
+/// single operation.
+#[cfg(feature = "alloc-stats")]
+pub fn reset_peak() {
+    PEAK.store(CURRENT.load(Ordering::Relaxed), Ordering::Relaxed);
+}
+
+// called by the global allocator, must not allocate
+
+#[cfg(feature = "alloc-stats")]
+pub(crate) fn record_alloc(size: usize) {
+    ALLOCATIONS.fetch_add(1, Ordering::Relaxed);
+    let current = CURRENT.fetch_add(size, Ordering::Relaxed) + size;
+    PEAK.fetch_max(current, Ordering::Relaxed);
+}
+
+#[cfg(feature = "alloc-stats")]
+pub(crate) fn record_dealloc(size: usize) {
+    DEALLOCATIONS.fetch_add(1, Ordering::Relaxed);
+    CURRENT.fetch_sub(size, Ordering::Relaxed);
+}
+
+#[cfg(feature = "alloc-stats")]
+pub(crate) fn record_failure() {
+    FAILURES.fetch_add(1, Ordering::Relaxed);
+}
+
+/// Memory accounted to the app by the OS, in KiB. This is what counts
+/// against the memory limit.
+#[cfg(feature = "applications")]
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub struct OsUsage {
+    /// Including kernel memory used on behalf of the app, e.g. for sockets.
+    pub total_kb: usize,
+    pub user_mode_kb: usize,
+    /// Highest `user_mode_kb` since the app started.
+    pub peak_user_mode_kb: usize,
+}
+
+#[cfg(feature = "applications")]
+pub fn os_usage() -> OsUsage {
+    unsafe {
+        OsUsage {
+            total_kb: Applications_GetTotalMemoryUsageInKB(),
+            user_mode_kb: Applications_GetUserModeMemoryUsageInKB(),
+            peak_user_mode_kb: Applications_GetPeakUserModeMemoryUsageInKB(),
+        }
+    }
+}
+
+/// Hands `region` to the pools, call it first thing in `start`. Allocations
+/// fail once the pages taken from the region plus what's allocated from
+/// `malloc` would exceed `budget` bytes, so the app can react before the OS
+/// kills it.
+///
+/// Pages are aligned to 2 KiB, up to that much of `region` may go unused.
+///
+/// Panics if called twice.
+///
+/// ```ignore
+/// static mut HEAP: [u8; 64 * 1024] = [0; 64 * 1024];
+///
+/// memory::init_pool(unsafe { &mut HEAP }, 200 * 1024);
+/// ```
+#[cfg(feature = "pool-alloc")]
+pub fn init_pool(region: &'static mut [u8], budget: usize) {
+    crate::allocator::pool::init_pool(region, budget)
+}
+
+#[cfg(feature = "pool-alloc")]
+pub fn pool_stats() -> PoolStats {
+    crate::allocator::pool::pool_stats()
+}
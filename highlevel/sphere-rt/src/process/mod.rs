@@ -0,0 +1,45 @@
+//! Ending the app.
+//!
+//! The OS restarts an app that exits, unless it's being debugged.
+
+use core::fmt::{self, Write};
+
+extern crate sphere_sys;
+
+use sphere_lib::logging::DebugWriter;
+use sphere_lib::shutdown;
+
+/// Exit code of `start` returning an error.
+pub const EXIT_FAILURE: i32 = 1;
+
+/// Ends the app with `code`, without running destructors. Runs the
+/// `sphere_lib::shutdown` hooks first, also when `start` returns.
+pub fn exit(code: i32) -> ! {
+    shutdown::run();
+    unsafe { sphere_sys::exit(code) }
+}
+
+/// What `start` may return, see `entry!`.
+pub trait Termination {
+    /// The exit code.
+    fn report(self) -> i32;
+}
+
+impl Termination for () {
+    fn report(self) -> i32 {
+        0
+    }
+}
+
+/// Logs the error and fails with `EXIT_FAILURE`.
+impl<E: fmt::Debug> Termination for Result<(), E> {
+    fn report(self) -> i32 {
+        match self {
+            Ok(()) => 0,
+            Err(error) => {
+                let _ = writeln!(DebugWriter::new(), "Error: {:?}", error);
+                EXIT_FAILURE
+            }
+        }
+    }
+}
@@ -0,0 +1,6 @@
+// `application` bindings, written by hand rather than generated from a sysroot, see
+// bindings/README.md.
+
+extern "C" {
+    pub fn Application_Connect(componentId: *const std::os::raw::c_char) -> std::os::raw::c_int;
+}
@@ -0,0 +1,12 @@
+// `applications` bindings, written by hand rather than generated from a sysroot, see
+// bindings/README.md.
+
+extern "C" {
+    pub fn Applications_GetTotalMemoryUsageInKB() -> usize;
+}
+extern "C" {
+    pub fn Applications_GetUserModeMemoryUsageInKB() -> usize;
+}
+extern "C" {
+    pub fn Applications_GetPeakUserModeMemoryUsageInKB() -> usize;
+}
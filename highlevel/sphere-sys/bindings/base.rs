@@ -0,0 +1,230 @@
+// `base` bindings, written by hand rather than generated from a sysroot, see
+// bindings/README.md.
+
+pub const SEEK_SET: u32 = 0;
+pub const SEEK_CUR: u32 = 1;
+pub const SEEK_END: u32 = 2;
+pub const EINTR: u32 = 4;
+pub const EAGAIN: u32 = 11;
+pub const EBUSY: u32 = 16;
+pub const EINVAL: u32 = 22;
+pub const ENOSPC: u32 = 28;
+pub const CLOCK_REALTIME: u32 = 0;
+pub const CLOCK_MONOTONIC: u32 = 1;
+pub const SIGEV_SIGNAL: u32 = 0;
+pub const SIGEV_NONE: u32 = 1;
+pub const SIGEV_THREAD: u32 = 2;
+pub const SIGALRM: u32 = 14;
+pub const SIGTERM: u32 = 15;
+pub const SIG_BLOCK: u32 = 0;
+pub const SIG_SETMASK: u32 = 2;
+pub const EPOLL_CLOEXEC: u32 = 524288;
+pub const EPOLLIN: u32 = 1;
+pub const EPOLLOUT: u32 = 4;
+pub const EPOLLERR: u32 = 8;
+pub const EPOLLHUP: u32 = 16;
+pub const EPOLL_CTL_ADD: u32 = 1;
+pub const EPOLL_CTL_DEL: u32 = 2;
+pub const EPOLL_CTL_MOD: u32 = 3;
+pub const TFD_NONBLOCK: u32 = 2048;
+pub const TFD_CLOEXEC: u32 = 524288;
+pub const TFD_TIMER_ABSTIME: u32 = 1;
+pub const EFD_NONBLOCK: u32 = 2048;
+pub const EFD_CLOEXEC: u32 = 524288;
+pub const POLLIN: u32 = 1;
+pub const POLLOUT: u32 = 4;
+pub type off_t = std::os::raw::c_longlong;
+pub type time_t = std::os::raw::c_long;
+pub type clockid_t = std::os::raw::c_int;
+pub type useconds_t = std::os::raw::c_uint;
+pub type timer_t = *mut std::os::raw::c_void;
+#[repr(C)]
+#[derive(Debug, Copy, Clone)]
+pub struct timespec {
+    pub tv_sec: time_t,
+    pub tv_nsec: std::os::raw::c_long,
+}
+#[repr(C)]
+#[derive(Debug, Copy, Clone)]
+pub struct itimerspec {
+    pub it_interval: timespec,
+    pub it_value: timespec,
+}
+#[repr(C)]
+#[derive(Copy, Clone)]
+pub union sigval {
+    pub sival_int: std::os::raw::c_int,
+    pub sival_ptr: *mut std::os::raw::c_void,
+    _bindgen_union_align: u32,
+}
+#[repr(C)]
+#[derive(Copy, Clone)]
+pub struct sigevent {
+    pub sigev_value: sigval,
+    pub sigev_signo: std::os::raw::c_int,
+    pub sigev_notify: std::os::raw::c_int,
+    pub sigev_notify_function: ::core::option::Option<unsafe extern "C" fn(arg1: sigval)>,
+    pub sigev_notify_attributes: *mut std::os::raw::c_void,
+    pub __pad: [std::os::raw::c_char; 44usize],
+}
+#[repr(C)]
+#[derive(Debug, Copy, Clone)]
+pub struct __sigset_t {
+    pub __bits: [std::os::raw::c_ulong; 32usize],
+}
+pub type sigset_t = __sigset_t;
+#[repr(C)]
+#[derive(Copy, Clone)]
+pub union epoll_data {
+    pub ptr: *mut std::os::raw::c_void,
+    pub fd: std::os::raw::c_int,
+    pub u32: u32,
+    pub u64: u64,
+    _bindgen_union_align: u64,
+}
+pub type epoll_data_t = epoll_data;
+#[repr(C)]
+#[derive(Copy, Clone)]
+pub struct epoll_event {
+    pub events: u32,
+    pub data: epoll_data_t,
+}
+pub type nfds_t = std::os::raw::c_ulong;
+#[repr(C)]
+#[derive(Debug, Copy, Clone)]
+pub struct pollfd {
+    pub fd: std::os::raw::c_int,
+    pub events: std::os::raw::c_short,
+    pub revents: std::os::raw::c_short,
+}
+extern "C" {
+    pub fn malloc(arg1: usize) -> *mut std::os::raw::c_void;
+}
+extern "C" {
+    pub fn free(arg1: *mut std::os::raw::c_void);
+}
+extern "C" {
+    pub fn read(arg1: std::os::raw::c_int, arg2: *mut std::os::raw::c_void, arg3: usize) -> isize;
+}
+extern "C" {
+    pub fn write(
+        arg1: std::os::raw::c_int,
+        arg2: *const std::os::raw::c_void,
+        arg3: usize,
+    ) -> isize;
+}
+extern "C" {
+    pub fn close(arg1: std::os::raw::c_int) -> std::os::raw::c_int;
+}
+extern "C" {
+    pub fn lseek(arg1: std::os::raw::c_int, arg2: off_t, arg3: std::os::raw::c_int) -> off_t;
+}
+extern "C" {
+    pub fn fsync(arg1: std::os::raw::c_int) -> std::os::raw::c_int;
+}
+extern "C" {
+    pub fn ftruncate(arg1: std::os::raw::c_int, arg2: off_t) -> std::os::raw::c_int;
+}
+extern "C" {
+    pub fn exit(arg1: std::os::raw::c_int) -> !;
+}
+extern "C" {
+    pub fn _exit(arg1: std::os::raw::c_int) -> !;
+}
+extern "C" {
+    pub fn sleep(arg1: std::os::raw::c_uint) -> std::os::raw::c_uint;
+}
+extern "C" {
+    pub fn usleep(arg1: useconds_t) -> std::os::raw::c_int;
+}
+extern "C" {
+    pub fn __errno_location() -> *mut std::os::raw::c_int;
+}
+extern "C" {
+    pub fn clock_gettime(arg1: clockid_t, arg2: *mut timespec) -> std::os::raw::c_int;
+}
+extern "C" {
+    pub fn timer_create(
+        arg1: clockid_t,
+        arg2: *mut sigevent,
+        arg3: *mut timer_t,
+    ) -> std::os::raw::c_int;
+}
+extern "C" {
+    pub fn timer_delete(arg1: timer_t) -> std::os::raw::c_int;
+}
+extern "C" {
+    pub fn timer_settime(
+        arg1: timer_t,
+        arg2: std::os::raw::c_int,
+        arg3: *const itimerspec,
+        arg4: *mut itimerspec,
+    ) -> std::os::raw::c_int;
+}
+extern "C" {
+    pub fn signal(
+        arg1: std::os::raw::c_int,
+        arg2: ::core::option::Option<unsafe extern "C" fn(arg1: std::os::raw::c_int)>,
+    ) -> ::core::option::Option<unsafe extern "C" fn(arg1: std::os::raw::c_int)>;
+}
+extern "C" {
+    pub fn sigemptyset(arg1: *mut sigset_t) -> std::os::raw::c_int;
+}
+extern "C" {
+    pub fn sigaddset(arg1: *mut sigset_t, arg2: std::os::raw::c_int) -> std::os::raw::c_int;
+}
+extern "C" {
+    pub fn sigprocmask(
+        arg1: std::os::raw::c_int,
+        arg2: *const sigset_t,
+        arg3: *mut sigset_t,
+    ) -> std::os::raw::c_int;
+}
+extern "C" {
+    pub fn epoll_create1(arg1: std::os::raw::c_int) -> std::os::raw::c_int;
+}
+extern "C" {
+    pub fn epoll_ctl(
+        arg1: std::os::raw::c_int,
+        arg2: std::os::raw::c_int,
+        arg3: std::os::raw::c_int,
+        arg4: *mut epoll_event,
+    ) -> std::os::raw::c_int;
+}
+extern "C" {
+    pub fn epoll_wait(
+        arg1: std::os::raw::c_int,
+        arg2: *mut epoll_event,
+        arg3: std::os::raw::c_int,
+        arg4: std::os::raw::c_int,
+    ) -> std::os::raw::c_int;
+}
+extern "C" {
+    pub fn timerfd_create(
+        arg1: std::os::raw::c_int,
+        arg2: std::os::raw::c_int,
+    ) -> std::os::raw::c_int;
+}
+extern "C" {
+    pub fn timerfd_settime(
+        arg1: std::os::raw::c_int,
+        arg2: std::os::raw::c_int,
+        arg3: *const itimerspec,
+        arg4: *mut itimerspec,
+    ) -> std::os::raw::c_int;
+}
+extern "C" {
+    pub fn timerfd_gettime(
+        arg1: std::os::raw::c_int,
+        arg2: *mut itimerspec,
+    ) -> std::os::raw::c_int;
+}
+extern "C" {
+    pub fn eventfd(arg1: std::os::raw::c_uint, arg2: std::os::raw::c_int) -> std::os::raw::c_int;
+}
+extern "C" {
+    pub fn poll(arg1: *mut pollfd, arg2: nfds_t, arg3: std::os::raw::c_int) -> std::os::raw::c_int;
+}
+extern "C" {
+    pub fn Log_Debug(fmt: *const std::os::raw::c_char, ...) -> std::os::raw::c_int;
+}
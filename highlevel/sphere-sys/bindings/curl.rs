@@ -0,0 +1,90 @@
+// `curl` bindings, written by hand rather than generated from a sysroot, see
+// bindings/README.md.
+
+pub const CURL_GLOBAL_SSL: u32 = 1;
+pub const CURL_GLOBAL_WIN32: u32 = 2;
+pub const CURL_GLOBAL_ALL: u32 = 3;
+#[repr(C)]
+#[derive(Debug, Copy, Clone)]
+pub struct Curl_easy {
+    _unused: [u8; 0],
+}
+pub type CURL = Curl_easy;
+#[repr(C)]
+#[derive(Debug, Copy, Clone)]
+pub struct curl_slist {
+    pub data: *mut std::os::raw::c_char,
+    pub next: *mut curl_slist,
+}
+pub type curl_write_callback = ::core::option::Option<
+    unsafe extern "C" fn(
+        buffer: *mut std::os::raw::c_char,
+        size: usize,
+        nitems: usize,
+        outstream: *mut std::os::raw::c_void,
+    ) -> usize,
+>;
+pub type curl_ssl_ctx_callback = ::core::option::Option<
+    unsafe extern "C" fn(
+        curl: *mut CURL,
+        ssl_ctx: *mut std::os::raw::c_void,
+        userptr: *mut std::os::raw::c_void,
+    ) -> CURLcode,
+>;
+pub const CURLcode_CURLE_OK: CURLcode = 0;
+pub const CURLcode_CURLE_UNSUPPORTED_PROTOCOL: CURLcode = 1;
+pub const CURLcode_CURLE_FAILED_INIT: CURLcode = 2;
+pub const CURLcode_CURLE_URL_MALFORMAT: CURLcode = 3;
+pub const CURLcode_CURLE_COULDNT_RESOLVE_HOST: CURLcode = 6;
+pub const CURLcode_CURLE_COULDNT_CONNECT: CURLcode = 7;
+pub const CURLcode_CURLE_WRITE_ERROR: CURLcode = 23;
+pub const CURLcode_CURLE_OPERATION_TIMEDOUT: CURLcode = 28;
+pub const CURLcode_CURLE_SSL_CONNECT_ERROR: CURLcode = 35;
+pub const CURLcode_CURLE_PEER_FAILED_VERIFICATION: CURLcode = 60;
+pub type CURLcode = u32;
+pub const CURLoption_CURLOPT_WRITEDATA: CURLoption = 10001;
+pub const CURLoption_CURLOPT_URL: CURLoption = 10002;
+pub const CURLoption_CURLOPT_POSTFIELDS: CURLoption = 10015;
+pub const CURLoption_CURLOPT_HTTPHEADER: CURLoption = 10023;
+pub const CURLoption_CURLOPT_CUSTOMREQUEST: CURLoption = 10036;
+pub const CURLoption_CURLOPT_CAINFO: CURLoption = 10065;
+pub const CURLoption_CURLOPT_WRITEFUNCTION: CURLoption = 20011;
+pub const CURLoption_CURLOPT_SSL_CTX_FUNCTION: CURLoption = 20108;
+pub const CURLoption_CURLOPT_VERBOSE: CURLoption = 41;
+pub const CURLoption_CURLOPT_SSL_VERIFYHOST: CURLoption = 81;
+pub type CURLoption = u32;
+pub const CURLINFO_CURLINFO_RESPONSE_CODE: CURLINFO = 2097154;
+pub type CURLINFO = u32;
+extern "C" {
+    pub fn curl_slist_append(
+        arg1: *mut curl_slist,
+        arg2: *const std::os::raw::c_char,
+    ) -> *mut curl_slist;
+}
+extern "C" {
+    pub fn curl_slist_free_all(arg1: *mut curl_slist);
+}
+extern "C" {
+    pub fn curl_global_init(flags: std::os::raw::c_long) -> CURLcode;
+}
+extern "C" {
+    pub fn curl_global_cleanup();
+}
+extern "C" {
+    pub fn curl_easy_init() -> *mut CURL;
+}
+extern "C" {
+    pub fn curl_easy_setopt(curl: *mut CURL, option: CURLoption, ...) -> CURLcode;
+}
+extern "C" {
+    pub fn curl_easy_perform(curl: *mut CURL) -> CURLcode;
+}
+extern "C" {
+    pub fn curl_easy_cleanup(curl: *mut CURL);
+}
+extern "C" {
+    pub fn curl_easy_getinfo(curl: *mut CURL, info: CURLINFO, ...) -> CURLcode;
+}
+extern "C" {
+    pub fn DeviceAuth_SslCtxFunc(sslctx: *mut std::os::raw::c_void) -> CURLcode;
+}
@@ -0,0 +1,33 @@
+// `gpio` bindings, written by hand rather than generated from a sysroot, see
+// bindings/README.md.
+
+pub type GPIO_Id = std::os::raw::c_int;
+pub type GPIO_OutputMode_Type = u8;
+pub const GPIO_OutputMode_PushPull: GPIO_OutputMode_Type = 0;
+pub const GPIO_OutputMode_OpenDrain: GPIO_OutputMode_Type = 1;
+pub const GPIO_OutputMode_OpenSource: GPIO_OutputMode_Type = 2;
+pub type GPIO_Value_Type = u8;
+pub const GPIO_Value_Low: GPIO_Value_Type = 0;
+pub const GPIO_Value_High: GPIO_Value_Type = 1;
+extern "C" {
+    pub fn GPIO_OpenAsInput(gpioId: GPIO_Id) -> std::os::raw::c_int;
+}
+extern "C" {
+    pub fn GPIO_OpenAsOutput(
+        gpioId: GPIO_Id,
+        outputMode: GPIO_OutputMode_Type,
+        initialValue: GPIO_Value_Type,
+    ) -> std::os::raw::c_int;
+}
+extern "C" {
+    pub fn GPIO_GetValue(
+        gpioFd: std::os::raw::c_int,
+        outValue: *mut GPIO_Value_Type,
+    ) -> std::os::raw::c_int;
+}
+extern "C" {
+    pub fn GPIO_SetValue(
+        gpioFd: std::os::raw::c_int,
+        value: GPIO_Value_Type,
+    ) -> std::os::raw::c_int;
+}
@@ -0,0 +1,54 @@
+// `i2c` bindings, written by hand rather than generated from a sysroot, see
+// bindings/README.md.
+
+pub const I2C_STRUCTS_VERSION: u32 = 1;
+pub const I2C_BUS_SPEED_STANDARD: u32 = 100000;
+pub const I2C_BUS_SPEED_FAST: u32 = 400000;
+pub const I2C_BUS_SPEED_FAST_PLUS: u32 = 1000000;
+pub type I2C_InterfaceId = std::os::raw::c_int;
+pub type I2C_DeviceAddress = u32;
+pub type I2C_BusSpeed = u32;
+extern "C" {
+    pub fn I2CMaster_Open(id: I2C_InterfaceId) -> std::os::raw::c_int;
+}
+extern "C" {
+    pub fn I2CMaster_SetBusSpeed(
+        fd: std::os::raw::c_int,
+        speedInHz: I2C_BusSpeed,
+    ) -> std::os::raw::c_int;
+}
+extern "C" {
+    pub fn I2CMaster_SetTimeout(fd: std::os::raw::c_int, timeoutInMs: u32) -> std::os::raw::c_int;
+}
+extern "C" {
+    pub fn I2CMaster_Write(
+        fd: std::os::raw::c_int,
+        address: I2C_DeviceAddress,
+        data: *const u8,
+        length: usize,
+    ) -> isize;
+}
+extern "C" {
+    pub fn I2CMaster_WriteThenRead(
+        fd: std::os::raw::c_int,
+        address: I2C_DeviceAddress,
+        writeData: *const u8,
+        lenWriteData: usize,
+        readData: *mut u8,
+        lenReadData: usize,
+    ) -> isize;
+}
+extern "C" {
+    pub fn I2CMaster_Read(
+        fd: std::os::raw::c_int,
+        address: I2C_DeviceAddress,
+        buffer: *mut u8,
+        maxLength: usize,
+    ) -> isize;
+}
+extern "C" {
+    pub fn I2CMaster_SetDefaultTargetAddress(
+        fd: std::os::raw::c_int,
+        address: I2C_DeviceAddress,
+    ) -> std::os::raw::c_int;
+}
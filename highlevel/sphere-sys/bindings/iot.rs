@@ -0,0 +1,187 @@
+// `iot` bindings, written by hand rather than generated from a sysroot, see
+// bindings/README.md.
+
+#[repr(C)]
+#[derive(Debug, Copy, Clone)]
+pub struct IOTHUB_CLIENT_CORE_LL_HANDLE_DATA_TAG {
+    _unused: [u8; 0],
+}
+pub type IOTHUB_DEVICE_CLIENT_LL_HANDLE = *mut IOTHUB_CLIENT_CORE_LL_HANDLE_DATA_TAG;
+#[repr(C)]
+#[derive(Debug, Copy, Clone)]
+pub struct IOTHUB_MESSAGE_HANDLE_DATA_TAG {
+    _unused: [u8; 0],
+}
+pub type IOTHUB_MESSAGE_HANDLE = *mut IOTHUB_MESSAGE_HANDLE_DATA_TAG;
+#[repr(C)]
+#[derive(Debug, Copy, Clone)]
+pub struct TRANSPORT_PROVIDER_TAG {
+    _unused: [u8; 0],
+}
+pub type TRANSPORT_PROVIDER = TRANSPORT_PROVIDER_TAG;
+pub type IOTHUB_CLIENT_TRANSPORT_PROVIDER =
+    ::core::option::Option<unsafe extern "C" fn() -> *const TRANSPORT_PROVIDER>;
+pub const IOTHUB_CLIENT_RESULT_TAG_IOTHUB_CLIENT_OK: IOTHUB_CLIENT_RESULT_TAG = 0;
+pub const IOTHUB_CLIENT_RESULT_TAG_IOTHUB_CLIENT_INVALID_ARG: IOTHUB_CLIENT_RESULT_TAG = 1;
+pub const IOTHUB_CLIENT_RESULT_TAG_IOTHUB_CLIENT_ERROR: IOTHUB_CLIENT_RESULT_TAG = 2;
+pub const IOTHUB_CLIENT_RESULT_TAG_IOTHUB_CLIENT_INVALID_SIZE: IOTHUB_CLIENT_RESULT_TAG = 3;
+pub const IOTHUB_CLIENT_RESULT_TAG_IOTHUB_CLIENT_INDEFINITE_TIME: IOTHUB_CLIENT_RESULT_TAG = 4;
+pub type IOTHUB_CLIENT_RESULT_TAG = u32;
+pub use self::IOTHUB_CLIENT_RESULT_TAG as IOTHUB_CLIENT_RESULT;
+pub const IOTHUB_CLIENT_CONFIRMATION_RESULT_TAG_IOTHUB_CLIENT_CONFIRMATION_OK:
+    IOTHUB_CLIENT_CONFIRMATION_RESULT_TAG = 0;
+pub type IOTHUB_CLIENT_CONFIRMATION_RESULT_TAG = u32;
+pub use self::IOTHUB_CLIENT_CONFIRMATION_RESULT_TAG as IOTHUB_CLIENT_CONFIRMATION_RESULT;
+pub const IOTHUB_CLIENT_CONNECTION_STATUS_TAG_IOTHUB_CLIENT_CONNECTION_AUTHENTICATED:
+    IOTHUB_CLIENT_CONNECTION_STATUS_TAG = 0;
+pub const IOTHUB_CLIENT_CONNECTION_STATUS_TAG_IOTHUB_CLIENT_CONNECTION_UNAUTHENTICATED:
+    IOTHUB_CLIENT_CONNECTION_STATUS_TAG = 1;
+pub type IOTHUB_CLIENT_CONNECTION_STATUS_TAG = u32;
+pub use self::IOTHUB_CLIENT_CONNECTION_STATUS_TAG as IOTHUB_CLIENT_CONNECTION_STATUS;
+pub const IOTHUB_CLIENT_CONNECTION_STATUS_REASON_TAG_IOTHUB_CLIENT_CONNECTION_OK:
+    IOTHUB_CLIENT_CONNECTION_STATUS_REASON_TAG = 6;
+pub type IOTHUB_CLIENT_CONNECTION_STATUS_REASON_TAG = u32;
+pub use self::IOTHUB_CLIENT_CONNECTION_STATUS_REASON_TAG as IOTHUB_CLIENT_CONNECTION_STATUS_REASON;
+pub const DEVICE_TWIN_UPDATE_STATE_TAG_DEVICE_TWIN_UPDATE_COMPLETE: DEVICE_TWIN_UPDATE_STATE_TAG =
+    0;
+pub const DEVICE_TWIN_UPDATE_STATE_TAG_DEVICE_TWIN_UPDATE_PARTIAL: DEVICE_TWIN_UPDATE_STATE_TAG = 1;
+pub type DEVICE_TWIN_UPDATE_STATE_TAG = u32;
+pub use self::DEVICE_TWIN_UPDATE_STATE_TAG as DEVICE_TWIN_UPDATE_STATE;
+pub const IOTHUB_SECURITY_TYPE_TAG_IOTHUB_SECURITY_TYPE_UNKNOWN: IOTHUB_SECURITY_TYPE_TAG = 0;
+pub const IOTHUB_SECURITY_TYPE_TAG_IOTHUB_SECURITY_TYPE_SAS: IOTHUB_SECURITY_TYPE_TAG = 1;
+pub const IOTHUB_SECURITY_TYPE_TAG_IOTHUB_SECURITY_TYPE_X509: IOTHUB_SECURITY_TYPE_TAG = 2;
+pub type IOTHUB_SECURITY_TYPE_TAG = u32;
+pub use self::IOTHUB_SECURITY_TYPE_TAG as IOTHUB_SECURITY_TYPE;
+pub type IOTHUB_CLIENT_EVENT_CONFIRMATION_CALLBACK = ::core::option::Option<
+    unsafe extern "C" fn(
+        result: IOTHUB_CLIENT_CONFIRMATION_RESULT,
+        userContextCallback: *mut std::os::raw::c_void,
+    ),
+>;
+pub type IOTHUB_CLIENT_CONNECTION_STATUS_CALLBACK = ::core::option::Option<
+    unsafe extern "C" fn(
+        result: IOTHUB_CLIENT_CONNECTION_STATUS,
+        reason: IOTHUB_CLIENT_CONNECTION_STATUS_REASON,
+        userContextCallback: *mut std::os::raw::c_void,
+    ),
+>;
+pub type IOTHUB_CLIENT_DEVICE_TWIN_CALLBACK = ::core::option::Option<
+    unsafe extern "C" fn(
+        update_state: DEVICE_TWIN_UPDATE_STATE,
+        payLoad: *const std::os::raw::c_uchar,
+        size: usize,
+        userContextCallback: *mut std::os::raw::c_void,
+    ),
+>;
+pub type IOTHUB_CLIENT_DEVICE_METHOD_CALLBACK_ASYNC = ::core::option::Option<
+    unsafe extern "C" fn(
+        method_name: *const std::os::raw::c_char,
+        payload: *const std::os::raw::c_uchar,
+        size: usize,
+        response: *mut *mut std::os::raw::c_uchar,
+        response_size: *mut usize,
+        userContextCallback: *mut std::os::raw::c_void,
+    ) -> std::os::raw::c_int,
+>;
+extern "C" {
+    pub fn IoTHub_Init() -> std::os::raw::c_int;
+}
+extern "C" {
+    pub fn IoTHub_Deinit();
+}
+extern "C" {
+    pub fn iothub_security_init(sec_type: IOTHUB_SECURITY_TYPE) -> std::os::raw::c_int;
+}
+extern "C" {
+    pub fn MQTT_Protocol() -> *const TRANSPORT_PROVIDER;
+}
+extern "C" {
+    pub fn IoTHubDeviceClient_LL_CreateFromConnectionString(
+        connectionString: *const std::os::raw::c_char,
+        protocol: IOTHUB_CLIENT_TRANSPORT_PROVIDER,
+    ) -> IOTHUB_DEVICE_CLIENT_LL_HANDLE;
+}
+extern "C" {
+    pub fn IoTHubDeviceClient_LL_CreateFromDeviceAuth(
+        iothub_uri: *const std::os::raw::c_char,
+        device_id: *const std::os::raw::c_char,
+        protocol: IOTHUB_CLIENT_TRANSPORT_PROVIDER,
+    ) -> IOTHUB_DEVICE_CLIENT_LL_HANDLE;
+}
+extern "C" {
+    pub fn IoTHubDeviceClient_LL_Destroy(iotHubClientHandle: IOTHUB_DEVICE_CLIENT_LL_HANDLE);
+}
+extern "C" {
+    pub fn IoTHubDeviceClient_LL_SendEventAsync(
+        iotHubClientHandle: IOTHUB_DEVICE_CLIENT_LL_HANDLE,
+        eventMessageHandle: IOTHUB_MESSAGE_HANDLE,
+        eventConfirmationCallback: IOTHUB_CLIENT_EVENT_CONFIRMATION_CALLBACK,
+        userContextCallback: *mut std::os::raw::c_void,
+    ) -> IOTHUB_CLIENT_RESULT;
+}
+extern "C" {
+    pub fn IoTHubDeviceClient_LL_SetConnectionStatusCallback(
+        iotHubClientHandle: IOTHUB_DEVICE_CLIENT_LL_HANDLE,
+        connectionStatusCallback: IOTHUB_CLIENT_CONNECTION_STATUS_CALLBACK,
+        userContextCallback: *mut std::os::raw::c_void,
+    ) -> IOTHUB_CLIENT_RESULT;
+}
+extern "C" {
+    pub fn IoTHubDeviceClient_LL_SetDeviceTwinCallback(
+        iotHubClientHandle: IOTHUB_DEVICE_CLIENT_LL_HANDLE,
+        deviceTwinCallback: IOTHUB_CLIENT_DEVICE_TWIN_CALLBACK,
+        userContextCallback: *mut std::os::raw::c_void,
+    ) -> IOTHUB_CLIENT_RESULT;
+}
+extern "C" {
+    pub fn IoTHubDeviceClient_LL_SetDeviceMethodCallback(
+        iotHubClientHandle: IOTHUB_DEVICE_CLIENT_LL_HANDLE,
+        deviceMethodCallback: IOTHUB_CLIENT_DEVICE_METHOD_CALLBACK_ASYNC,
+        userContextCallback: *mut std::os::raw::c_void,
+    ) -> IOTHUB_CLIENT_RESULT;
+}
+extern "C" {
+    pub fn IoTHubDeviceClient_LL_DoWork(iotHubClientHandle: IOTHUB_DEVICE_CLIENT_LL_HANDLE);
+}
+extern "C" {
+    pub fn IoTHubDeviceClient_LL_SetOption(
+        iotHubClientHandle: IOTHUB_DEVICE_CLIENT_LL_HANDLE,
+        optionName: *const std::os::raw::c_char,
+        value: *const std::os::raw::c_void,
+    ) -> IOTHUB_CLIENT_RESULT;
+}
+extern "C" {
+    pub fn IoTHubMessage_CreateFromString(
+        source: *const std::os::raw::c_char,
+    ) -> IOTHUB_MESSAGE_HANDLE;
+}
+extern "C" {
+    pub fn IoTHubMessage_Destroy(iotHubMessageHandle: IOTHUB_MESSAGE_HANDLE);
+}
+pub const AZURE_SPHERE_PROV_RESULT_AZURE_SPHERE_PROV_RESULT_OK: AZURE_SPHERE_PROV_RESULT = 0;
+pub const AZURE_SPHERE_PROV_RESULT_AZURE_SPHERE_PROV_RESULT_INVALID_PARAM:
+    AZURE_SPHERE_PROV_RESULT = 1;
+pub const AZURE_SPHERE_PROV_RESULT_AZURE_SPHERE_PROV_RESULT_NETWORK_NOT_READY:
+    AZURE_SPHERE_PROV_RESULT = 2;
+pub const AZURE_SPHERE_PROV_RESULT_AZURE_SPHERE_PROV_RESULT_DEVICEAUTH_NOT_READY:
+    AZURE_SPHERE_PROV_RESULT = 3;
+pub const AZURE_SPHERE_PROV_RESULT_AZURE_SPHERE_PROV_RESULT_PROV_DEVICE_ERROR:
+    AZURE_SPHERE_PROV_RESULT = 4;
+pub const AZURE_SPHERE_PROV_RESULT_AZURE_SPHERE_PROV_RESULT_GENERIC_ERROR:
+    AZURE_SPHERE_PROV_RESULT = 5;
+pub type AZURE_SPHERE_PROV_RESULT = u32;
+pub type PROV_DEVICE_RESULT = u32;
+#[repr(C)]
+#[derive(Debug, Copy, Clone)]
+pub struct AZURE_SPHERE_PROV_RETURN_VALUE {
+    pub result: AZURE_SPHERE_PROV_RESULT,
+    pub prov_device_error: PROV_DEVICE_RESULT,
+    pub iothub_client_error: IOTHUB_CLIENT_RESULT,
+}
+extern "C" {
+    pub fn IoTHubDeviceClient_LL_CreateWithAzureSphereDeviceAuthProvisioning(
+        idScope: *const std::os::raw::c_char,
+        timeout: std::os::raw::c_uint,
+        handle: *mut IOTHUB_DEVICE_CLIENT_LL_HANDLE,
+    ) -> AZURE_SPHERE_PROV_RETURN_VALUE;
+}
@@ -0,0 +1,17 @@
+// `networking` bindings, written by hand rather than generated from a sysroot, see
+// bindings/README.md.
+
+pub const NETWORKING_STRUCTS_VERSION: u32 = 1;
+pub const WIFICONFIG_STRUCTS_VERSION: u32 = 1;
+extern "C" {
+    pub fn Networking_IsNetworkingReady(outIsNetworkingReady: *mut bool) -> std::os::raw::c_int;
+}
+extern "C" {
+    pub fn Networking_GetInterfaceCount() -> isize;
+}
+extern "C" {
+    pub fn Networking_SetInterfaceState(
+        networkInterfaceName: *const std::os::raw::c_char,
+        isEnabled: bool,
+    ) -> std::os::raw::c_int;
+}
@@ -0,0 +1,100 @@
+// `spi` bindings, written by hand rather than generated from a sysroot, see
+// bindings/README.md.
+
+pub const SPI_STRUCTS_VERSION: u32 = 1;
+pub type SPI_InterfaceId = std::os::raw::c_int;
+pub type SPI_ChipSelectId = std::os::raw::c_int;
+pub type SPI_ChipSelectPolarity = u32;
+pub const SPI_ChipSelectPolarity_Invalid: SPI_ChipSelectPolarity = 0;
+pub const SPI_ChipSelectPolarity_ActiveLow: SPI_ChipSelectPolarity = 1;
+pub const SPI_ChipSelectPolarity_ActiveHigh: SPI_ChipSelectPolarity = 2;
+pub type SPI_Mode = u32;
+pub const SPI_Mode_Invalid: SPI_Mode = 0;
+pub const SPI_Mode_0: SPI_Mode = 1;
+pub const SPI_Mode_1: SPI_Mode = 2;
+pub const SPI_Mode_2: SPI_Mode = 3;
+pub const SPI_Mode_3: SPI_Mode = 4;
+pub type SPI_BitOrder = u32;
+pub const SPI_BitOrder_Invalid: SPI_BitOrder = 0;
+pub const SPI_BitOrder_LsbFirst: SPI_BitOrder = 1;
+pub const SPI_BitOrder_MsbFirst: SPI_BitOrder = 2;
+pub type SPI_TransferFlags = u32;
+pub const SPI_TransferFlags_None: SPI_TransferFlags = 0;
+pub const SPI_TransferFlags_Read: SPI_TransferFlags = 1;
+pub const SPI_TransferFlags_Write: SPI_TransferFlags = 2;
+#[repr(C)]
+#[derive(Debug, Copy, Clone)]
+pub struct z__SPIMaster_Config_Base {
+    pub z__magicAndVersion: u32,
+}
+#[repr(C)]
+#[derive(Debug, Copy, Clone)]
+pub struct z__SPIMaster_Config_v1 {
+    pub z__magicAndVersion: u32,
+    pub csPolarity: SPI_ChipSelectPolarity,
+}
+pub type SPIMaster_Config = z__SPIMaster_Config_v1;
+#[repr(C)]
+#[derive(Debug, Copy, Clone)]
+pub struct z__SPIMaster_Transfer_Base {
+    pub z__magicAndVersion: u32,
+}
+#[repr(C)]
+#[derive(Debug, Copy, Clone)]
+pub struct z__SPIMaster_Transfer_v1 {
+    pub z__magicAndVersion: u32,
+    pub flags: SPI_TransferFlags,
+    pub writeData: *const u8,
+    pub readData: *mut u8,
+    pub length: usize,
+}
+pub type SPIMaster_Transfer = z__SPIMaster_Transfer_v1;
+extern "C" {
+    pub fn z__SPIMaster_InitConfig(
+        config: *mut z__SPIMaster_Config_Base,
+        structVersion: u32,
+    ) -> std::os::raw::c_int;
+}
+extern "C" {
+    pub fn z__SPIMaster_Open(
+        interfaceId: SPI_InterfaceId,
+        chipSelectId: SPI_ChipSelectId,
+        config: *const z__SPIMaster_Config_Base,
+    ) -> std::os::raw::c_int;
+}
+extern "C" {
+    pub fn z__SPIMaster_InitTransfers(
+        transfers: *mut z__SPIMaster_Transfer_Base,
+        transferCount: usize,
+        transferStructVersion: u32,
+    ) -> std::os::raw::c_int;
+}
+extern "C" {
+    pub fn z__SPIMaster_TransferSequential(
+        fd: std::os::raw::c_int,
+        transfers: *const z__SPIMaster_Transfer_Base,
+        transferCount: usize,
+        transferStructVersion: u32,
+    ) -> isize;
+}
+extern "C" {
+    pub fn SPIMaster_SetBusSpeed(fd: std::os::raw::c_int, speedInHz: u32) -> std::os::raw::c_int;
+}
+extern "C" {
+    pub fn SPIMaster_SetMode(fd: std::os::raw::c_int, mode: SPI_Mode) -> std::os::raw::c_int;
+}
+extern "C" {
+    pub fn SPIMaster_SetBitOrder(
+        fd: std::os::raw::c_int,
+        order: SPI_BitOrder,
+    ) -> std::os::raw::c_int;
+}
+extern "C" {
+    pub fn SPIMaster_WriteThenRead(
+        fd: std::os::raw::c_int,
+        writeData: *const u8,
+        lenWriteData: usize,
+        readData: *mut u8,
+        lenReadData: usize,
+    ) -> isize;
+}
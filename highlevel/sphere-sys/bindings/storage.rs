@@ -0,0 +1,19 @@
+// `storage` bindings, written by hand rather than generated from a sysroot, see
+// bindings/README.md.
+
+extern "C" {
+    pub fn Storage_OpenFileInImagePackage(
+        relativePath: *const std::os::raw::c_char,
+    ) -> std::os::raw::c_int;
+}
+extern "C" {
+    pub fn Storage_GetAbsolutePathInImagePackage(
+        relativePath: *const std::os::raw::c_char,
+    ) -> *mut std::os::raw::c_char;
+}
+extern "C" {
+    pub fn Storage_OpenMutableFile() -> std::os::raw::c_int;
+}
+extern "C" {
+    pub fn Storage_DeleteMutableFile() -> std::os::raw::c_int;
+}
@@ -0,0 +1,52 @@
+// `uart` bindings, written by hand rather than generated from a sysroot, see
+// bindings/README.md.
+
+pub const UART_STRUCTS_VERSION: u32 = 1;
+pub type UART_Id = std::os::raw::c_int;
+pub type UART_BaudRate_Type = u32;
+pub type UART_BlockingMode_Type = u8;
+pub const UART_BlockingMode_NonBlocking: UART_BlockingMode_Type = 0;
+pub const UART_BlockingMode_Blocking: UART_BlockingMode_Type = 1;
+pub type UART_DataBits_Type = u8;
+pub const UART_DataBits_Five: UART_DataBits_Type = 5;
+pub const UART_DataBits_Six: UART_DataBits_Type = 6;
+pub const UART_DataBits_Seven: UART_DataBits_Type = 7;
+pub const UART_DataBits_Eight: UART_DataBits_Type = 8;
+pub type UART_Parity_Type = u8;
+pub const UART_Parity_None: UART_Parity_Type = 0;
+pub const UART_Parity_Even: UART_Parity_Type = 1;
+pub const UART_Parity_Odd: UART_Parity_Type = 2;
+pub type UART_StopBits_Type = u8;
+pub const UART_StopBits_One: UART_StopBits_Type = 1;
+pub const UART_StopBits_Two: UART_StopBits_Type = 2;
+pub type UART_FlowControl_Type = u8;
+pub const UART_FlowControl_None: UART_FlowControl_Type = 0;
+pub const UART_FlowControl_RTSCTS: UART_FlowControl_Type = 1;
+pub const UART_FlowControl_XONXOFF: UART_FlowControl_Type = 2;
+#[repr(C)]
+#[derive(Debug, Copy, Clone)]
+pub struct z__UART_Config_Base {
+    pub z__magicAndVersion: u32,
+}
+#[repr(C)]
+#[derive(Debug, Copy, Clone)]
+pub struct z__UART_Config_v1 {
+    pub z__magicAndVersion: u32,
+    pub baudRate: UART_BaudRate_Type,
+    pub blockingMode: UART_BlockingMode_Type,
+    pub dataBits: UART_DataBits_Type,
+    pub parity: UART_Parity_Type,
+    pub stopBits: UART_StopBits_Type,
+    pub flowControl: UART_FlowControl_Type,
+}
+pub type UART_Config = z__UART_Config_v1;
+extern "C" {
+    pub fn z__UART_InitConfig(config: *mut z__UART_Config_Base, structVersion: u32);
+}
+extern "C" {
+    pub fn z__UART_Open(uartId: UART_Id, config: *const z__UART_Config_Base)
+        -> std::os::raw::c_int;
+}
+extern "C" {
+    pub fn tcdrain(arg1: std::os::raw::c_int) -> std::os::raw::c_int;
+}
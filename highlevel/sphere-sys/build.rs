@@ -1,21 +1,150 @@
 use std::env;
 use std::fs;
-use std::path::PathBuf;
-use std::str;
+use std::path::{Path, PathBuf};
 
+#[cfg(feature = "bindgen")]
 extern crate bindgen;
 
+/// Azure Sphere API sets, oldest first. They share the checked-in bindings,
+/// which only differ in the groups a set doesn't have.
+const API_SETS: &[(&str, &str)] = &[
+    ("CARGO_FEATURE_API_SET_6", "6"),
+    ("CARGO_FEATURE_API_SET_7", "7"),
+    ("CARGO_FEATURE_API_SET_7_BETA2010", "7+Beta2010"),
+];
+
+/// A set of headers that is only bound (and linked) if its cargo feature is enabled.
+#[cfg_attr(not(feature = "bindgen"), allow(dead_code))]
+struct Group {
+    name: &'static str,
+    /// `None` for the headers every app needs
+    feature: Option<&'static str>,
+    /// the oldest API set with the headers, `None` if all have them
+    since: Option<&'static str>,
+    headers: &'static str,
+    link: &'static [&'static str],
+    functions: &'static [&'static str],
+    types: &'static [&'static str],
+    vars: &'static [&'static str],
+}
+
 fn main() {
+    // the simulated backend doesn't need the sysroot
+    if env::var_os("CARGO_FEATURE_MOCK").is_some() {
+        return;
+    }
+
+    // Features are additive, so with several API sets enabled the oldest one wins.
+    // Apps built against an older API set keep running on newer OS releases.
+    let api_set = API_SETS
+        .iter()
+        .position(|(feature, _)| env::var_os(feature).is_some())
+        .expect("No API set selected - enable one of the api-set-* features");
+
     let out_dir = PathBuf::from(env::var("OUT_DIR").unwrap());
-    fs::write(out_dir.join("wrapper.h"), WRAPPER_H).expect("Error writing file");
+    let checked_in_dir = PathBuf::from(env::var("CARGO_MANIFEST_DIR").unwrap()).join("bindings");
+
+    let mut bindings = String::new();
+
+    for group in GROUPS.iter().filter(|group| group.enabled()) {
+        group.check_api_set(api_set);
+        let checked_in = checked_in_dir.join(format!("{}.rs", group.name));
+
+        let group_bindings = if cfg!(feature = "bindgen") {
+            let generated = generate_bindings(&out_dir, group);
+
+            // kept apart from the checked-in files, to compare them by hand
+            println!("cargo:rerun-if-env-changed=SPHERE_SYS_UPDATE_BINDINGS");
+            if env::var_os("SPHERE_SYS_UPDATE_BINDINGS").is_some() {
+                let generated_dir = out_dir.join("generated");
+                let path = generated_dir.join(format!("{}.rs", group.name));
+                let header = format!(
+                    "// `{}` bindings generated from the Azure Sphere API set {} sysroot, see\n\
+                     // bindings/README.md.\n\n",
+                    group.name, API_SETS[api_set].1
+                );
+                fs::create_dir_all(&generated_dir).expect("Couldn't create bindings directory!");
+                fs::write(&path, format!("{}{}", header, generated))
+                    .expect("Couldn't write generated bindings!");
+                println!(
+                    "cargo:warning=Generated bindings written to {}",
+                    path.display()
+                );
+            }
+
+            generated
+        } else {
+            println!("cargo:rerun-if-changed={}", checked_in.display());
+            read_checked_in(&checked_in)
+        };
+
+        bindings.push_str(&group_bindings);
+
+        for lib in group.link {
+            println!("cargo:rustc-link-lib=dylib={}", lib);
+        }
+    }
+
+    fs::write(out_dir.join("bindings.rs"), bindings).expect("Couldn't write bindings!");
+}
+
+impl Group {
+    fn enabled(&self) -> bool {
+        match self.feature {
+            None => true,
+            Some(feature) => {
+                let var = format!("CARGO_FEATURE_{}", feature.to_uppercase().replace('-', "_"));
+                env::var_os(var).is_some()
+            }
+        }
+    }
+
+    /// Fails the build if the API set at `api_set` in `API_SETS` predates the
+    /// headers, rather than leaving out the group.
+    fn check_api_set(&self, api_set: usize) {
+        if let Some(since) = self.since {
+            let oldest = API_SETS
+                .iter()
+                .position(|(_, name)| *name == since)
+                .unwrap();
+            if api_set < oldest {
+                panic!(
+                    "The `{}` feature needs API set {} or newer, but API set {} is selected - \
+                     with several api-set-* features enabled the oldest one wins",
+                    self.name, since, API_SETS[api_set].1
+                );
+            }
+        }
+    }
+}
+
+fn read_checked_in(path: &Path) -> String {
+    fs::read_to_string(path)
+        .unwrap_or_else(|_| panic!("No checked-in bindings at {}", path.display()))
+}
+
+#[cfg(not(feature = "bindgen"))]
+fn generate_bindings(_out_dir: &Path, _group: &Group) -> String {
+    unreachable!()
+}
 
-    let wrapper_h_str = out_dir
-        .join("wrapper.h")
-        .as_path()
-        .to_str()
-        .unwrap()
-        .replace("\\", "/");
+#[cfg(feature = "bindgen")]
+fn generate_bindings(out_dir: &Path, group: &Group) -> String {
+    let wrapper_h = out_dir.join(format!("wrapper_{}.h", group.name));
+    let base_headers = if group.feature.is_some() {
+        GROUPS[0].headers
+    } else {
+        ""
+    };
+    fs::write(
+        &wrapper_h,
+        format!("{}{}{}", WRAPPER_H_DEFINES, base_headers, group.headers),
+    )
+    .expect("Error writing file");
 
+    let wrapper_h_str = wrapper_h.as_path().to_str().unwrap().replace("\\", "/");
+
+    println!("cargo:rerun-if-env-changed=SYSROOT");
     let sysroot_env_var = env::var_os("SYSROOT")
         .expect("SYSROOT not defined - needs to point to e.g. .../sysroots/1");
     let sysroot = sysroot_env_var.to_str().unwrap();
@@ -23,7 +152,7 @@ fn main() {
     let iot_include = format!("{}/usr/include/azureiot", sysroot);
     let azure_prov_client_include = format!("{}/usr/include/azure_prov_client", sysroot);
 
-    let bindings = bindgen::Builder::default()
+    let mut builder = bindgen::Builder::default()
         .ctypes_prefix("std::os::raw")
         .use_core()
         .layout_tests(false)
@@ -40,18 +169,273 @@ fn main() {
         .clang_arg("c")
         .clang_arg("-nostdinc")
         .clang_arg("--verbose")
-        .header(wrapper_h_str)
-        .generate()
-        .expect("Unable to generate bindings");
+        .header(wrapper_h_str);
 
-    let out_path = PathBuf::from(env::var("OUT_DIR").unwrap());
+    // Only what sphere-lib uses, so the checked-in files stay reviewable. Items
+    // of the base group are shared by all groups and must not be emitted twice.
+    for function in group.functions {
+        builder = builder.whitelist_function(function);
+    }
+    for type_ in group.types {
+        builder = builder.whitelist_type(type_);
+    }
+    for var in group.vars {
+        builder = builder.whitelist_var(var);
+    }
+    if group.feature.is_some() {
+        for type_ in GROUPS[0].types {
+            builder = builder.blacklist_type(type_);
+        }
+    }
 
-    bindings
-        .write_to_file(out_path.join("bindings.rs"))
-        .expect("Couldn't write bindings!");
+    builder
+        .generate()
+        .expect("Unable to generate bindings")
+        .to_string()
 }
 
-const WRAPPER_H: &str = r#"
+/// The base group comes first, the others may use its types.
+const GROUPS: &[Group] = &[
+    Group {
+        name: "base",
+        feature: None,
+        since: None,
+        headers: r#"
+// Sphere App Libs
+#include <applibs/log.h>
+
+// Other
+#include <stdlib.h>
+#include <unistd.h>
+#include <errno.h>
+#include <time.h>
+#include <signal.h>
+#include <sys/epoll.h>
+#include <sys/timerfd.h>
+#include <sys/eventfd.h>
+#include <poll.h>
+"#,
+        link: &[],
+        functions: &[
+            "malloc",
+            "free",
+            "read",
+            "write",
+            "close",
+            "lseek",
+            "fsync",
+            "ftruncate",
+            "exit",
+            "_exit",
+            "sleep",
+            "usleep",
+            "__errno_location",
+            "clock_gettime",
+            "timer_.*",
+            "signal",
+            "sigemptyset",
+            "sigaddset",
+            "sigprocmask",
+            "epoll_create1",
+            "epoll_ctl",
+            "epoll_wait",
+            "timerfd_.*",
+            "eventfd",
+            "poll",
+            "Log_Debug",
+        ],
+        types: &[
+            "off_t",
+            "time_t",
+            "clockid_t",
+            "useconds_t",
+            "timer_t",
+            "timespec",
+            "itimerspec",
+            "sigval",
+            "sigevent",
+            "sigset_t",
+            "epoll_event",
+            "pollfd",
+            "nfds_t",
+        ],
+        vars: &[
+            "SEEK_SET",
+            "SEEK_CUR",
+            "SEEK_END",
+            "EINTR",
+            "EAGAIN",
+            "EBUSY",
+            "EINVAL",
+            "ENOSPC",
+            "CLOCK_REALTIME",
+            "CLOCK_MONOTONIC",
+            "SIGEV_.*",
+            "SIGALRM",
+            "SIGTERM",
+            "SIG_BLOCK",
+            "SIG_SETMASK",
+            "EPOLL_.*",
+            "EPOLLIN",
+            "EPOLLOUT",
+            "EPOLLERR",
+            "EPOLLHUP",
+            "TFD_NONBLOCK",
+            "TFD_CLOEXEC",
+            "TFD_TIMER_ABSTIME",
+            "EFD_NONBLOCK",
+            "EFD_CLOEXEC",
+            "POLLIN",
+            "POLLOUT",
+        ],
+    },
+    Group {
+        name: "networking",
+        feature: Some("networking"),
+        since: None,
+        headers: r#"
+#include <applibs/networking.h>
+"#,
+        link: &[],
+        functions: &["Networking_.*"],
+        types: &[],
+        vars: &["NETWORKING_STRUCTS_VERSION", "WIFICONFIG_STRUCTS_VERSION"],
+    },
+    Group {
+        name: "gpio",
+        feature: Some("gpio"),
+        since: None,
+        headers: r#"
+#include <applibs/gpio.h>
+"#,
+        link: &[],
+        functions: &["GPIO_.*"],
+        types: &["GPIO_.*"],
+        vars: &["GPIO_.*"],
+    },
+    Group {
+        name: "uart",
+        feature: Some("uart"),
+        since: None,
+        headers: r#"
+#include <applibs/uart.h>
+#include <termios.h>
+"#,
+        link: &[],
+        functions: &["z__UART_.*", "tcdrain"],
+        types: &["UART_.*", "z__UART_.*"],
+        vars: &["UART_.*"],
+    },
+    Group {
+        name: "i2c",
+        feature: Some("i2c"),
+        since: None,
+        headers: r#"
+#include <applibs/i2c.h>
+"#,
+        link: &[],
+        functions: &["I2CMaster_.*"],
+        types: &["I2C_.*"],
+        vars: &["I2C_.*"],
+    },
+    Group {
+        name: "spi",
+        feature: Some("spi"),
+        since: None,
+        headers: r#"
+#include <applibs/spi.h>
+"#,
+        link: &[],
+        functions: &["SPIMaster_.*", "z__SPIMaster_.*"],
+        types: &["SPI_.*", "SPIMaster_.*", "z__SPIMaster_.*"],
+        vars: &["SPI_.*"],
+    },
+    Group {
+        name: "application",
+        feature: Some("application"),
+        since: None,
+        headers: r#"
+#include <applibs/application.h>
+"#,
+        link: &[],
+        functions: &["Application_.*"],
+        types: &[],
+        vars: &[],
+    },
+    Group {
+        name: "applications",
+        feature: Some("applications"),
+        since: Some("7+Beta2010"),
+        headers: r#"
+#include <applibs/applications.h>
+"#,
+        link: &[],
+        functions: &["Applications_.*"],
+        types: &[],
+        vars: &[],
+    },
+    Group {
+        name: "storage",
+        feature: Some("storage"),
+        since: None,
+        headers: r#"
+#include <applibs/storage.h>
+"#,
+        link: &[],
+        functions: &["Storage_.*"],
+        types: &[],
+        vars: &[],
+    },
+    Group {
+        name: "iot",
+        feature: Some("iot"),
+        since: None,
+        headers: r#"
+// Azure IoT SDK
+#include <iothub_client_core_common.h>
+#include <iothub_device_client_ll.h>
+#include <iothub_client_options.h>
+#include <iothubtransportmqtt.h>
+#include <iothub.h>
+#include <azure_sphere_provisioning.h>
+#include <iothub_security_factory.h>
+"#,
+        link: &["azureiot"],
+        functions: &["IoTHub.*", "iothub_security_init", "MQTT_Protocol"],
+        types: &[
+            "IOTHUB_.*",
+            "DEVICE_TWIN_.*",
+            "AZURE_SPHERE_PROV_.*",
+            "PROV_DEVICE_RESULT",
+        ],
+        vars: &[],
+    },
+    Group {
+        name: "curl",
+        feature: Some("curl"),
+        since: None,
+        headers: r#"
+// CURL
+#include <curl/curl.h>
+
+// TLS
+#include <tlsutils/deviceauth_curl.h>
+"#,
+        link: &["curl", "tlsutils"],
+        functions: &["curl_.*", "DeviceAuth_.*"],
+        types: &[
+            "curl_write_callback",
+            "curl_ssl_ctx_callback",
+            "CURLcode",
+            "CURLoption",
+            "CURLINFO",
+        ],
+        vars: &["CURL_GLOBAL_.*"],
+    },
+];
+
+#[cfg(feature = "bindgen")]
+const WRAPPER_H_DEFINES: &str = r#"
 /// <summary>
 /// This identifier should be defined before including any of the networking-related header files.
 /// It indicates which version of the Wi-Fi data structures the application uses.
@@ -70,39 +454,15 @@ const WRAPPER_H: &str = r#"
 /// </summary>
 #define UART_STRUCTS_VERSION 1
 
+/// <summary>
+/// This identifier must be defined before including any of the I2C-related header files.
+/// It indicates which version of the I2C data structures the application uses.
+/// </summary>
+#define I2C_STRUCTS_VERSION 1
+
 /// <summary>
 /// This identifier must be defined before including any of the SPI-related header files.
 /// It indicates which version of the SPI data structures the application uses.
 /// </summary>
 #define SPI_STRUCTS_VERSION 1
-
-// Sphere App Libs
-#include <applibs/log.h>
-#include <applibs/networking.h>
-#include <applibs/gpio.h>
-#include <applibs/uart.h>
-#include <applibs/application.h>
-#include <applibs/storage.h>
-
-// Other
-#include <unistd.h>
-
-// Azure IoT SDK
-#include <iothub_client_core_common.h>
-#include <iothub_device_client_ll.h>
-#include <iothub_client_options.h>
-#include <iothubtransportmqtt.h>
-#include <iothub.h>
-#include <azure_sphere_provisioning.h>
-#include <iothub_security_factory.h>
-
-#include <time.h>
-
-// CURL
-#include <curl/curl.h>
-
-// TLS
-#include <tlsutils/deviceauth_curl.h>
-
-#include <signal.h>
 "#;
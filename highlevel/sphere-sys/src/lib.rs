@@ -2,6 +2,7 @@
 #![allow(non_upper_case_globals)]
 #![allow(non_camel_case_types)]
 #![allow(non_snake_case)]
+#![cfg_attr(feature = "mock", feature(c_variadic))]
 
 pub extern crate libc;
 
@@ -28,4 +29,14 @@ pub mod std {
     }
 }
 
+#[cfg(feature = "mock")]
+extern crate std as host_std;
+
+#[cfg(not(feature = "mock"))]
 include!(concat!(env!("OUT_DIR"), "/bindings.rs"));
+
+#[cfg(feature = "mock")]
+pub mod mock;
+
+#[cfg(feature = "mock")]
+pub use mock::{applibs::*, azureiot::*, curl::*, posix::*, types::*};
@@ -0,0 +1,752 @@
+// Simulated Applibs: log, gpio, uart, i2c, spi, application, applications, storage,
+// networking.
+
+use host_std::collections::VecDeque;
+use host_std::format;
+use host_std::string::String;
+use host_std::vec::Vec;
+
+use crate::std::os::raw::c_char;
+use crate::std::os::raw::c_int;
+
+// not glob imported, types has the constants bound for sphere-lib
+use super::errno::{EACCES, EBADF, EBUSY, EINVAL, ENODEV, ENOENT, ENOTTY, ENXIO};
+use super::types::*;
+use super::{c_str, c_string_malloc, with_state, I2cBus, Resource, SpiBus, State, TargetDevice};
+
+/// Records the format string, or the argument of a plain `"%s"`; sphere-lib
+/// always passes preformatted text.
+pub unsafe extern "C" fn Log_Debug(fmt: *const c_char, mut args: ...) -> c_int {
+    let mut line = c_str(fmt);
+    if line == "%s" {
+        line = c_str(args.arg::<*const c_char>());
+    }
+    with_state(|state| {
+        state.record("Log_Debug", line.clone());
+        state.log.push(line);
+    });
+    0
+}
+
+pub unsafe extern "C" fn GPIO_OpenAsOutput(
+    gpioId: GPIO_Id,
+    outputMode: GPIO_OutputMode_Type,
+    initialValue: GPIO_Value_Type,
+) -> c_int {
+    with_state(|state| {
+        state.record(
+            "GPIO_OpenAsOutput",
+            format!("{}, {}, {}", gpioId, outputMode, initialValue),
+        );
+        if state.take_failure("GPIO_OpenAsOutput").is_some() {
+            return -1;
+        }
+        if gpio_is_open(state, gpioId) {
+            return state.fail(EBUSY);
+        }
+        state.gpio.insert(gpioId, initialValue);
+        state.allocate_fd(Resource::Gpio(gpioId))
+    })
+}
+
+pub unsafe extern "C" fn GPIO_OpenAsInput(gpioId: GPIO_Id) -> c_int {
+    with_state(|state| {
+        state.record("GPIO_OpenAsInput", format!("{}", gpioId));
+        if state.take_failure("GPIO_OpenAsInput").is_some() {
+            return -1;
+        }
+        if gpio_is_open(state, gpioId) {
+            return state.fail(EBUSY);
+        }
+        state.gpio.entry(gpioId).or_insert(GPIO_Value_High);
+        state.allocate_fd(Resource::Gpio(gpioId))
+    })
+}
+
+pub unsafe extern "C" fn GPIO_SetValue(gpioFd: c_int, value: GPIO_Value_Type) -> c_int {
+    with_state(|state| {
+        state.record("GPIO_SetValue", format!("{}, {}", gpioFd, value));
+        if state.take_failure("GPIO_SetValue").is_some() {
+            return -1;
+        }
+        match state.fds.get(&gpioFd) {
+            Some(Resource::Gpio(gpio)) => {
+                let gpio = *gpio;
+                state.gpio.insert(gpio, value);
+                0
+            }
+            _ => state.fail(EBADF),
+        }
+    })
+}
+
+pub unsafe extern "C" fn GPIO_GetValue(gpioFd: c_int, outValue: *mut GPIO_Value_Type) -> c_int {
+    with_state(|state| {
+        state.record("GPIO_GetValue", format!("{}", gpioFd));
+        if state.take_failure("GPIO_GetValue").is_some() {
+            return -1;
+        }
+        match state.fds.get(&gpioFd) {
+            Some(Resource::Gpio(gpio)) => {
+                *outValue = state.gpio.get(gpio).copied().unwrap_or(GPIO_Value_Low);
+                0
+            }
+            _ => state.fail(EBADF),
+        }
+    })
+}
+
+fn gpio_is_open(state: &State, gpio: GPIO_Id) -> bool {
+    state
+        .fds
+        .values()
+        .any(|resource| matches!(resource, Resource::Gpio(open) if *open == gpio))
+}
+
+pub unsafe extern "C" fn z__UART_InitConfig(config: *mut z__UART_Config_Base, structVersion: u32) {
+    with_state(|state| state.record("z__UART_InitConfig", format!("{}", structVersion)));
+
+    let config = config as *mut z__UART_Config_v1;
+    (*config).z__magicAndVersion = structVersion;
+    (*config).baudRate = 0;
+    (*config).blockingMode = UART_BlockingMode_NonBlocking;
+    (*config).dataBits = UART_DataBits_Eight;
+    (*config).parity = UART_Parity_None;
+    (*config).stopBits = UART_StopBits_One;
+    (*config).flowControl = UART_FlowControl_None;
+}
+
+pub unsafe extern "C" fn z__UART_Open(
+    uartId: UART_Id,
+    config: *const z__UART_Config_Base,
+) -> c_int {
+    let config = *(config as *const z__UART_Config_v1);
+    with_state(|state| {
+        state.record("z__UART_Open", format!("{}, {:?}", uartId, config));
+        if state.take_failure("z__UART_Open").is_some() {
+            return -1;
+        }
+        let valid = config.z__magicAndVersion == UART_STRUCTS_VERSION
+            && (UART_DataBits_Five..=UART_DataBits_Eight).contains(&config.dataBits)
+            && config.parity <= UART_Parity_Odd
+            && (UART_StopBits_One..=UART_StopBits_Two).contains(&config.stopBits)
+            && config.flowControl <= UART_FlowControl_XONXOFF;
+        if !valid {
+            return state.fail(EINVAL);
+        }
+        let already_open = state
+            .fds
+            .values()
+            .any(|resource| matches!(resource, Resource::Uart(open) if *open == uartId));
+        if already_open {
+            return state.fail(EBUSY);
+        }
+        state.uart_rx.entry(uartId).or_insert_with(VecDeque::new);
+        state.uart_tx.entry(uartId).or_insert_with(Vec::new);
+        state.allocate_fd(Resource::Uart(uartId))
+    })
+}
+
+// the simulated transmitter is done as soon as the data is written
+pub unsafe extern "C" fn tcdrain(fd: c_int) -> c_int {
+    with_state(|state| {
+        state.record("tcdrain", format!("{}", fd));
+        if state.take_failure("tcdrain").is_some() {
+            return -1;
+        }
+        match state.fds.get(&fd) {
+            Some(Resource::Uart(_)) => 0,
+            Some(_) => state.fail(ENOTTY),
+            None => state.fail(EBADF),
+        }
+    })
+}
+
+pub unsafe extern "C" fn I2CMaster_Open(id: I2C_InterfaceId) -> c_int {
+    with_state(|state| {
+        state.record("I2CMaster_Open", format!("{}", id));
+        if state.take_failure("I2CMaster_Open").is_some() {
+            return -1;
+        }
+        let already_open = state
+            .fds
+            .values()
+            .any(|resource| matches!(resource, Resource::I2c(open) if *open == id));
+        if already_open {
+            return state.fail(EBUSY);
+        }
+        state.i2c_buses.insert(id, I2cBus::default());
+        state.allocate_fd(Resource::I2c(id))
+    })
+}
+
+pub unsafe extern "C" fn I2CMaster_SetBusSpeed(fd: c_int, speedInHz: I2C_BusSpeed) -> c_int {
+    with_state(|state| {
+        state.record("I2CMaster_SetBusSpeed", format!("{}, {}", fd, speedInHz));
+        if state.take_failure("I2CMaster_SetBusSpeed").is_some() {
+            return -1;
+        }
+        match speedInHz {
+            I2C_BUS_SPEED_STANDARD | I2C_BUS_SPEED_FAST | I2C_BUS_SPEED_FAST_PLUS => {
+                with_i2c_bus(state, fd, |bus| bus.speed = speedInHz)
+            }
+            _ => state.fail(EINVAL),
+        }
+    })
+}
+
+pub unsafe extern "C" fn I2CMaster_SetTimeout(fd: c_int, timeoutInMs: u32) -> c_int {
+    with_state(|state| {
+        state.record("I2CMaster_SetTimeout", format!("{}, {}", fd, timeoutInMs));
+        if state.take_failure("I2CMaster_SetTimeout").is_some() {
+            return -1;
+        }
+        with_i2c_bus(state, fd, |bus| bus.timeout_ms = timeoutInMs)
+    })
+}
+
+pub unsafe extern "C" fn I2CMaster_SetDefaultTargetAddress(
+    fd: c_int,
+    address: I2C_DeviceAddress,
+) -> c_int {
+    with_state(|state| {
+        state.record(
+            "I2CMaster_SetDefaultTargetAddress",
+            format!("{}, {}", fd, address),
+        );
+        if state
+            .take_failure("I2CMaster_SetDefaultTargetAddress")
+            .is_some()
+        {
+            return -1;
+        }
+        with_i2c_bus(state, fd, |bus| bus.default_address = Some(address))
+    })
+}
+
+pub unsafe extern "C" fn I2CMaster_Write(
+    fd: c_int,
+    address: I2C_DeviceAddress,
+    data: *const u8,
+    length: usize,
+) -> isize {
+    let data = host_std::slice::from_raw_parts(data, length);
+    with_state(|state| {
+        state.record(
+            "I2CMaster_Write",
+            format!("{}, {}, {:?}", fd, address, data),
+        );
+        if state.take_failure("I2CMaster_Write").is_some() {
+            return -1;
+        }
+        match i2c_interface(state, fd) {
+            Some(interface) => i2c_write(state, interface, Some(address), data),
+            None => state.fail(EBADF) as isize,
+        }
+    })
+}
+
+pub unsafe extern "C" fn I2CMaster_Read(
+    fd: c_int,
+    address: I2C_DeviceAddress,
+    buffer: *mut u8,
+    maxLength: usize,
+) -> isize {
+    let buffer = host_std::slice::from_raw_parts_mut(buffer, maxLength);
+    with_state(|state| {
+        state.record(
+            "I2CMaster_Read",
+            format!("{}, {}, {}", fd, address, maxLength),
+        );
+        if state.take_failure("I2CMaster_Read").is_some() {
+            return -1;
+        }
+        match i2c_interface(state, fd) {
+            Some(interface) => i2c_read(state, interface, Some(address), buffer),
+            None => state.fail(EBADF) as isize,
+        }
+    })
+}
+
+/// Returns the total number of bytes transferred, like the real API.
+pub unsafe extern "C" fn I2CMaster_WriteThenRead(
+    fd: c_int,
+    address: I2C_DeviceAddress,
+    writeData: *const u8,
+    lenWriteData: usize,
+    readData: *mut u8,
+    lenReadData: usize,
+) -> isize {
+    let write_data = host_std::slice::from_raw_parts(writeData, lenWriteData);
+    let read_data = host_std::slice::from_raw_parts_mut(readData, lenReadData);
+    with_state(|state| {
+        state.record(
+            "I2CMaster_WriteThenRead",
+            format!("{}, {}, {:?}, {}", fd, address, write_data, lenReadData),
+        );
+        if state.take_failure("I2CMaster_WriteThenRead").is_some() {
+            return -1;
+        }
+        let interface = match i2c_interface(state, fd) {
+            Some(interface) => interface,
+            None => return state.fail(EBADF) as isize,
+        };
+        let written = i2c_write(state, interface, Some(address), write_data);
+        if written < 0 {
+            return written;
+        }
+        let read = i2c_read(state, interface, Some(address), read_data);
+        if read < 0 {
+            return read;
+        }
+        written + read
+    })
+}
+
+fn i2c_interface(state: &State, fd: c_int) -> Option<c_int> {
+    match state.fds.get(&fd) {
+        Some(Resource::I2c(interface)) => Some(*interface),
+        _ => None,
+    }
+}
+
+fn with_i2c_bus(state: &mut State, fd: c_int, f: impl FnOnce(&mut I2cBus)) -> c_int {
+    let bus = i2c_interface(state, fd).and_then(|interface| state.i2c_buses.get_mut(&interface));
+    match bus {
+        Some(bus) => {
+            f(bus);
+            0
+        }
+        None => state.fail(EBADF),
+    }
+}
+
+// `None` addresses the default target, as `read`/`write` on the fd do
+fn i2c_target(
+    state: &mut State,
+    interface: c_int,
+    address: Option<u32>,
+) -> Option<&mut TargetDevice> {
+    let address = address.or_else(|| state.i2c_buses.get(&interface)?.default_address)?;
+    state.i2c_devices.get_mut(&(interface, address))
+}
+
+pub(super) fn i2c_write(
+    state: &mut State,
+    interface: c_int,
+    address: Option<u32>,
+    data: &[u8],
+) -> isize {
+    match i2c_target(state, interface, address) {
+        Some(device) => {
+            device.tx.extend_from_slice(data);
+            data.len() as isize
+        }
+        None => state.fail(ENXIO) as isize,
+    }
+}
+
+pub(super) fn i2c_read(
+    state: &mut State,
+    interface: c_int,
+    address: Option<u32>,
+    buffer: &mut [u8],
+) -> isize {
+    match i2c_target(state, interface, address) {
+        Some(device) => {
+            for byte in buffer.iter_mut() {
+                *byte = device.rx.pop_front().unwrap_or(0xff);
+            }
+            buffer.len() as isize
+        }
+        None => state.fail(ENXIO) as isize,
+    }
+}
+
+pub unsafe extern "C" fn z__SPIMaster_InitConfig(
+    config: *mut z__SPIMaster_Config_Base,
+    structVersion: u32,
+) -> c_int {
+    with_state(|state| state.record("z__SPIMaster_InitConfig", format!("{}", structVersion)));
+
+    let config = config as *mut z__SPIMaster_Config_v1;
+    (*config).z__magicAndVersion = structVersion;
+    (*config).csPolarity = SPI_ChipSelectPolarity_ActiveLow;
+    0
+}
+
+pub unsafe extern "C" fn z__SPIMaster_Open(
+    interfaceId: SPI_InterfaceId,
+    chipSelectId: SPI_ChipSelectId,
+    config: *const z__SPIMaster_Config_Base,
+) -> c_int {
+    let config = *(config as *const z__SPIMaster_Config_v1);
+    with_state(|state| {
+        state.record(
+            "z__SPIMaster_Open",
+            format!("{}, {}, {:?}", interfaceId, chipSelectId, config),
+        );
+        if state.take_failure("z__SPIMaster_Open").is_some() {
+            return -1;
+        }
+        let already_open = state
+            .fds
+            .values()
+            .any(|resource| matches!(resource, Resource::Spi(open) if *open == interfaceId));
+        if already_open {
+            return state.fail(EBUSY);
+        }
+        match config.csPolarity {
+            SPI_ChipSelectPolarity_ActiveLow | SPI_ChipSelectPolarity_ActiveHigh => {}
+            _ => return state.fail(EINVAL),
+        }
+        state.spi_buses.insert(
+            interfaceId,
+            SpiBus {
+                chip_select: chipSelectId,
+                cs_polarity: config.csPolarity,
+                speed: 0,
+                mode: SPI_Mode_0,
+                bit_order: SPI_BitOrder_MsbFirst,
+            },
+        );
+        state.allocate_fd(Resource::Spi(interfaceId))
+    })
+}
+
+pub unsafe extern "C" fn z__SPIMaster_InitTransfers(
+    transfers: *mut z__SPIMaster_Transfer_Base,
+    transferCount: usize,
+    transferStructVersion: u32,
+) -> c_int {
+    with_state(|state| {
+        state.record(
+            "z__SPIMaster_InitTransfers",
+            format!("{}, {}", transferCount, transferStructVersion),
+        )
+    });
+
+    let transfers = host_std::slice::from_raw_parts_mut(
+        transfers as *mut z__SPIMaster_Transfer_v1,
+        transferCount,
+    );
+    for transfer in transfers {
+        *transfer = z__SPIMaster_Transfer_v1 {
+            z__magicAndVersion: transferStructVersion,
+            flags: SPI_TransferFlags_None,
+            writeData: host_std::ptr::null(),
+            readData: host_std::ptr::null_mut(),
+            length: 0,
+        };
+    }
+    0
+}
+
+/// Returns the number of bytes transferred, full-duplex bytes count once.
+pub unsafe extern "C" fn z__SPIMaster_TransferSequential(
+    fd: c_int,
+    transfers: *const z__SPIMaster_Transfer_Base,
+    transferCount: usize,
+    transferStructVersion: u32,
+) -> isize {
+    let transfers = host_std::slice::from_raw_parts(
+        transfers as *const z__SPIMaster_Transfer_v1,
+        transferCount,
+    );
+    with_state(|state| {
+        state.record(
+            "z__SPIMaster_TransferSequential",
+            format!("{}, {:?}, {}", fd, transfers, transferStructVersion),
+        );
+        if state
+            .take_failure("z__SPIMaster_TransferSequential")
+            .is_some()
+        {
+            return -1;
+        }
+        let interface = match spi_interface(state, fd) {
+            Some(interface) => interface,
+            None => return state.fail(EBADF) as isize,
+        };
+        let mut transferred = 0;
+        for transfer in transfers {
+            let flags = transfer.flags;
+            if transfer.length == 0 || flags == SPI_TransferFlags_None {
+                return state.fail(EINVAL) as isize;
+            }
+            // copied first, read and write buffer may be the same
+            if flags & SPI_TransferFlags_Write != 0 {
+                let data = host_std::slice::from_raw_parts(transfer.writeData, transfer.length);
+                spi_write(state, interface, data);
+            }
+            if flags & SPI_TransferFlags_Read != 0 {
+                let buffer =
+                    host_std::slice::from_raw_parts_mut(transfer.readData, transfer.length);
+                spi_read(state, interface, buffer);
+            }
+            transferred += transfer.length as isize;
+        }
+        transferred
+    })
+}
+
+pub unsafe extern "C" fn SPIMaster_SetBusSpeed(fd: c_int, speedInHz: u32) -> c_int {
+    with_state(|state| {
+        state.record("SPIMaster_SetBusSpeed", format!("{}, {}", fd, speedInHz));
+        if state.take_failure("SPIMaster_SetBusSpeed").is_some() {
+            return -1;
+        }
+        with_spi_bus(state, fd, |bus| bus.speed = speedInHz)
+    })
+}
+
+pub unsafe extern "C" fn SPIMaster_SetMode(fd: c_int, mode: SPI_Mode) -> c_int {
+    with_state(|state| {
+        state.record("SPIMaster_SetMode", format!("{}, {}", fd, mode));
+        if state.take_failure("SPIMaster_SetMode").is_some() {
+            return -1;
+        }
+        match mode {
+            SPI_Mode_0 | SPI_Mode_1 | SPI_Mode_2 | SPI_Mode_3 => {
+                with_spi_bus(state, fd, |bus| bus.mode = mode)
+            }
+            _ => state.fail(EINVAL),
+        }
+    })
+}
+
+pub unsafe extern "C" fn SPIMaster_SetBitOrder(fd: c_int, order: SPI_BitOrder) -> c_int {
+    with_state(|state| {
+        state.record("SPIMaster_SetBitOrder", format!("{}, {}", fd, order));
+        if state.take_failure("SPIMaster_SetBitOrder").is_some() {
+            return -1;
+        }
+        match order {
+            SPI_BitOrder_LsbFirst | SPI_BitOrder_MsbFirst => {
+                with_spi_bus(state, fd, |bus| bus.bit_order = order)
+            }
+            _ => state.fail(EINVAL),
+        }
+    })
+}
+
+/// Returns the total number of bytes transferred, like the real API.
+pub unsafe extern "C" fn SPIMaster_WriteThenRead(
+    fd: c_int,
+    writeData: *const u8,
+    lenWriteData: usize,
+    readData: *mut u8,
+    lenReadData: usize,
+) -> isize {
+    let write_data = host_std::slice::from_raw_parts(writeData, lenWriteData);
+    let read_data = host_std::slice::from_raw_parts_mut(readData, lenReadData);
+    with_state(|state| {
+        state.record(
+            "SPIMaster_WriteThenRead",
+            format!("{}, {:?}, {}", fd, write_data, lenReadData),
+        );
+        if state.take_failure("SPIMaster_WriteThenRead").is_some() {
+            return -1;
+        }
+        match spi_interface(state, fd) {
+            Some(interface) => {
+                spi_write(state, interface, write_data) + spi_read(state, interface, read_data)
+            }
+            None => state.fail(EBADF) as isize,
+        }
+    })
+}
+
+fn spi_interface(state: &State, fd: c_int) -> Option<c_int> {
+    match state.fds.get(&fd) {
+        Some(Resource::Spi(interface)) => Some(*interface),
+        _ => None,
+    }
+}
+
+fn with_spi_bus(state: &mut State, fd: c_int, f: impl FnOnce(&mut SpiBus)) -> c_int {
+    let bus = spi_interface(state, fd).and_then(|interface| state.spi_buses.get_mut(&interface));
+    match bus {
+        Some(bus) => {
+            f(bus);
+            0
+        }
+        None => state.fail(EBADF),
+    }
+}
+
+// SPI has no acknowledge, transfers always succeed
+fn spi_target(state: &mut State, interface: c_int) -> &mut TargetDevice {
+    let chip_select = state.spi_buses[&interface].chip_select;
+    state
+        .spi_devices
+        .entry((interface, chip_select))
+        .or_insert_with(TargetDevice::default)
+}
+
+pub(super) fn spi_write(state: &mut State, interface: c_int, data: &[u8]) -> isize {
+    spi_target(state, interface).tx.extend_from_slice(data);
+    data.len() as isize
+}
+
+pub(super) fn spi_read(state: &mut State, interface: c_int, buffer: &mut [u8]) -> isize {
+    let device = spi_target(state, interface);
+    for byte in buffer.iter_mut() {
+        *byte = device.rx.pop_front().unwrap_or(0xff);
+    }
+    buffer.len() as isize
+}
+
+pub unsafe extern "C" fn Application_Connect(componentId: *const c_char) -> c_int {
+    let component_id = c_str(componentId);
+    with_state(|state| {
+        state.record("Application_Connect", component_id.clone());
+        if state.take_failure("Application_Connect").is_some() {
+            return -1;
+        }
+        state.allocate_fd(Resource::Socket(component_id))
+    })
+}
+
+pub unsafe extern "C" fn Applications_GetTotalMemoryUsageInKB() -> usize {
+    with_state(|state| {
+        state.record("Applications_GetTotalMemoryUsageInKB", String::new());
+        state.memory_usage_kb
+    })
+}
+
+pub unsafe extern "C" fn Applications_GetUserModeMemoryUsageInKB() -> usize {
+    with_state(|state| {
+        state.record("Applications_GetUserModeMemoryUsageInKB", String::new());
+        state.memory_usage_kb
+    })
+}
+
+pub unsafe extern "C" fn Applications_GetPeakUserModeMemoryUsageInKB() -> usize {
+    with_state(|state| {
+        state.record("Applications_GetPeakUserModeMemoryUsageInKB", String::new());
+        state.peak_memory_usage_kb
+    })
+}
+
+pub unsafe extern "C" fn Storage_OpenFileInImagePackage(relativePath: *const c_char) -> c_int {
+    let relative_path = c_str(relativePath);
+    with_state(|state| {
+        state.record("Storage_OpenFileInImagePackage", relative_path.clone());
+        if state
+            .take_failure("Storage_OpenFileInImagePackage")
+            .is_some()
+        {
+            return -1;
+        }
+        if relative_path.starts_with('/') {
+            return state.fail(EINVAL);
+        }
+        match state.image_package_files.get(&relative_path) {
+            Some(data) => {
+                let data = data.clone();
+                state.allocate_fd(Resource::ImageFile(data, 0))
+            }
+            None => state.fail(ENOENT),
+        }
+    })
+}
+
+pub unsafe extern "C" fn Storage_GetAbsolutePathInImagePackage(
+    relativePath: *const c_char,
+) -> *mut c_char {
+    let relative_path = c_str(relativePath);
+    let absolute_path = with_state(|state| {
+        state.record(
+            "Storage_GetAbsolutePathInImagePackage",
+            relative_path.clone(),
+        );
+        if state
+            .take_failure("Storage_GetAbsolutePathInImagePackage")
+            .is_some()
+        {
+            return None;
+        }
+        if relative_path.starts_with('/') {
+            state.fail(EINVAL);
+            return None;
+        }
+        Some(format!("{}/{}", state.image_package_root, relative_path))
+    });
+
+    match absolute_path {
+        Some(path) => c_string_malloc(&path) as *mut c_char,
+        None => host_std::ptr::null_mut(),
+    }
+}
+
+pub unsafe extern "C" fn Storage_OpenMutableFile() -> c_int {
+    with_state(|state| {
+        state.record("Storage_OpenMutableFile", String::new());
+        if state.take_failure("Storage_OpenMutableFile").is_some() {
+            return -1;
+        }
+        if state.mutable_storage_size.is_none() {
+            return state.fail(EACCES);
+        }
+        state.allocate_fd(Resource::MutableFile(0))
+    })
+}
+
+pub unsafe extern "C" fn Storage_DeleteMutableFile() -> c_int {
+    with_state(|state| {
+        state.record("Storage_DeleteMutableFile", String::new());
+        if state.take_failure("Storage_DeleteMutableFile").is_some() {
+            return -1;
+        }
+        if state.mutable_storage_size.is_none() {
+            return state.fail(EACCES);
+        }
+        state.mutable_storage.clear();
+        0
+    })
+}
+
+pub unsafe extern "C" fn Networking_SetInterfaceState(
+    networkInterfaceName: *const c_char,
+    isEnabled: bool,
+) -> c_int {
+    let name = c_str(networkInterfaceName);
+    with_state(|state| {
+        state.record(
+            "Networking_SetInterfaceState",
+            format!("{}, {}", name, isEnabled),
+        );
+        if state.take_failure("Networking_SetInterfaceState").is_some() {
+            return -1;
+        }
+        match state.interfaces.get_mut(&name) {
+            Some(enabled) => {
+                *enabled = isEnabled;
+                0
+            }
+            None => state.fail(ENODEV),
+        }
+    })
+}
+
+pub unsafe extern "C" fn Networking_GetInterfaceCount() -> isize {
+    with_state(|state| {
+        state.record("Networking_GetInterfaceCount", String::new());
+        if state.take_failure("Networking_GetInterfaceCount").is_some() {
+            return -1;
+        }
+        state.interfaces.len() as isize
+    })
+}
+
+pub unsafe extern "C" fn Networking_IsNetworkingReady(outIsNetworkingReady: *mut bool) -> c_int {
+    with_state(|state| {
+        state.record("Networking_IsNetworkingReady", String::new());
+        if state.take_failure("Networking_IsNetworkingReady").is_some() {
+            return -1;
+        }
+        *outIsNetworkingReady = state.networking_ready;
+        0
+    })
+}
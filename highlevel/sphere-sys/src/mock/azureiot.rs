@@ -0,0 +1,476 @@
+// Simulated Azure IoT device client (low level layer) and Azure Sphere provisioning.
+
+use host_std::collections::{HashMap, VecDeque};
+use host_std::format;
+use host_std::string::String;
+use host_std::vec::Vec;
+
+use crate::std::os::raw::c_char;
+use crate::std::os::raw::c_int;
+use crate::std::os::raw::c_uchar;
+use crate::std::os::raw::c_uint;
+use crate::std::os::raw::c_void;
+
+use super::types::*;
+use super::{c_str, with_state};
+
+/// An option set with `IoTHubDeviceClient_LL_SetOption`.
+///
+/// The SDK passes option values as `void*`; the simulation knows which
+/// options take integers and treats everything else as a string.
+#[derive(Clone, Debug, PartialEq)]
+pub enum IotHubOption {
+    Int(c_int),
+    Str(String),
+}
+
+const INT_OPTIONS: &[&str] = &["keepalive", "SetDeviceId", "messageTimeout"];
+
+#[derive(Default)]
+pub(crate) struct IotHubState {
+    next_handle: usize,
+    clients: HashMap<usize, Client>,
+    next_message: usize,
+    messages: HashMap<usize, String>,
+    sent: Vec<String>,
+    confirmations: Vec<(IOTHUB_CLIENT_EVENT_CONFIRMATION_CALLBACK, usize)>,
+    provisioning: VecDeque<AZURE_SPHERE_PROV_RESULT>,
+    security_type: Option<IOTHUB_SECURITY_TYPE>,
+}
+
+#[derive(Default)]
+struct Client {
+    options: Vec<(String, IotHubOption)>,
+    connection_status: (IOTHUB_CLIENT_CONNECTION_STATUS_CALLBACK, usize),
+    device_method: (IOTHUB_CLIENT_DEVICE_METHOD_CALLBACK_ASYNC, usize),
+    device_twin: (IOTHUB_CLIENT_DEVICE_TWIN_CALLBACK, usize),
+}
+
+impl IotHubState {
+    fn create_client(&mut self) -> IOTHUB_DEVICE_CLIENT_LL_HANDLE {
+        self.next_handle += 1;
+        self.clients.insert(self.next_handle, Client::default());
+        self.next_handle as IOTHUB_DEVICE_CLIENT_LL_HANDLE
+    }
+}
+
+/// Queues the results of the next calls to
+/// `IoTHubDeviceClient_LL_CreateWithAzureSphereDeviceAuthProvisioning`.
+///
+/// Once the queue is empty provisioning succeeds.
+pub fn script_provisioning(results: &[AZURE_SPHERE_PROV_RESULT]) {
+    with_state(|state| state.iothub.provisioning.extend(results));
+}
+
+/// Payloads of all telemetry messages sent so far.
+pub fn iothub_sent_messages() -> Vec<String> {
+    with_state(|state| state.iothub.sent.clone())
+}
+
+/// Options set on the client behind `handle`.
+pub fn iothub_options(handle: IOTHUB_DEVICE_CLIENT_LL_HANDLE) -> Vec<(String, IotHubOption)> {
+    with_state(|state| {
+        state
+            .iothub
+            .clients
+            .get(&(handle as usize))
+            .map(|client| client.options.clone())
+            .unwrap_or_default()
+    })
+}
+
+/// Reports a connection status change to every client that registered a callback.
+pub fn iothub_set_connection_status(
+    status: IOTHUB_CLIENT_CONNECTION_STATUS,
+    reason: IOTHUB_CLIENT_CONNECTION_STATUS_REASON,
+) {
+    let callbacks: Vec<_> = with_state(|state| {
+        state
+            .iothub
+            .clients
+            .values()
+            .map(|client| client.connection_status)
+            .collect()
+    });
+
+    for (callback, context) in callbacks {
+        if let Some(callback) = callback {
+            unsafe { callback(status, reason, context as *mut c_void) };
+        }
+    }
+}
+
+/// Invokes a direct method as the cloud would.
+///
+/// Returns the status and response payload of the first client with a
+/// registered method callback, `None` if there is none.
+pub fn iothub_invoke_method(method_name: &str, payload: &[u8]) -> Option<(c_int, Vec<u8>)> {
+    let callback = with_state(|state| {
+        state
+            .iothub
+            .clients
+            .values()
+            .map(|client| client.device_method)
+            .find(|(callback, _)| callback.is_some())
+    });
+
+    let (callback, context) = callback?;
+    let callback = callback?;
+    let method_name = host_std::ffi::CString::new(method_name).unwrap();
+    let mut response: *mut c_uchar = host_std::ptr::null_mut();
+    let mut response_size: usize = 0;
+
+    unsafe {
+        let status = callback(
+            method_name.as_ptr() as *const c_char,
+            payload.as_ptr(),
+            payload.len(),
+            &mut response,
+            &mut response_size,
+            context as *mut c_void,
+        );
+
+        // the SDK owns and frees the response buffer
+        let mut body = Vec::new();
+        if !response.is_null() {
+            body.extend_from_slice(host_std::slice::from_raw_parts(response, response_size));
+            libc::free(response as *mut libc::c_void);
+        }
+        Some((status, body))
+    }
+}
+
+/// Delivers a device twin document to every client that registered a callback.
+pub fn iothub_update_device_twin(update_state: DEVICE_TWIN_UPDATE_STATE, payload: &[u8]) {
+    let callbacks: Vec<_> = with_state(|state| {
+        state
+            .iothub
+            .clients
+            .values()
+            .map(|client| client.device_twin)
+            .collect()
+    });
+
+    for (callback, context) in callbacks {
+        if let Some(callback) = callback {
+            unsafe {
+                callback(
+                    update_state,
+                    payload.as_ptr(),
+                    payload.len(),
+                    context as *mut c_void,
+                )
+            };
+        }
+    }
+}
+
+pub unsafe extern "C" fn IoTHub_Init() -> c_int {
+    with_state(|state| {
+        state.record("IoTHub_Init", String::new());
+        state.take_failure("IoTHub_Init").unwrap_or(0)
+    })
+}
+
+pub unsafe extern "C" fn IoTHub_Deinit() {
+    with_state(|state| state.record("IoTHub_Deinit", String::new()));
+}
+
+pub unsafe extern "C" fn iothub_security_init(sec_type: IOTHUB_SECURITY_TYPE) -> c_int {
+    with_state(|state| {
+        state.record("iothub_security_init", format!("{}", sec_type));
+        if let Some(code) = state.take_failure("iothub_security_init") {
+            return code;
+        }
+        state.iothub.security_type = Some(sec_type);
+        0
+    })
+}
+
+pub unsafe extern "C" fn MQTT_Protocol() -> *const TRANSPORT_PROVIDER {
+    // only ever compared against null by the SDK
+    1usize as *const TRANSPORT_PROVIDER
+}
+
+pub unsafe extern "C" fn IoTHubDeviceClient_LL_CreateWithAzureSphereDeviceAuthProvisioning(
+    idScope: *const c_char,
+    timeout: c_uint,
+    handle: *mut IOTHUB_DEVICE_CLIENT_LL_HANDLE,
+) -> AZURE_SPHERE_PROV_RETURN_VALUE {
+    let scope_id = c_str(idScope);
+    with_state(|state| {
+        state.record(
+            "IoTHubDeviceClient_LL_CreateWithAzureSphereDeviceAuthProvisioning",
+            format!("{}, {}", scope_id, timeout),
+        );
+
+        let scripted = state.iothub.provisioning.pop_front();
+        let failure = state
+            .take_failure("IoTHubDeviceClient_LL_CreateWithAzureSphereDeviceAuthProvisioning")
+            .map(|code| code as AZURE_SPHERE_PROV_RESULT);
+        let result = failure
+            .or(scripted)
+            .unwrap_or(AZURE_SPHERE_PROV_RESULT_AZURE_SPHERE_PROV_RESULT_OK);
+
+        if result == AZURE_SPHERE_PROV_RESULT_AZURE_SPHERE_PROV_RESULT_OK {
+            *handle = state.iothub.create_client();
+        }
+
+        AZURE_SPHERE_PROV_RETURN_VALUE {
+            result,
+            prov_device_error: 0,
+            iothub_client_error: 0,
+        }
+    })
+}
+
+pub unsafe extern "C" fn IoTHubDeviceClient_LL_CreateFromDeviceAuth(
+    iothub_uri: *const c_char,
+    device_id: *const c_char,
+    protocol: IOTHUB_CLIENT_TRANSPORT_PROVIDER,
+) -> IOTHUB_DEVICE_CLIENT_LL_HANDLE {
+    let uri = c_str(iothub_uri);
+    let device_id = c_str(device_id);
+    with_state(|state| {
+        state.record(
+            "IoTHubDeviceClient_LL_CreateFromDeviceAuth",
+            format!("{}, {}", uri, device_id),
+        );
+        let failed = state
+            .take_failure("IoTHubDeviceClient_LL_CreateFromDeviceAuth")
+            .is_some();
+        if failed || protocol.is_none() || state.iothub.security_type.is_none() {
+            return host_std::ptr::null_mut();
+        }
+        state.iothub.create_client()
+    })
+}
+
+pub unsafe extern "C" fn IoTHubDeviceClient_LL_CreateFromConnectionString(
+    connectionString: *const c_char,
+    protocol: IOTHUB_CLIENT_TRANSPORT_PROVIDER,
+) -> IOTHUB_DEVICE_CLIENT_LL_HANDLE {
+    let connection_string = c_str(connectionString);
+    with_state(|state| {
+        state.record(
+            "IoTHubDeviceClient_LL_CreateFromConnectionString",
+            connection_string.clone(),
+        );
+        let failed = state
+            .take_failure("IoTHubDeviceClient_LL_CreateFromConnectionString")
+            .is_some();
+        if failed || protocol.is_none() || !connection_string.contains("HostName=") {
+            return host_std::ptr::null_mut();
+        }
+        state.iothub.create_client()
+    })
+}
+
+pub unsafe extern "C" fn IoTHubDeviceClient_LL_Destroy(
+    iotHubClientHandle: IOTHUB_DEVICE_CLIENT_LL_HANDLE,
+) {
+    with_state(|state| {
+        state.record(
+            "IoTHubDeviceClient_LL_Destroy",
+            format!("{}", iotHubClientHandle as usize),
+        );
+        state.iothub.clients.remove(&(iotHubClientHandle as usize));
+    });
+}
+
+/// Delivers the send confirmations queued since the last call.
+pub unsafe extern "C" fn IoTHubDeviceClient_LL_DoWork(
+    iotHubClientHandle: IOTHUB_DEVICE_CLIENT_LL_HANDLE,
+) {
+    let confirmations = with_state(|state| {
+        state.record(
+            "IoTHubDeviceClient_LL_DoWork",
+            format!("{}", iotHubClientHandle as usize),
+        );
+        host_std::mem::take(&mut state.iothub.confirmations)
+    });
+
+    for (callback, context) in confirmations {
+        if let Some(callback) = callback {
+            callback(
+                IOTHUB_CLIENT_CONFIRMATION_RESULT_TAG_IOTHUB_CLIENT_CONFIRMATION_OK,
+                context as *mut c_void,
+            );
+        }
+    }
+}
+
+pub unsafe extern "C" fn IoTHubDeviceClient_LL_SetOption(
+    iotHubClientHandle: IOTHUB_DEVICE_CLIENT_LL_HANDLE,
+    optionName: *const c_char,
+    value: *const c_void,
+) -> IOTHUB_CLIENT_RESULT {
+    let name = c_str(optionName);
+    let value = if value.is_null() {
+        IotHubOption::Str(String::new())
+    } else if INT_OPTIONS.contains(&name.as_str()) {
+        IotHubOption::Int(*(value as *const c_int))
+    } else {
+        IotHubOption::Str(c_str(value as *const c_char))
+    };
+    with_state(|state| {
+        state.record(
+            "IoTHubDeviceClient_LL_SetOption",
+            format!("{}, {}, {:?}", iotHubClientHandle as usize, name, value),
+        );
+        if let Some(code) = state.take_failure("IoTHubDeviceClient_LL_SetOption") {
+            return code as IOTHUB_CLIENT_RESULT;
+        }
+        match state.iothub.clients.get_mut(&(iotHubClientHandle as usize)) {
+            Some(client) => {
+                client.options.push((name, value));
+                IOTHUB_CLIENT_RESULT_TAG_IOTHUB_CLIENT_OK
+            }
+            None => IOTHUB_CLIENT_RESULT_TAG_IOTHUB_CLIENT_INVALID_ARG,
+        }
+    })
+}
+
+pub unsafe extern "C" fn IoTHubDeviceClient_LL_SetConnectionStatusCallback(
+    iotHubClientHandle: IOTHUB_DEVICE_CLIENT_LL_HANDLE,
+    connectionStatusCallback: IOTHUB_CLIENT_CONNECTION_STATUS_CALLBACK,
+    userContextCallback: *mut c_void,
+) -> IOTHUB_CLIENT_RESULT {
+    with_state(|state| {
+        state.record(
+            "IoTHubDeviceClient_LL_SetConnectionStatusCallback",
+            format!("{}", iotHubClientHandle as usize),
+        );
+        if let Some(code) = state.take_failure("IoTHubDeviceClient_LL_SetConnectionStatusCallback")
+        {
+            return code as IOTHUB_CLIENT_RESULT;
+        }
+        match state.iothub.clients.get_mut(&(iotHubClientHandle as usize)) {
+            Some(client) => {
+                client.connection_status = (connectionStatusCallback, userContextCallback as usize);
+                IOTHUB_CLIENT_RESULT_TAG_IOTHUB_CLIENT_OK
+            }
+            None => IOTHUB_CLIENT_RESULT_TAG_IOTHUB_CLIENT_INVALID_ARG,
+        }
+    })
+}
+
+pub unsafe extern "C" fn IoTHubDeviceClient_LL_SetDeviceMethodCallback(
+    iotHubClientHandle: IOTHUB_DEVICE_CLIENT_LL_HANDLE,
+    deviceMethodCallback: IOTHUB_CLIENT_DEVICE_METHOD_CALLBACK_ASYNC,
+    userContextCallback: *mut c_void,
+) -> IOTHUB_CLIENT_RESULT {
+    with_state(|state| {
+        state.record(
+            "IoTHubDeviceClient_LL_SetDeviceMethodCallback",
+            format!("{}", iotHubClientHandle as usize),
+        );
+        if let Some(code) = state.take_failure("IoTHubDeviceClient_LL_SetDeviceMethodCallback") {
+            return code as IOTHUB_CLIENT_RESULT;
+        }
+        match state.iothub.clients.get_mut(&(iotHubClientHandle as usize)) {
+            Some(client) => {
+                client.device_method = (deviceMethodCallback, userContextCallback as usize);
+                IOTHUB_CLIENT_RESULT_TAG_IOTHUB_CLIENT_OK
+            }
+            None => IOTHUB_CLIENT_RESULT_TAG_IOTHUB_CLIENT_INVALID_ARG,
+        }
+    })
+}
+
+pub unsafe extern "C" fn IoTHubDeviceClient_LL_SetDeviceTwinCallback(
+    iotHubClientHandle: IOTHUB_DEVICE_CLIENT_LL_HANDLE,
+    deviceTwinCallback: IOTHUB_CLIENT_DEVICE_TWIN_CALLBACK,
+    userContextCallback: *mut c_void,
+) -> IOTHUB_CLIENT_RESULT {
+    with_state(|state| {
+        state.record(
+            "IoTHubDeviceClient_LL_SetDeviceTwinCallback",
+            format!("{}", iotHubClientHandle as usize),
+        );
+        if let Some(code) = state.take_failure("IoTHubDeviceClient_LL_SetDeviceTwinCallback") {
+            return code as IOTHUB_CLIENT_RESULT;
+        }
+        match state.iothub.clients.get_mut(&(iotHubClientHandle as usize)) {
+            Some(client) => {
+                client.device_twin = (deviceTwinCallback, userContextCallback as usize);
+                IOTHUB_CLIENT_RESULT_TAG_IOTHUB_CLIENT_OK
+            }
+            None => IOTHUB_CLIENT_RESULT_TAG_IOTHUB_CLIENT_INVALID_ARG,
+        }
+    })
+}
+
+pub unsafe extern "C" fn IoTHubMessage_CreateFromString(
+    source: *const c_char,
+) -> IOTHUB_MESSAGE_HANDLE {
+    let payload = c_str(source);
+    with_state(|state| {
+        state.record("IoTHubMessage_CreateFromString", payload.clone());
+        if state
+            .take_failure("IoTHubMessage_CreateFromString")
+            .is_some()
+        {
+            return host_std::ptr::null_mut();
+        }
+        state.iothub.next_message += 1;
+        state
+            .iothub
+            .messages
+            .insert(state.iothub.next_message, payload);
+        state.iothub.next_message as IOTHUB_MESSAGE_HANDLE
+    })
+}
+
+pub unsafe extern "C" fn IoTHubMessage_Destroy(iotHubMessageHandle: IOTHUB_MESSAGE_HANDLE) {
+    with_state(|state| {
+        state.record(
+            "IoTHubMessage_Destroy",
+            format!("{}", iotHubMessageHandle as usize),
+        );
+        state
+            .iothub
+            .messages
+            .remove(&(iotHubMessageHandle as usize));
+    });
+}
+
+pub unsafe extern "C" fn IoTHubDeviceClient_LL_SendEventAsync(
+    iotHubClientHandle: IOTHUB_DEVICE_CLIENT_LL_HANDLE,
+    eventMessageHandle: IOTHUB_MESSAGE_HANDLE,
+    eventConfirmationCallback: IOTHUB_CLIENT_EVENT_CONFIRMATION_CALLBACK,
+    userContextCallback: *mut c_void,
+) -> IOTHUB_CLIENT_RESULT {
+    with_state(|state| {
+        state.record(
+            "IoTHubDeviceClient_LL_SendEventAsync",
+            format!(
+                "{}, {}",
+                iotHubClientHandle as usize, eventMessageHandle as usize
+            ),
+        );
+        if let Some(code) = state.take_failure("IoTHubDeviceClient_LL_SendEventAsync") {
+            return code as IOTHUB_CLIENT_RESULT;
+        }
+        if !state
+            .iothub
+            .clients
+            .contains_key(&(iotHubClientHandle as usize))
+        {
+            return IOTHUB_CLIENT_RESULT_TAG_IOTHUB_CLIENT_INVALID_ARG;
+        }
+        // the SDK clones the message, the caller may destroy it right away
+        match state.iothub.messages.get(&(eventMessageHandle as usize)) {
+            Some(payload) => {
+                let payload = payload.clone();
+                state.iothub.sent.push(payload);
+                state
+                    .iothub
+                    .confirmations
+                    .push((eventConfirmationCallback, userContextCallback as usize));
+                IOTHUB_CLIENT_RESULT_TAG_IOTHUB_CLIENT_OK
+            }
+            None => IOTHUB_CLIENT_RESULT_TAG_IOTHUB_CLIENT_INVALID_ARG,
+        }
+    })
+}
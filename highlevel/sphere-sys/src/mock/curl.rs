@@ -0,0 +1,306 @@
+// Simulated libcurl easy interface and the tlsutils device auth hook.
+
+use host_std::boxed::Box;
+use host_std::collections::{HashMap, VecDeque};
+use host_std::format;
+use host_std::string::String;
+use host_std::vec::Vec;
+
+use crate::std::os::raw::c_char;
+use crate::std::os::raw::c_long;
+use crate::std::os::raw::c_void;
+
+use super::types::*;
+use super::{c_str, c_string_malloc, with_state};
+
+/// A response `curl_easy_perform` delivers to the next request.
+#[derive(Clone, Debug, Default)]
+pub struct CurlResponse {
+    /// Result of `curl_easy_perform`, `CURLE_OK` for a completed transfer.
+    pub result: CURLcode,
+    pub status: c_long,
+    pub body: Vec<u8>,
+}
+
+/// A request as seen by `curl_easy_perform`.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct CurlRequest {
+    pub url: String,
+    pub method: Option<String>,
+    pub headers: Vec<String>,
+    pub post_fields: Option<String>,
+    pub ca_info: Option<String>,
+    pub verify_host: Option<c_long>,
+    pub device_auth: bool,
+}
+
+#[derive(Default)]
+pub(crate) struct CurlState {
+    next_handle: usize,
+    handles: HashMap<usize, Handle>,
+    responses: VecDeque<CurlResponse>,
+    requests: Vec<CurlRequest>,
+}
+
+#[derive(Default)]
+struct Handle {
+    request: CurlRequest,
+    write_function: curl_write_callback,
+    write_data: usize,
+    ssl_ctx_function: curl_ssl_ctx_callback,
+    status: c_long,
+}
+
+/// Queues the response for the next `curl_easy_perform` call.
+///
+/// Without a scripted response a transfer fails with
+/// `CURLE_COULDNT_CONNECT`.
+pub fn script_curl_response(response: CurlResponse) {
+    with_state(|state| state.curl.responses.push_back(response));
+}
+
+/// All requests performed since the last reset.
+pub fn curl_requests() -> Vec<CurlRequest> {
+    with_state(|state| state.curl.requests.clone())
+}
+
+pub unsafe extern "C" fn curl_global_init(flags: c_long) -> CURLcode {
+    with_state(|state| {
+        state.record("curl_global_init", format!("{}", flags));
+        state
+            .take_failure("curl_global_init")
+            .map_or(CURLcode_CURLE_OK, |code| code as CURLcode)
+    })
+}
+
+pub unsafe extern "C" fn curl_global_cleanup() {
+    with_state(|state| state.record("curl_global_cleanup", String::new()));
+}
+
+pub unsafe extern "C" fn curl_easy_init() -> *mut CURL {
+    with_state(|state| {
+        state.record("curl_easy_init", String::new());
+        if state.take_failure("curl_easy_init").is_some() {
+            return host_std::ptr::null_mut();
+        }
+        state.curl.next_handle += 1;
+        let handle = state.curl.next_handle;
+        state.curl.handles.insert(handle, Handle::default());
+        handle as *mut CURL
+    })
+}
+
+pub unsafe extern "C" fn curl_easy_cleanup(curl: *mut CURL) {
+    with_state(|state| {
+        state.record("curl_easy_cleanup", format!("{}", curl as usize));
+        state.curl.handles.remove(&(curl as usize));
+    });
+}
+
+pub unsafe extern "C" fn curl_easy_setopt(
+    curl: *mut CURL,
+    option: CURLoption,
+    mut args: ...
+) -> CURLcode {
+    // curl encodes the argument type in the option number
+    let value = match option {
+        0..=9999 => OptionValue::Long(args.arg::<c_long>()),
+        10000..=29999 => OptionValue::Pointer(args.arg::<*const c_void>()),
+        _ => OptionValue::OffT(args.arg::<i64>()),
+    };
+    with_state(|state| {
+        state.record(
+            "curl_easy_setopt",
+            format!("{}, {}, {:?}", curl as usize, option, value),
+        );
+        if let Some(code) = state.take_failure("curl_easy_setopt") {
+            return code as CURLcode;
+        }
+        let handle = match state.curl.handles.get_mut(&(curl as usize)) {
+            Some(handle) => handle,
+            None => return CURLcode_CURLE_FAILED_INIT,
+        };
+        let request = &mut handle.request;
+        match (option, value) {
+            (CURLoption_CURLOPT_URL, OptionValue::Pointer(ptr)) => {
+                request.url = c_str(ptr as *const c_char)
+            }
+            (CURLoption_CURLOPT_CUSTOMREQUEST, OptionValue::Pointer(ptr)) => {
+                request.method = non_null_str(ptr)
+            }
+            (CURLoption_CURLOPT_POSTFIELDS, OptionValue::Pointer(ptr)) => {
+                request.post_fields = non_null_str(ptr)
+            }
+            (CURLoption_CURLOPT_CAINFO, OptionValue::Pointer(ptr)) => {
+                request.ca_info = non_null_str(ptr)
+            }
+            (CURLoption_CURLOPT_HTTPHEADER, OptionValue::Pointer(ptr)) => {
+                request.headers = slist_to_vec(ptr as *const curl_slist)
+            }
+            (CURLoption_CURLOPT_SSL_VERIFYHOST, OptionValue::Long(value)) => {
+                request.verify_host = Some(value)
+            }
+            (CURLoption_CURLOPT_WRITEDATA, OptionValue::Pointer(ptr)) => {
+                handle.write_data = ptr as usize
+            }
+            (CURLoption_CURLOPT_WRITEFUNCTION, OptionValue::Pointer(ptr)) => {
+                handle.write_function = host_std::mem::transmute(ptr)
+            }
+            (CURLoption_CURLOPT_SSL_CTX_FUNCTION, OptionValue::Pointer(ptr)) => {
+                handle.ssl_ctx_function = host_std::mem::transmute(ptr)
+            }
+            _ => {}
+        }
+        CURLcode_CURLE_OK
+    })
+}
+
+pub unsafe extern "C" fn curl_easy_perform(curl: *mut CURL) -> CURLcode {
+    let transfer = with_state(|state| {
+        state.record("curl_easy_perform", format!("{}", curl as usize));
+        if let Some(code) = state.take_failure("curl_easy_perform") {
+            return Err(code as CURLcode);
+        }
+        let response = state.curl.responses.pop_front().unwrap_or(CurlResponse {
+            result: CURLcode_CURLE_COULDNT_CONNECT,
+            status: 0,
+            body: Vec::new(),
+        });
+        let handle = match state.curl.handles.get_mut(&(curl as usize)) {
+            Some(handle) => handle,
+            None => return Err(CURLcode_CURLE_FAILED_INIT),
+        };
+        handle.request.device_auth = handle.ssl_ctx_function.is_some();
+        handle.status = response.status;
+        let request = handle.request.clone();
+        let transfer = (
+            handle.write_function,
+            handle.write_data,
+            handle.ssl_ctx_function,
+        );
+        state.curl.requests.push(request);
+        Ok((transfer, response))
+    });
+
+    // callbacks run outside of the state borrow, they may call back into curl
+    let ((write_function, write_data, ssl_ctx_function), response) = match transfer {
+        Ok(transfer) => transfer,
+        Err(code) => return code,
+    };
+
+    if let Some(ssl_ctx_function) = ssl_ctx_function {
+        let result = ssl_ctx_function(curl, host_std::ptr::null_mut(), host_std::ptr::null_mut());
+        if result != CURLcode_CURLE_OK {
+            return CURLcode_CURLE_SSL_CONNECT_ERROR;
+        }
+    }
+
+    if response.result != CURLcode_CURLE_OK {
+        return response.result;
+    }
+
+    if let Some(write_function) = write_function {
+        if !response.body.is_empty() {
+            let mut body = response.body.clone();
+            let written = write_function(
+                body.as_mut_ptr() as *mut c_char,
+                1,
+                body.len(),
+                write_data as *mut c_void,
+            );
+            if written != body.len() {
+                return CURLcode_CURLE_WRITE_ERROR;
+            }
+        }
+    }
+
+    CURLcode_CURLE_OK
+}
+
+pub unsafe extern "C" fn curl_easy_getinfo(
+    curl: *mut CURL,
+    info: CURLINFO,
+    mut args: ...
+) -> CURLcode {
+    let out = args.arg::<*mut c_long>();
+    with_state(|state| {
+        state.record("curl_easy_getinfo", format!("{}, {}", curl as usize, info));
+        if let Some(code) = state.take_failure("curl_easy_getinfo") {
+            return code as CURLcode;
+        }
+        match (state.curl.handles.get(&(curl as usize)), info) {
+            (Some(handle), CURLINFO_CURLINFO_RESPONSE_CODE) => {
+                *out = handle.status;
+                CURLcode_CURLE_OK
+            }
+            (Some(_), _) => CURLcode_CURLE_UNSUPPORTED_PROTOCOL,
+            (None, _) => CURLcode_CURLE_FAILED_INIT,
+        }
+    })
+}
+
+pub unsafe extern "C" fn curl_slist_append(
+    list: *mut curl_slist,
+    string: *const c_char,
+) -> *mut curl_slist {
+    let node = Box::into_raw(Box::new(curl_slist {
+        data: c_string_malloc(&c_str(string)) as *mut c_char,
+        next: host_std::ptr::null_mut(),
+    }));
+
+    if list.is_null() {
+        return node;
+    }
+
+    let mut last = list;
+    while !(*last).next.is_null() {
+        last = (*last).next;
+    }
+    (*last).next = node;
+    list
+}
+
+pub unsafe extern "C" fn curl_slist_free_all(list: *mut curl_slist) {
+    let mut node = list;
+    while !node.is_null() {
+        let next = (*node).next;
+        libc::free((*node).data as *mut libc::c_void);
+        drop(Box::from_raw(node));
+        node = next;
+    }
+}
+
+pub unsafe extern "C" fn DeviceAuth_SslCtxFunc(sslctx: *mut c_void) -> CURLcode {
+    with_state(|state| {
+        state.record("DeviceAuth_SslCtxFunc", format!("{}", sslctx as usize));
+        state
+            .take_failure("DeviceAuth_SslCtxFunc")
+            .map_or(CURLcode_CURLE_OK, |code| code as CURLcode)
+    })
+}
+
+// off_t values are only recorded
+#[allow(dead_code)]
+#[derive(Copy, Clone, Debug)]
+enum OptionValue {
+    Long(c_long),
+    Pointer(*const c_void),
+    OffT(i64),
+}
+
+unsafe fn non_null_str(ptr: *const c_void) -> Option<String> {
+    if ptr.is_null() {
+        None
+    } else {
+        Some(c_str(ptr as *const c_char))
+    }
+}
+
+unsafe fn slist_to_vec(mut node: *const curl_slist) -> Vec<String> {
+    let mut items = Vec::new();
+    while !node.is_null() {
+        items.push(c_str((*node).data));
+        node = (*node).next;
+    }
+    items
+}
@@ -0,0 +1,537 @@
+//! Host-side simulation of the Applibs, POSIX, curl and Azure IoT APIs.
+//!
+//! Enabled with the `mock` feature. Instead of the bindgen-generated bindings
+//! the crate then exports pure-Rust functions with the same names and
+//! signatures, so `sphere-lib` can be built and tested on the host, e.g. with
+//! `cargo test --target x86_64-unknown-linux-gnu --features mock`. The
+//! simulation always provides every header group, whatever features are set.
+//!
+//! All simulated state lives in a thread local, so tests running in parallel
+//! don't see each other. Every simulated call is recorded and can be inspected
+//! with [`calls`]; the functions in this module script the behaviour of the
+//! simulated device.
+
+use host_std::cell::RefCell;
+use host_std::collections::{HashMap, VecDeque};
+use host_std::string::String;
+use host_std::vec::Vec;
+
+use crate::std::os::raw::c_int;
+use crate::std::os::raw::c_void;
+
+pub(crate) mod applibs;
+pub(crate) mod azureiot;
+pub(crate) mod curl;
+pub(crate) mod posix;
+pub(crate) mod types;
+
+pub use self::azureiot::{
+    iothub_invoke_method, iothub_options, iothub_sent_messages, iothub_set_connection_status,
+    iothub_update_device_twin, script_provisioning, IotHubOption,
+};
+pub use self::curl::{curl_requests, script_curl_response, CurlRequest, CurlResponse};
+
+/// errno values used by the simulation.
+pub mod errno {
+    pub const ENOENT: i32 = 2;
+    pub const EIO: i32 = 5;
+    pub const ENXIO: i32 = 6;
+    pub const EBADF: i32 = 9;
+    pub const EAGAIN: i32 = 11;
+    pub const EACCES: i32 = 13;
+    pub const EBUSY: i32 = 16;
+    pub const EEXIST: i32 = 17;
+    pub const ENODEV: i32 = 19;
+    pub const EINVAL: i32 = 22;
+    pub const ENOTTY: i32 = 25;
+    pub const ENOSPC: i32 = 28;
+    pub const ESPIPE: i32 = 29;
+}
+
+/// A single recorded call into the simulated API.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Call {
+    pub name: &'static str,
+    pub args: String,
+}
+
+pub(crate) enum Resource {
+    Gpio(c_int),
+    Uart(c_int),
+    I2c(c_int),
+    Spi(c_int),
+    Socket(String),
+    Epoll(HashMap<c_int, types::epoll_event>),
+    TimerFd(TimerFd),
+    /// an eventfd and its counter
+    EventFd(u64),
+    /// an open mutable storage file, with its file position
+    MutableFile(usize),
+    /// an open image package file, its contents and the file position
+    ImageFile(Vec<u8>, usize),
+}
+
+/// A simulated timerfd, expirations are counted against the monotonic clock.
+#[derive(Default)]
+pub(crate) struct TimerFd {
+    /// monotonic time of the next expiry, `None` while disarmed
+    pub next_ns: Option<u64>,
+    pub interval_ns: u64,
+    pub expirations: u64,
+}
+
+impl TimerFd {
+    /// Counts the expirations up to `now_ns`.
+    pub fn update(&mut self, now_ns: u64) {
+        while let Some(next_ns) = self.next_ns {
+            if next_ns > now_ns {
+                break;
+            }
+            self.expirations += 1;
+            self.next_ns = match self.interval_ns {
+                0 => None,
+                interval_ns => Some(next_ns + interval_ns),
+            };
+        }
+    }
+}
+
+/// Configuration of a simulated I2C master interface.
+#[derive(Clone, Debug, PartialEq)]
+pub struct I2cBus {
+    pub speed: u32,
+    pub timeout_ms: u32,
+    pub default_address: Option<u32>,
+}
+
+impl Default for I2cBus {
+    fn default() -> I2cBus {
+        I2cBus {
+            speed: types::I2C_BUS_SPEED_STANDARD,
+            timeout_ms: 0,
+            default_address: None,
+        }
+    }
+}
+
+/// Configuration of a simulated SPI master interface.
+#[derive(Clone, Debug, PartialEq)]
+pub struct SpiBus {
+    pub chip_select: i32,
+    pub cs_polarity: u32,
+    pub speed: u32,
+    pub mode: u32,
+    pub bit_order: u32,
+}
+
+/// A target device attached to a simulated I2C or SPI bus.
+#[derive(Default)]
+pub(crate) struct TargetDevice {
+    pub rx: VecDeque<u8>,
+    pub tx: Vec<u8>,
+}
+
+pub(crate) struct State {
+    pub calls: Vec<Call>,
+    pub failures: HashMap<&'static str, VecDeque<i32>>,
+    pub errno: c_int,
+    pub next_fd: c_int,
+    pub fds: HashMap<c_int, Resource>,
+    pub gpio: HashMap<c_int, u8>,
+    pub uart_rx: HashMap<c_int, VecDeque<u8>>,
+    pub uart_tx: HashMap<c_int, Vec<u8>>,
+    pub i2c_buses: HashMap<c_int, I2cBus>,
+    pub i2c_devices: HashMap<(c_int, u32), TargetDevice>,
+    pub spi_buses: HashMap<c_int, SpiBus>,
+    pub spi_devices: HashMap<(c_int, c_int), TargetDevice>,
+    pub socket_rx: HashMap<String, VecDeque<u8>>,
+    pub socket_tx: HashMap<String, Vec<u8>>,
+    pub log: Vec<String>,
+    pub networking_ready: bool,
+    pub interfaces: HashMap<String, bool>,
+    pub image_package_root: String,
+    pub image_package_files: HashMap<String, Vec<u8>>,
+    pub mutable_storage: Vec<u8>,
+    /// `SizeKB` of the `MutableStorage` capability in bytes, `None` without it
+    pub mutable_storage_size: Option<usize>,
+    /// the simulation doesn't tell kernel and user mode memory apart
+    pub memory_usage_kb: usize,
+    pub peak_memory_usage_kb: usize,
+    pub monotonic_ns: u64,
+    pub next_timer: usize,
+    pub timers: HashMap<usize, Option<types::itimerspec>>,
+    pub signal_handlers: HashMap<c_int, posix::sighandler_t>,
+    pub signal_mask: types::sigset_t,
+    /// raised while blocked, delivered once unblocked
+    pub pending_signals: Vec<c_int>,
+    pub curl: curl::CurlState,
+    pub iothub: azureiot::IotHubState,
+}
+
+impl State {
+    fn new() -> State {
+        let mut interfaces = HashMap::new();
+        interfaces.insert(String::from("wlan0"), true);
+        interfaces.insert(String::from("eth0"), false);
+
+        State {
+            calls: Vec::new(),
+            failures: HashMap::new(),
+            errno: 0,
+            next_fd: 3,
+            fds: HashMap::new(),
+            gpio: HashMap::new(),
+            uart_rx: HashMap::new(),
+            uart_tx: HashMap::new(),
+            i2c_buses: HashMap::new(),
+            i2c_devices: HashMap::new(),
+            spi_buses: HashMap::new(),
+            spi_devices: HashMap::new(),
+            socket_rx: HashMap::new(),
+            socket_tx: HashMap::new(),
+            log: Vec::new(),
+            networking_ready: true,
+            interfaces,
+            image_package_root: String::from("/mnt/apps/approot"),
+            image_package_files: HashMap::new(),
+            mutable_storage: Vec::new(),
+            mutable_storage_size: Some(64 * 1024),
+            memory_usage_kb: 0,
+            peak_memory_usage_kb: 0,
+            monotonic_ns: 0,
+            next_timer: 0,
+            timers: HashMap::new(),
+            signal_handlers: HashMap::new(),
+            signal_mask: types::sigset_t { __bits: [0; 16] },
+            pending_signals: Vec::new(),
+            curl: curl::CurlState::default(),
+            iothub: azureiot::IotHubState::default(),
+        }
+    }
+
+    pub fn record(&mut self, name: &'static str, args: String) {
+        self.calls.push(Call { name, args });
+    }
+
+    /// Returns the scripted failure for `name`, if any, and stores it in errno.
+    pub fn take_failure(&mut self, name: &'static str) -> Option<i32> {
+        let failure = self
+            .failures
+            .get_mut(name)
+            .and_then(|queue| queue.pop_front());
+        if let Some(code) = failure {
+            self.errno = code;
+        }
+        failure
+    }
+
+    pub fn allocate_fd(&mut self, resource: Resource) -> c_int {
+        let fd = self.next_fd;
+        self.next_fd += 1;
+        self.fds.insert(fd, resource);
+        fd
+    }
+
+    pub fn fail(&mut self, errno: c_int) -> c_int {
+        self.errno = errno;
+        -1
+    }
+}
+
+host_std::thread_local! {
+    static STATE: RefCell<State> = RefCell::new(State::new());
+}
+
+/// Runs `f` with exclusive access to the simulated device of this thread.
+///
+/// Must not be nested and must not call back into application code, since
+/// callbacks may re-enter the simulation.
+pub(crate) fn with_state<R>(f: impl FnOnce(&mut State) -> R) -> R {
+    STATE.with(|state| f(&mut state.borrow_mut()))
+}
+
+/// Resets the simulated device of the current thread to its power-on state.
+/// Like on a reboot, the contents of mutable storage are kept.
+pub fn reset() {
+    with_state(|state| {
+        let mutable_storage = host_std::mem::take(&mut state.mutable_storage);
+        *state = State::new();
+        state.mutable_storage = mutable_storage;
+    });
+}
+
+/// All calls recorded since the last [`reset`] or [`clear_calls`].
+pub fn calls() -> Vec<Call> {
+    with_state(|state| state.calls.clone())
+}
+
+/// The recorded calls to `name`.
+pub fn calls_to(name: &str) -> Vec<Call> {
+    with_state(|state| {
+        state
+            .calls
+            .iter()
+            .filter(|call| call.name == name)
+            .cloned()
+            .collect()
+    })
+}
+
+pub fn call_count(name: &str) -> usize {
+    calls_to(name).len()
+}
+
+pub fn clear_calls() {
+    with_state(|state| state.calls.clear());
+}
+
+/// Makes the next call to `function` fail.
+///
+/// Applibs and POSIX functions return their error value and set errno to
+/// `code`; curl and IoT functions return `code` as their result code. Calling
+/// this repeatedly queues several failures.
+pub fn fail_next(function: &'static str, code: i32) {
+    with_state(|state| {
+        state
+            .failures
+            .entry(function)
+            .or_insert_with(VecDeque::new)
+            .push_back(code)
+    });
+}
+
+/// errno as left by the last failing simulated call.
+pub fn errno() -> i32 {
+    with_state(|state| state.errno)
+}
+
+/// Lines passed to `Log_Debug`.
+pub fn log_lines() -> Vec<String> {
+    with_state(|state| state.log.clone())
+}
+
+/// Current value of an opened GPIO, `None` if it was never opened.
+pub fn gpio_value(gpio: i32) -> Option<u8> {
+    with_state(|state| state.gpio.get(&gpio).copied())
+}
+
+/// Drives a GPIO from the outside, e.g. a button press.
+pub fn set_gpio_value(gpio: i32, value: u8) {
+    with_state(|state| {
+        state.gpio.insert(gpio, value);
+    });
+}
+
+/// Queues bytes to be received on the UART with the given id.
+pub fn uart_inject(uart_id: i32, data: &[u8]) {
+    with_state(|state| {
+        state
+            .uart_rx
+            .entry(uart_id)
+            .or_insert_with(VecDeque::new)
+            .extend(data)
+    });
+}
+
+/// Takes everything written to the UART with the given id so far.
+pub fn uart_take_written(uart_id: i32) -> Vec<u8> {
+    with_state(|state| state.uart_tx.remove(&uart_id).unwrap_or_default())
+}
+
+/// Attaches a target device at `address` to the I2C interface, so transfers
+/// to it are acknowledged. Transfers to other addresses fail with `ENXIO`.
+pub fn i2c_attach(interface: i32, address: u32) {
+    with_state(|state| {
+        state
+            .i2c_devices
+            .entry((interface, address))
+            .or_insert_with(TargetDevice::default);
+    });
+}
+
+/// Queues bytes the device at `address` returns on reads, attaching it if
+/// needed. Reads beyond the queued bytes return `0xff`.
+pub fn i2c_inject(interface: i32, address: u32, data: &[u8]) {
+    with_state(|state| {
+        state
+            .i2c_devices
+            .entry((interface, address))
+            .or_insert_with(TargetDevice::default)
+            .rx
+            .extend(data)
+    });
+}
+
+/// Takes everything written to the device at `address` so far.
+pub fn i2c_take_written(interface: i32, address: u32) -> Vec<u8> {
+    with_state(|state| {
+        state
+            .i2c_devices
+            .get_mut(&(interface, address))
+            .map(|device| host_std::mem::take(&mut device.tx))
+            .unwrap_or_default()
+    })
+}
+
+/// Configuration of the I2C interface, `None` if it was never opened.
+pub fn i2c_bus(interface: i32) -> Option<I2cBus> {
+    with_state(|state| state.i2c_buses.get(&interface).cloned())
+}
+
+/// Queues bytes the SPI device behind `chip_select` shifts out while it is
+/// read from. Reads beyond the queued bytes return `0xff`.
+pub fn spi_inject(interface: i32, chip_select: i32, data: &[u8]) {
+    with_state(|state| {
+        state
+            .spi_devices
+            .entry((interface, chip_select))
+            .or_insert_with(TargetDevice::default)
+            .rx
+            .extend(data)
+    });
+}
+
+/// Takes everything written to the SPI device behind `chip_select` so far.
+pub fn spi_take_written(interface: i32, chip_select: i32) -> Vec<u8> {
+    with_state(|state| {
+        state
+            .spi_devices
+            .get_mut(&(interface, chip_select))
+            .map(|device| host_std::mem::take(&mut device.tx))
+            .unwrap_or_default()
+    })
+}
+
+/// Configuration of the SPI interface, `None` if it was never opened.
+pub fn spi_bus(interface: i32) -> Option<SpiBus> {
+    with_state(|state| state.spi_buses.get(&interface).cloned())
+}
+
+/// Queues bytes to be received on the application socket to `component_id`.
+pub fn socket_inject(component_id: &str, data: &[u8]) {
+    with_state(|state| {
+        state
+            .socket_rx
+            .entry(String::from(component_id))
+            .or_insert_with(VecDeque::new)
+            .extend(data)
+    });
+}
+
+/// Takes everything written to the application socket to `component_id` so far.
+pub fn socket_take_written(component_id: &str) -> Vec<u8> {
+    with_state(|state| state.socket_tx.remove(component_id).unwrap_or_default())
+}
+
+pub fn set_networking_ready(ready: bool) {
+    with_state(|state| state.networking_ready = ready);
+}
+
+/// Enabled state of a network interface, `None` if the interface is unknown.
+pub fn interface_enabled(interface: &str) -> Option<bool> {
+    with_state(|state| state.interfaces.get(interface).copied())
+}
+
+/// Directory the image package is "mounted" at.
+pub fn set_image_package_root(path: &str) {
+    with_state(|state| state.image_package_root = String::from(path));
+}
+
+/// Adds a file to the image package, `path` is relative to its root.
+pub fn add_image_package_file(path: &str, data: &[u8]) {
+    with_state(|state| {
+        state
+            .image_package_files
+            .insert(String::from(path), data.to_vec())
+    });
+}
+
+/// Contents of the mutable storage file. Survives `reset`, like storage
+/// survives a reboot.
+pub fn mutable_storage() -> Vec<u8> {
+    with_state(|state| state.mutable_storage.clone())
+}
+
+pub fn set_mutable_storage(data: &[u8]) {
+    with_state(|state| state.mutable_storage = data.to_vec());
+}
+
+/// The size granted by the app manifest, `None` removes the capability.
+pub fn set_mutable_storage_size(size: Option<usize>) {
+    with_state(|state| state.mutable_storage_size = size);
+}
+
+/// What `Applications_Get*MemoryUsageInKB` report, the peak follows.
+pub fn set_memory_usage_kb(usage: usize) {
+    with_state(|state| {
+        state.memory_usage_kb = usage;
+        state.peak_memory_usage_kb = state.peak_memory_usage_kb.max(usage);
+    });
+}
+
+/// Simulated monotonic time in nanoseconds. `sleep`/`usleep` advance it
+/// instead of blocking.
+pub fn monotonic_ns() -> u64 {
+    with_state(|state| state.monotonic_ns)
+}
+
+/// Settings of all POSIX timers that are currently armed.
+pub fn armed_timers() -> Vec<types::itimerspec> {
+    with_state(|state| state.timers.values().filter_map(|timer| *timer).collect())
+}
+
+/// Runs the handler installed for `signo` with `signal`, as if the signal
+/// arrived. Returns false if there is none. While `signo` is blocked with
+/// `sigprocmask` it stays pending instead, until it's unblocked.
+pub fn raise_signal(signo: i32) -> bool {
+    // the handler runs outside of the state borrow, it may call into the simulation
+    let handler = with_state(|state| {
+        if posix::sigismember(&state.signal_mask, signo) {
+            if !state.pending_signals.contains(&signo) {
+                state.pending_signals.push(signo);
+            }
+            return None;
+        }
+        state.signal_handlers.get(&signo).copied().flatten()
+    });
+    match handler {
+        Some(handler) => {
+            unsafe { handler(signo) };
+            true
+        }
+        None => false,
+    }
+}
+
+/// Settings of all armed timerfds as (next expiry, interval) in monotonic
+/// nanoseconds.
+pub fn armed_timerfds() -> Vec<(u64, u64)> {
+    with_state(|state| {
+        state
+            .fds
+            .values()
+            .filter_map(|resource| match resource {
+                Resource::TimerFd(timer) => timer.next_ns.map(|next| (next, timer.interval_ns)),
+                _ => None,
+            })
+            .collect()
+    })
+}
+
+pub(crate) unsafe fn c_str(ptr: *const crate::std::os::raw::c_char) -> String {
+    if ptr.is_null() {
+        return String::new();
+    }
+    host_std::ffi::CStr::from_ptr(ptr as *const _)
+        .to_string_lossy()
+        .into_owned()
+}
+
+pub(crate) unsafe fn c_string_malloc(s: &str) -> *mut c_void {
+    let ptr = libc::malloc(s.len() + 1) as *mut u8;
+    if !ptr.is_null() {
+        host_std::ptr::copy_nonoverlapping(s.as_ptr(), ptr, s.len());
+        *ptr.add(s.len()) = 0;
+    }
+    ptr as *mut c_void
+}
@@ -0,0 +1,738 @@
+// Simulated POSIX subset: fd I/O, epoll, sleeping, heap and timers.
+
+use host_std::collections::HashMap;
+use host_std::format;
+use host_std::vec::Vec;
+
+use crate::std::os::raw::c_int;
+use crate::std::os::raw::c_uint;
+use crate::std::os::raw::c_ulong;
+use crate::std::os::raw::c_void;
+
+use super::applibs::{i2c_read, i2c_write, spi_read, spi_write};
+// not glob imported, types has the constants bound for sphere-lib
+use super::errno::{EAGAIN, EBADF, EEXIST, EINVAL, ENOENT, ENOSPC, ESPIPE};
+use super::types::*;
+use super::{with_state, Resource, State, TimerFd};
+
+pub unsafe extern "C" fn read(fd: c_int, buf: *mut c_void, count: usize) -> isize {
+    with_state(|state| {
+        state.record("read", format!("{}, {}", fd, count));
+        if state.take_failure("read").is_some() {
+            return -1;
+        }
+        let now_ns = state.monotonic_ns;
+        let queue = match state.fds.get_mut(&fd) {
+            Some(Resource::Uart(uart)) => state.uart_rx.get_mut(uart),
+            Some(Resource::Socket(component)) => state.socket_rx.get_mut(component),
+            Some(Resource::I2c(interface)) => {
+                // plain reads and writes go to the default target address
+                let interface = *interface;
+                let buf = host_std::slice::from_raw_parts_mut(buf as *mut u8, count);
+                return i2c_read(state, interface, None, buf);
+            }
+            Some(Resource::Spi(interface)) => {
+                let interface = *interface;
+                let buf = host_std::slice::from_raw_parts_mut(buf as *mut u8, count);
+                return spi_read(state, interface, buf);
+            }
+            Some(Resource::MutableFile(position)) => {
+                let data = state.mutable_storage.get(*position..).unwrap_or_default();
+                let count = count.min(data.len());
+                let buf = host_std::slice::from_raw_parts_mut(buf as *mut u8, count);
+                buf.copy_from_slice(&data[..count]);
+                *position += count;
+                return count as isize;
+            }
+            Some(Resource::ImageFile(data, position)) => {
+                let data = data.get(*position..).unwrap_or_default();
+                let count = count.min(data.len());
+                let buf = host_std::slice::from_raw_parts_mut(buf as *mut u8, count);
+                buf.copy_from_slice(&data[..count]);
+                *position += count;
+                return count as isize;
+            }
+            Some(Resource::TimerFd(timer)) => {
+                // the number of expirations since the last read, as u64
+                timer.update(now_ns);
+                if count < 8 {
+                    return state.fail(EINVAL) as isize;
+                }
+                if timer.expirations == 0 {
+                    return state.fail(EAGAIN) as isize;
+                }
+                *(buf as *mut u64) = host_std::mem::take(&mut timer.expirations);
+                return 8;
+            }
+            Some(Resource::EventFd(counter)) => {
+                if count < 8 {
+                    return state.fail(EINVAL) as isize;
+                }
+                if *counter == 0 {
+                    return state.fail(EAGAIN) as isize;
+                }
+                *(buf as *mut u64) = host_std::mem::take(counter);
+                return 8;
+            }
+            Some(Resource::Gpio(_)) | Some(Resource::Epoll(_)) => {
+                return state.fail(EINVAL) as isize
+            }
+            None => return state.fail(EBADF) as isize,
+        };
+        let queue = match queue {
+            Some(queue) if !queue.is_empty() => queue,
+            _ => return state.fail(EAGAIN) as isize,
+        };
+
+        let count = count.min(queue.len());
+        let buf = host_std::slice::from_raw_parts_mut(buf as *mut u8, count);
+        for (dst, src) in buf.iter_mut().zip(queue.drain(..count)) {
+            *dst = src;
+        }
+        count as isize
+    })
+}
+
+pub unsafe extern "C" fn write(fd: c_int, buf: *const c_void, count: usize) -> isize {
+    let data = host_std::slice::from_raw_parts(buf as *const u8, count);
+    with_state(|state| {
+        state.record("write", format!("{}, {}", fd, count));
+        if state.take_failure("write").is_some() {
+            return -1;
+        }
+        match state.fds.get(&fd) {
+            Some(Resource::Uart(uart)) => {
+                let uart = *uart;
+                state
+                    .uart_tx
+                    .entry(uart)
+                    .or_default()
+                    .extend_from_slice(data);
+            }
+            Some(Resource::Socket(component)) => {
+                let component = component.clone();
+                state
+                    .socket_tx
+                    .entry(component)
+                    .or_default()
+                    .extend_from_slice(data);
+            }
+            Some(Resource::I2c(interface)) => {
+                let interface = *interface;
+                return i2c_write(state, interface, None, data);
+            }
+            Some(Resource::Spi(interface)) => {
+                let interface = *interface;
+                return spi_write(state, interface, data);
+            }
+            Some(Resource::MutableFile(position)) => {
+                let position = *position;
+                return mutable_file_write(state, fd, position, data);
+            }
+            Some(Resource::EventFd(_)) if count < 8 => return state.fail(EINVAL) as isize,
+            Some(Resource::EventFd(_)) => {
+                if let Some(Resource::EventFd(counter)) = state.fds.get_mut(&fd) {
+                    *counter += *(buf as *const u64);
+                }
+                return 8;
+            }
+            Some(Resource::Gpio(_)) | Some(Resource::Epoll(_)) | Some(Resource::TimerFd(_)) => {
+                return state.fail(EINVAL) as isize
+            }
+            // opened read-only
+            Some(Resource::ImageFile(..)) | None => return state.fail(EBADF) as isize,
+        }
+        count as isize
+    })
+}
+
+// writes past the end zero-fill the gap, like sparse files read back
+fn mutable_file_write(state: &mut State, fd: c_int, position: usize, data: &[u8]) -> isize {
+    let end = position + data.len();
+    if state.mutable_storage_size.map_or(true, |size| end > size) {
+        return state.fail(ENOSPC) as isize;
+    }
+    let storage = &mut state.mutable_storage;
+    if storage.len() < end {
+        storage.resize(end, 0);
+    }
+    storage[position..end].copy_from_slice(data);
+    state.fds.insert(fd, Resource::MutableFile(end));
+    data.len() as isize
+}
+
+pub unsafe extern "C" fn close(fd: c_int) -> c_int {
+    with_state(|state| {
+        state.record("close", format!("{}", fd));
+        if state.take_failure("close").is_some() {
+            return -1;
+        }
+        match state.fds.remove(&fd) {
+            Some(_) => {
+                // closing a fd drops it from all epoll sets
+                for resource in state.fds.values_mut() {
+                    if let Resource::Epoll(interests) = resource {
+                        interests.remove(&fd);
+                    }
+                }
+                0
+            }
+            None => state.fail(EBADF),
+        }
+    })
+}
+
+pub unsafe extern "C" fn lseek(fd: c_int, offset: off_t, whence: c_int) -> off_t {
+    with_state(|state| {
+        state.record("lseek", format!("{}, {}, {}", fd, offset, whence));
+        if state.take_failure("lseek").is_some() {
+            return -1;
+        }
+        let len = state.mutable_storage.len() as off_t;
+        let position = match state.fds.get_mut(&fd) {
+            Some(Resource::MutableFile(position)) => position,
+            Some(_) => return state.fail(ESPIPE) as off_t,
+            None => return state.fail(EBADF) as off_t,
+        };
+        let base = match whence as u32 {
+            SEEK_SET => 0,
+            SEEK_CUR => *position as off_t,
+            SEEK_END => len,
+            _ => return state.fail(EINVAL) as off_t,
+        };
+        if base + offset < 0 {
+            return state.fail(EINVAL) as off_t;
+        }
+        *position = (base + offset) as usize;
+        *position as off_t
+    })
+}
+
+/// Storage is written through, syncing only checks the fd.
+pub unsafe extern "C" fn fsync(fd: c_int) -> c_int {
+    with_state(|state| {
+        state.record("fsync", format!("{}", fd));
+        if state.take_failure("fsync").is_some() {
+            return -1;
+        }
+        match state.fds.get(&fd) {
+            Some(Resource::MutableFile(_)) => 0,
+            Some(_) => state.fail(EINVAL),
+            None => state.fail(EBADF),
+        }
+    })
+}
+
+pub unsafe extern "C" fn ftruncate(fd: c_int, length: off_t) -> c_int {
+    with_state(|state| {
+        state.record("ftruncate", format!("{}, {}", fd, length));
+        if state.take_failure("ftruncate").is_some() {
+            return -1;
+        }
+        match state.fds.get(&fd) {
+            Some(Resource::MutableFile(_)) => {}
+            Some(_) => return state.fail(EINVAL),
+            None => return state.fail(EBADF),
+        }
+        if length < 0 {
+            return state.fail(EINVAL);
+        }
+        if state
+            .mutable_storage_size
+            .map_or(true, |size| length as usize > size)
+        {
+            return state.fail(ENOSPC);
+        }
+        state.mutable_storage.resize(length as usize, 0);
+        0
+    })
+}
+
+/// Ends the host process, like on the device.
+pub unsafe extern "C" fn exit(status: c_int) -> ! {
+    with_state(|state| state.record("exit", format!("{}", status)));
+    host_std::process::exit(status)
+}
+
+pub unsafe extern "C" fn _exit(status: c_int) -> ! {
+    with_state(|state| state.record("_exit", format!("{}", status)));
+    host_std::process::exit(status)
+}
+
+pub unsafe extern "C" fn sleep(seconds: c_uint) -> c_uint {
+    with_state(|state| {
+        state.record("sleep", format!("{}", seconds));
+        state.monotonic_ns += seconds as u64 * 1_000_000_000;
+    });
+    0
+}
+
+pub unsafe extern "C" fn usleep(useconds: useconds_t) -> c_int {
+    with_state(|state| {
+        state.record("usleep", format!("{}", useconds));
+        state.monotonic_ns += useconds as u64 * 1_000;
+    });
+    0
+}
+
+/// Not recorded, sphere-lib reads errno after failing calls only.
+pub unsafe extern "C" fn __errno_location() -> *mut c_int {
+    // the thread local state never moves, so the pointer stays valid
+    with_state(|state| &mut state.errno as *mut c_int)
+}
+
+/// Seconds since the epoch the simulated realtime clock starts at.
+const REALTIME_EPOCH_OFFSET: u64 = 1_577_836_800;
+
+pub unsafe extern "C" fn clock_gettime(clk: clockid_t, tp: *mut timespec) -> c_int {
+    with_state(|state| {
+        state.record("clock_gettime", format!("{}", clk));
+        if state.take_failure("clock_gettime").is_some() {
+            return -1;
+        }
+        let ns = match clk as u32 {
+            CLOCK_MONOTONIC => state.monotonic_ns,
+            CLOCK_REALTIME => state.monotonic_ns + REALTIME_EPOCH_OFFSET * 1_000_000_000,
+            _ => return state.fail(EINVAL),
+        };
+        (*tp).tv_sec = (ns / 1_000_000_000) as time_t;
+        (*tp).tv_nsec = (ns % 1_000_000_000) as _;
+        0
+    })
+}
+
+pub unsafe extern "C" fn malloc(size: usize) -> *mut c_void {
+    libc::malloc(size) as *mut c_void
+}
+
+pub unsafe extern "C" fn free(ptr: *mut c_void) {
+    libc::free(ptr as *mut libc::c_void)
+}
+
+pub unsafe extern "C" fn timer_create(
+    clockid: clockid_t,
+    sevp: *mut sigevent,
+    timerid: *mut timer_t,
+) -> c_int {
+    with_state(|state| {
+        let signo = if sevp.is_null() {
+            0
+        } else {
+            (*sevp).sigev_signo
+        };
+        state.record("timer_create", format!("{}, {}", clockid, signo));
+        if state.take_failure("timer_create").is_some() {
+            return -1;
+        }
+        state.next_timer += 1;
+        state.timers.insert(state.next_timer, None);
+        *timerid = state.next_timer as timer_t;
+        0
+    })
+}
+
+pub unsafe extern "C" fn timer_settime(
+    timerid: timer_t,
+    flags: c_int,
+    new_value: *const itimerspec,
+    old_value: *mut itimerspec,
+) -> c_int {
+    with_state(|state| {
+        let new_value = *new_value;
+        state.record(
+            "timer_settime",
+            format!("{}, {}, {:?}", timerid as usize, flags, new_value),
+        );
+        if state.take_failure("timer_settime").is_some() {
+            return -1;
+        }
+        match state.timers.get_mut(&(timerid as usize)) {
+            Some(timer) => {
+                if !old_value.is_null() {
+                    *old_value = timer.unwrap_or(itimerspec {
+                        it_interval: timespec {
+                            tv_sec: 0,
+                            tv_nsec: 0,
+                        },
+                        it_value: timespec {
+                            tv_sec: 0,
+                            tv_nsec: 0,
+                        },
+                    });
+                }
+                let disarm = new_value.it_value.tv_sec == 0 && new_value.it_value.tv_nsec == 0;
+                *timer = if disarm { None } else { Some(new_value) };
+                0
+            }
+            None => state.fail(EINVAL),
+        }
+    })
+}
+
+pub unsafe extern "C" fn timer_delete(timerid: timer_t) -> c_int {
+    with_state(|state| {
+        state.record("timer_delete", format!("{}", timerid as usize));
+        match state.timers.remove(&(timerid as usize)) {
+            Some(_) => 0,
+            None => state.fail(EINVAL),
+        }
+    })
+}
+
+pub type sighandler_t = Option<unsafe extern "C" fn(signo: c_int)>;
+
+pub unsafe extern "C" fn signal(signum: c_int, handler: sighandler_t) -> sighandler_t {
+    with_state(|state| {
+        state.record("signal", format!("{}, {}", signum, handler.is_some()));
+        let previous = state.signal_handlers.remove(&signum).flatten();
+        state.signal_handlers.insert(signum, handler);
+        previous
+    })
+}
+
+// bits of a `sigset_t` word
+const SIGSET_WORD_BITS: usize = 8 * host_std::mem::size_of::<c_ulong>();
+
+pub(crate) fn sigismember(set: &sigset_t, signum: c_int) -> bool {
+    let bit = (signum - 1) as usize;
+    signum > 0 && set.__bits[bit / SIGSET_WORD_BITS] & (1 << (bit % SIGSET_WORD_BITS)) != 0
+}
+
+pub unsafe extern "C" fn sigemptyset(set: *mut sigset_t) -> c_int {
+    (*set).__bits = [0; 16];
+    0
+}
+
+pub unsafe extern "C" fn sigaddset(set: *mut sigset_t, signum: c_int) -> c_int {
+    let bit = (signum - 1) as usize;
+    if signum <= 0 || bit >= 16 * SIGSET_WORD_BITS {
+        return with_state(|state| state.fail(EINVAL));
+    }
+    (*set).__bits[bit / SIGSET_WORD_BITS] |= 1 << (bit % SIGSET_WORD_BITS);
+    0
+}
+
+pub unsafe extern "C" fn sigprocmask(
+    how: c_int,
+    set: *const sigset_t,
+    oldset: *mut sigset_t,
+) -> c_int {
+    let unblocked = with_state(|state| {
+        state.record("sigprocmask", format!("{}", how));
+        if state.take_failure("sigprocmask").is_some() {
+            return Err(-1);
+        }
+        if !oldset.is_null() {
+            *oldset = state.signal_mask;
+        }
+        if !set.is_null() {
+            let set = &*set;
+            match how as u32 {
+                SIG_BLOCK => {
+                    for (word, bits) in state.signal_mask.__bits.iter_mut().zip(&set.__bits) {
+                        *word |= bits;
+                    }
+                }
+                SIG_SETMASK => state.signal_mask = *set,
+                _ => return Err(state.fail(EINVAL)),
+            }
+        }
+
+        let mask = state.signal_mask;
+        let (blocked, unblocked) = state
+            .pending_signals
+            .iter()
+            .partition(|&&signo| sigismember(&mask, signo));
+        state.pending_signals = blocked;
+        Ok(unblocked)
+    });
+
+    match unblocked {
+        Ok(unblocked) => {
+            // delivered outside of the state borrow, like `raise_signal`
+            for signo in unblocked {
+                super::raise_signal(signo);
+            }
+            0
+        }
+        Err(result) => result,
+    }
+}
+
+pub unsafe extern "C" fn epoll_create1(flags: c_int) -> c_int {
+    with_state(|state| {
+        state.record("epoll_create1", format!("{}", flags));
+        if state.take_failure("epoll_create1").is_some() {
+            return -1;
+        }
+        state.allocate_fd(Resource::Epoll(HashMap::new()))
+    })
+}
+
+pub unsafe extern "C" fn epoll_ctl(
+    epfd: c_int,
+    op: c_int,
+    fd: c_int,
+    event: *mut epoll_event,
+) -> c_int {
+    with_state(|state| {
+        let events = if event.is_null() { 0 } else { (*event).events };
+        state.record("epoll_ctl", format!("{}, {}, {}, {}", epfd, op, fd, events));
+        if state.take_failure("epoll_ctl").is_some() {
+            return -1;
+        }
+        if fd == epfd || !state.fds.contains_key(&fd) {
+            return state.fail(EBADF);
+        }
+        let interests = match state.fds.get_mut(&epfd) {
+            Some(Resource::Epoll(interests)) => interests,
+            Some(_) => return state.fail(EINVAL),
+            None => return state.fail(EBADF),
+        };
+        let registered = interests.contains_key(&fd);
+        match op as u32 {
+            EPOLL_CTL_ADD if registered => state.fail(EEXIST),
+            EPOLL_CTL_MOD | EPOLL_CTL_DEL if !registered => state.fail(ENOENT),
+            EPOLL_CTL_ADD | EPOLL_CTL_MOD => {
+                interests.insert(fd, *event);
+                0
+            }
+            EPOLL_CTL_DEL => {
+                interests.remove(&fd);
+                0
+            }
+            _ => state.fail(EINVAL),
+        }
+    })
+}
+
+/// Instead of blocking, lets the simulated time pass until the next timerfd
+/// in the set expires or `timeout` (in ms) runs out. With nothing that could
+/// become ready and no timeout it returns 0 right away.
+pub unsafe extern "C" fn epoll_wait(
+    epfd: c_int,
+    events: *mut epoll_event,
+    maxevents: c_int,
+    timeout: c_int,
+) -> c_int {
+    with_state(|state| {
+        state.record(
+            "epoll_wait",
+            format!("{}, {}, {}", epfd, maxevents, timeout),
+        );
+        if state.take_failure("epoll_wait").is_some() {
+            return -1;
+        }
+        let mut interests: Vec<(c_int, epoll_event)> = match state.fds.get(&epfd) {
+            Some(Resource::Epoll(interests)) => {
+                interests.iter().map(|(fd, event)| (*fd, *event)).collect()
+            }
+            Some(_) => return state.fail(EINVAL),
+            None => return state.fail(EBADF),
+        };
+        if maxevents <= 0 {
+            return state.fail(EINVAL);
+        }
+        interests.sort_by_key(|(fd, _)| *fd);
+
+        let deadline_ns = match timeout {
+            timeout if timeout < 0 => None,
+            timeout => Some(state.monotonic_ns + timeout as u64 * 1_000_000),
+        };
+
+        loop {
+            let ready: Vec<epoll_event> = interests
+                .iter()
+                .filter_map(|(fd, interest)| {
+                    let events = ready_events(state, *fd) & (interest.events | EPOLLERR | EPOLLHUP);
+                    if events == 0 {
+                        None
+                    } else {
+                        Some(epoll_event {
+                            events,
+                            data: interest.data,
+                        })
+                    }
+                })
+                .take(maxevents as usize)
+                .collect();
+
+            if !ready.is_empty() {
+                for (i, event) in ready.iter().enumerate() {
+                    *events.add(i) = *event;
+                }
+                return ready.len() as c_int;
+            }
+
+            let next_expiry_ns = interests
+                .iter()
+                .filter_map(|(fd, _)| match state.fds.get(fd) {
+                    Some(Resource::TimerFd(timer)) => timer.next_ns,
+                    _ => None,
+                })
+                .min();
+
+            match (next_expiry_ns, deadline_ns) {
+                (Some(next_ns), Some(deadline_ns)) if next_ns > deadline_ns => {
+                    state.monotonic_ns = state.monotonic_ns.max(deadline_ns);
+                    return 0;
+                }
+                (Some(next_ns), _) => state.monotonic_ns = state.monotonic_ns.max(next_ns),
+                (None, Some(deadline_ns)) => {
+                    state.monotonic_ns = state.monotonic_ns.max(deadline_ns);
+                    return 0;
+                }
+                (None, None) => return 0,
+            }
+        }
+    })
+}
+
+pub unsafe extern "C" fn poll(fds: *mut pollfd, nfds: nfds_t, timeout: c_int) -> c_int {
+    let fds = host_std::slice::from_raw_parts_mut(fds, nfds as usize);
+    with_state(|state| {
+        state.record("poll", format!("{:?}, {}", fds, timeout));
+        if state.take_failure("poll").is_some() {
+            return -1;
+        }
+        // like a zero timeout, waiting only advances the simulated time
+        let mut ready = 0;
+        for pollfd in fds.iter_mut() {
+            let interest = pollfd.events as u32 | EPOLLERR | EPOLLHUP;
+            pollfd.revents = (ready_events(state, pollfd.fd) & interest) as i16;
+            if pollfd.revents != 0 {
+                ready += 1;
+            }
+        }
+        if ready == 0 && timeout > 0 {
+            state.monotonic_ns += timeout as u64 * 1_000_000;
+        }
+        ready
+    })
+}
+
+// Level-triggered readiness, 0 for fds that are closed or never become ready.
+fn ready_events(state: &mut State, fd: c_int) -> u32 {
+    let now_ns = state.monotonic_ns;
+    let readable = |queue: Option<&host_std::collections::VecDeque<u8>>| match queue {
+        Some(queue) if !queue.is_empty() => EPOLLIN,
+        _ => 0,
+    };
+    match state.fds.get_mut(&fd) {
+        Some(Resource::Uart(uart)) => readable(state.uart_rx.get(uart)) | EPOLLOUT,
+        Some(Resource::Socket(component)) => readable(state.socket_rx.get(component)) | EPOLLOUT,
+        // transfers complete right away
+        Some(Resource::I2c(_))
+        | Some(Resource::Spi(_))
+        | Some(Resource::MutableFile(_))
+        | Some(Resource::ImageFile(..)) => EPOLLIN | EPOLLOUT,
+        Some(Resource::TimerFd(timer)) => {
+            timer.update(now_ns);
+            if timer.expirations > 0 {
+                EPOLLIN
+            } else {
+                0
+            }
+        }
+        Some(Resource::EventFd(counter)) if *counter > 0 => EPOLLIN | EPOLLOUT,
+        Some(Resource::EventFd(_)) => EPOLLOUT,
+        Some(Resource::Gpio(_)) | Some(Resource::Epoll(_)) | None => 0,
+    }
+}
+
+pub unsafe extern "C" fn eventfd(initval: c_uint, flags: c_int) -> c_int {
+    with_state(|state| {
+        state.record("eventfd", format!("{}, {}", initval, flags));
+        if state.take_failure("eventfd").is_some() {
+            return -1;
+        }
+        state.allocate_fd(Resource::EventFd(initval as u64))
+    })
+}
+
+pub unsafe extern "C" fn timerfd_create(clockid: c_int, flags: c_int) -> c_int {
+    with_state(|state| {
+        state.record("timerfd_create", format!("{}, {}", clockid, flags));
+        if state.take_failure("timerfd_create").is_some() {
+            return -1;
+        }
+        if clockid as u32 != CLOCK_MONOTONIC {
+            return state.fail(EINVAL);
+        }
+        state.allocate_fd(Resource::TimerFd(TimerFd::default()))
+    })
+}
+
+pub unsafe extern "C" fn timerfd_settime(
+    fd: c_int,
+    flags: c_int,
+    new_value: *const itimerspec,
+    old_value: *mut itimerspec,
+) -> c_int {
+    with_state(|state| {
+        let new_value = *new_value;
+        state.record(
+            "timerfd_settime",
+            format!("{}, {}, {:?}", fd, flags, new_value),
+        );
+        if state.take_failure("timerfd_settime").is_some() {
+            return -1;
+        }
+        let now_ns = state.monotonic_ns;
+        let timer = match state.fds.get_mut(&fd) {
+            Some(Resource::TimerFd(timer)) => timer,
+            Some(_) => return state.fail(EINVAL),
+            None => return state.fail(EBADF),
+        };
+        timer.update(now_ns);
+        if !old_value.is_null() {
+            *old_value = timer_value(timer, now_ns);
+        }
+        let value_ns = to_ns(&new_value.it_value);
+        timer.expirations = 0;
+        timer.interval_ns = to_ns(&new_value.it_interval);
+        timer.next_ns = match value_ns {
+            0 => None,
+            value_ns if flags as u32 & TFD_TIMER_ABSTIME != 0 => Some(value_ns),
+            value_ns => Some(now_ns + value_ns),
+        };
+        0
+    })
+}
+
+pub unsafe extern "C" fn timerfd_gettime(fd: c_int, curr_value: *mut itimerspec) -> c_int {
+    with_state(|state| {
+        state.record("timerfd_gettime", format!("{}", fd));
+        if state.take_failure("timerfd_gettime").is_some() {
+            return -1;
+        }
+        let now_ns = state.monotonic_ns;
+        match state.fds.get_mut(&fd) {
+            Some(Resource::TimerFd(timer)) => {
+                timer.update(now_ns);
+                *curr_value = timer_value(timer, now_ns);
+                0
+            }
+            Some(_) => state.fail(EINVAL),
+            None => state.fail(EBADF),
+        }
+    })
+}
+
+fn timer_value(timer: &TimerFd, now_ns: u64) -> itimerspec {
+    itimerspec {
+        it_interval: from_ns(timer.interval_ns),
+        it_value: from_ns(timer.next_ns.map_or(0, |next_ns| next_ns - now_ns)),
+    }
+}
+
+fn to_ns(time: &timespec) -> u64 {
+    time.tv_sec as u64 * 1_000_000_000 + time.tv_nsec as u64
+}
+
+fn from_ns(ns: u64) -> timespec {
+    timespec {
+        tv_sec: (ns / 1_000_000_000) as time_t,
+        tv_nsec: (ns % 1_000_000_000) as _,
+    }
+}
@@ -0,0 +1,433 @@
+// C types and constants of the simulated API surface. Names and shapes follow
+// what bindgen generates for the Azure Sphere sysroot, so sphere-lib compiles
+// unchanged against either backend.
+
+use crate::std::os::raw::c_char;
+use crate::std::os::raw::c_int;
+use crate::std::os::raw::c_long;
+use crate::std::os::raw::c_longlong;
+use crate::std::os::raw::c_short;
+use crate::std::os::raw::c_uchar;
+use crate::std::os::raw::c_uint;
+use crate::std::os::raw::c_ulong;
+use crate::std::os::raw::c_void;
+
+// POSIX
+
+pub type off_t = c_longlong;
+pub type time_t = c_int;
+pub type clockid_t = c_int;
+pub type useconds_t = c_uint;
+pub type timer_t = *mut c_void;
+
+pub const SEEK_SET: u32 = 0;
+pub const SEEK_CUR: u32 = 1;
+pub const SEEK_END: u32 = 2;
+
+pub const EINTR: u32 = 4;
+pub const EAGAIN: u32 = 11;
+pub const EBUSY: u32 = 16;
+pub const EINVAL: u32 = 22;
+pub const ENOSPC: u32 = 28;
+
+pub const CLOCK_REALTIME: u32 = 0;
+pub const CLOCK_MONOTONIC: u32 = 1;
+
+pub const SIGEV_SIGNAL: u32 = 0;
+pub const SIGEV_NONE: u32 = 1;
+pub const SIGEV_THREAD: u32 = 2;
+
+pub const SIGALRM: u32 = 14;
+pub const SIGTERM: u32 = 15;
+pub const SIG_BLOCK: u32 = 0;
+pub const SIG_SETMASK: u32 = 2;
+
+pub const EPOLL_CLOEXEC: u32 = 524288;
+pub const EPOLLIN: u32 = 1;
+pub const EPOLLOUT: u32 = 4;
+pub const EPOLLERR: u32 = 8;
+pub const EPOLLHUP: u32 = 16;
+pub const EPOLL_CTL_ADD: u32 = 1;
+pub const EPOLL_CTL_DEL: u32 = 2;
+pub const EPOLL_CTL_MOD: u32 = 3;
+
+pub const TFD_NONBLOCK: u32 = 2048;
+pub const TFD_CLOEXEC: u32 = 524288;
+pub const TFD_TIMER_ABSTIME: u32 = 1;
+pub const EFD_NONBLOCK: u32 = 2048;
+pub const EFD_CLOEXEC: u32 = 524288;
+pub const POLLIN: u32 = 1;
+pub const POLLOUT: u32 = 4;
+
+#[repr(C)]
+#[derive(Debug, Copy, Clone)]
+pub struct timespec {
+    pub tv_sec: time_t,
+    pub tv_nsec: c_long,
+}
+
+#[repr(C)]
+#[derive(Debug, Copy, Clone)]
+pub struct itimerspec {
+    pub it_interval: timespec,
+    pub it_value: timespec,
+}
+
+#[repr(C)]
+#[derive(Copy, Clone)]
+pub union sigval {
+    pub sival_int: c_int,
+    pub sival_ptr: *mut c_void,
+}
+
+#[repr(C)]
+#[derive(Copy, Clone)]
+pub struct sigevent {
+    pub sigev_value: sigval,
+    pub sigev_signo: c_int,
+    pub sigev_notify: c_int,
+    pub sigev_notify_function: Option<unsafe extern "C" fn(arg1: sigval)>,
+    pub sigev_notify_attributes: *mut c_void,
+    pub __pad: [c_char; 44usize],
+}
+
+// 128 bytes like musl's, on the host's 64-bit longs
+#[repr(C)]
+#[derive(Debug, Copy, Clone)]
+pub struct __sigset_t {
+    pub __bits: [c_ulong; 16usize],
+}
+pub type sigset_t = __sigset_t;
+
+#[repr(C)]
+#[derive(Copy, Clone)]
+pub union epoll_data {
+    pub ptr: *mut c_void,
+    pub fd: c_int,
+    pub u32: u32,
+    pub u64: u64,
+}
+pub type epoll_data_t = epoll_data;
+
+#[repr(C)]
+#[derive(Copy, Clone)]
+pub struct epoll_event {
+    pub events: u32,
+    pub data: epoll_data_t,
+}
+
+pub type nfds_t = c_ulong;
+
+#[repr(C)]
+#[derive(Debug, Copy, Clone)]
+pub struct pollfd {
+    pub fd: c_int,
+    pub events: c_short,
+    pub revents: c_short,
+}
+
+// Applibs
+
+pub type GPIO_Id = c_int;
+pub type GPIO_OutputMode_Type = u8;
+pub type GPIO_Value_Type = u8;
+
+pub const GPIO_OutputMode_PushPull: GPIO_OutputMode_Type = 0;
+pub const GPIO_OutputMode_OpenDrain: GPIO_OutputMode_Type = 1;
+pub const GPIO_OutputMode_OpenSource: GPIO_OutputMode_Type = 2;
+
+pub const GPIO_Value_Low: GPIO_Value_Type = 0;
+pub const GPIO_Value_High: GPIO_Value_Type = 1;
+
+pub const UART_STRUCTS_VERSION: u32 = 1;
+
+pub type UART_Id = c_int;
+pub type UART_BaudRate_Type = u32;
+pub type UART_BlockingMode_Type = u8;
+pub const UART_BlockingMode_NonBlocking: UART_BlockingMode_Type = 0;
+pub const UART_BlockingMode_Blocking: UART_BlockingMode_Type = 1;
+pub type UART_DataBits_Type = u8;
+pub const UART_DataBits_Five: UART_DataBits_Type = 5;
+pub const UART_DataBits_Six: UART_DataBits_Type = 6;
+pub const UART_DataBits_Seven: UART_DataBits_Type = 7;
+pub const UART_DataBits_Eight: UART_DataBits_Type = 8;
+pub type UART_Parity_Type = u8;
+pub const UART_Parity_None: UART_Parity_Type = 0;
+pub const UART_Parity_Even: UART_Parity_Type = 1;
+pub const UART_Parity_Odd: UART_Parity_Type = 2;
+pub type UART_StopBits_Type = u8;
+pub const UART_StopBits_One: UART_StopBits_Type = 1;
+pub const UART_StopBits_Two: UART_StopBits_Type = 2;
+pub type UART_FlowControl_Type = u8;
+pub const UART_FlowControl_None: UART_FlowControl_Type = 0;
+pub const UART_FlowControl_RTSCTS: UART_FlowControl_Type = 1;
+pub const UART_FlowControl_XONXOFF: UART_FlowControl_Type = 2;
+
+#[repr(C)]
+#[derive(Debug, Copy, Clone)]
+pub struct z__UART_Config_Base {
+    pub z__magicAndVersion: u32,
+}
+
+#[repr(C)]
+#[derive(Debug, Copy, Clone)]
+pub struct z__UART_Config_v1 {
+    pub z__magicAndVersion: u32,
+    pub baudRate: UART_BaudRate_Type,
+    pub blockingMode: UART_BlockingMode_Type,
+    pub dataBits: UART_DataBits_Type,
+    pub parity: UART_Parity_Type,
+    pub stopBits: UART_StopBits_Type,
+    pub flowControl: UART_FlowControl_Type,
+}
+
+pub type UART_Config = z__UART_Config_v1;
+
+pub const I2C_STRUCTS_VERSION: u32 = 1;
+pub const I2C_BUS_SPEED_STANDARD: u32 = 100000;
+pub const I2C_BUS_SPEED_FAST: u32 = 400000;
+pub const I2C_BUS_SPEED_FAST_PLUS: u32 = 1000000;
+
+pub type I2C_InterfaceId = c_int;
+pub type I2C_DeviceAddress = u32;
+pub type I2C_BusSpeed = u32;
+
+pub const SPI_STRUCTS_VERSION: u32 = 1;
+
+pub type SPI_InterfaceId = c_int;
+pub type SPI_ChipSelectId = c_int;
+pub type SPI_ChipSelectPolarity = u32;
+pub type SPI_Mode = u32;
+pub type SPI_BitOrder = u32;
+pub type SPI_TransferFlags = u32;
+
+pub const SPI_ChipSelectPolarity_Invalid: SPI_ChipSelectPolarity = 0;
+pub const SPI_ChipSelectPolarity_ActiveLow: SPI_ChipSelectPolarity = 1;
+pub const SPI_ChipSelectPolarity_ActiveHigh: SPI_ChipSelectPolarity = 2;
+
+pub const SPI_Mode_Invalid: SPI_Mode = 0;
+pub const SPI_Mode_0: SPI_Mode = 1;
+pub const SPI_Mode_1: SPI_Mode = 2;
+pub const SPI_Mode_2: SPI_Mode = 3;
+pub const SPI_Mode_3: SPI_Mode = 4;
+
+pub const SPI_BitOrder_Invalid: SPI_BitOrder = 0;
+pub const SPI_BitOrder_LsbFirst: SPI_BitOrder = 1;
+pub const SPI_BitOrder_MsbFirst: SPI_BitOrder = 2;
+
+pub const SPI_TransferFlags_None: SPI_TransferFlags = 0;
+pub const SPI_TransferFlags_Read: SPI_TransferFlags = 1;
+pub const SPI_TransferFlags_Write: SPI_TransferFlags = 2;
+
+#[repr(C)]
+#[derive(Debug, Copy, Clone)]
+pub struct z__SPIMaster_Config_Base {
+    pub z__magicAndVersion: u32,
+}
+
+#[repr(C)]
+#[derive(Debug, Copy, Clone)]
+pub struct z__SPIMaster_Config_v1 {
+    pub z__magicAndVersion: u32,
+    pub csPolarity: SPI_ChipSelectPolarity,
+}
+
+pub type SPIMaster_Config = z__SPIMaster_Config_v1;
+
+#[repr(C)]
+#[derive(Debug, Copy, Clone)]
+pub struct z__SPIMaster_Transfer_Base {
+    pub z__magicAndVersion: u32,
+}
+
+#[repr(C)]
+#[derive(Debug, Copy, Clone)]
+pub struct z__SPIMaster_Transfer_v1 {
+    pub z__magicAndVersion: u32,
+    pub flags: SPI_TransferFlags,
+    pub writeData: *const u8,
+    pub readData: *mut u8,
+    pub length: usize,
+}
+
+pub type SPIMaster_Transfer = z__SPIMaster_Transfer_v1;
+
+// curl
+
+#[repr(C)]
+#[derive(Debug, Copy, Clone)]
+pub struct Curl_easy {
+    _unused: [u8; 0],
+}
+pub type CURL = Curl_easy;
+
+#[repr(C)]
+#[derive(Debug, Copy, Clone)]
+pub struct curl_slist {
+    pub data: *mut c_char,
+    pub next: *mut curl_slist,
+}
+
+pub type CURLcode = u32;
+pub const CURLcode_CURLE_OK: CURLcode = 0;
+pub const CURLcode_CURLE_UNSUPPORTED_PROTOCOL: CURLcode = 1;
+pub const CURLcode_CURLE_FAILED_INIT: CURLcode = 2;
+pub const CURLcode_CURLE_URL_MALFORMAT: CURLcode = 3;
+pub const CURLcode_CURLE_COULDNT_RESOLVE_HOST: CURLcode = 6;
+pub const CURLcode_CURLE_COULDNT_CONNECT: CURLcode = 7;
+pub const CURLcode_CURLE_WRITE_ERROR: CURLcode = 23;
+pub const CURLcode_CURLE_OPERATION_TIMEDOUT: CURLcode = 28;
+pub const CURLcode_CURLE_SSL_CONNECT_ERROR: CURLcode = 35;
+pub const CURLcode_CURLE_PEER_FAILED_VERIFICATION: CURLcode = 60;
+
+pub type CURLoption = u32;
+pub const CURLoption_CURLOPT_WRITEDATA: CURLoption = 10001;
+pub const CURLoption_CURLOPT_URL: CURLoption = 10002;
+pub const CURLoption_CURLOPT_POSTFIELDS: CURLoption = 10015;
+pub const CURLoption_CURLOPT_HTTPHEADER: CURLoption = 10023;
+pub const CURLoption_CURLOPT_CUSTOMREQUEST: CURLoption = 10036;
+pub const CURLoption_CURLOPT_CAINFO: CURLoption = 10065;
+pub const CURLoption_CURLOPT_WRITEFUNCTION: CURLoption = 20011;
+pub const CURLoption_CURLOPT_SSL_CTX_FUNCTION: CURLoption = 20108;
+pub const CURLoption_CURLOPT_VERBOSE: CURLoption = 41;
+pub const CURLoption_CURLOPT_SSL_VERIFYHOST: CURLoption = 81;
+
+pub type CURLINFO = u32;
+pub const CURLINFO_CURLINFO_RESPONSE_CODE: CURLINFO = 2097154;
+
+pub const CURL_GLOBAL_SSL: u32 = 1;
+pub const CURL_GLOBAL_WIN32: u32 = 2;
+pub const CURL_GLOBAL_ALL: u32 = 3;
+
+pub type curl_write_callback = Option<
+    unsafe extern "C" fn(
+        buffer: *mut c_char,
+        size: usize,
+        nitems: usize,
+        outstream: *mut c_void,
+    ) -> usize,
+>;
+
+pub type curl_ssl_ctx_callback = Option<
+    unsafe extern "C" fn(curl: *mut CURL, ssl_ctx: *mut c_void, userptr: *mut c_void) -> CURLcode,
+>;
+
+// Azure IoT SDK
+
+#[repr(C)]
+#[derive(Debug, Copy, Clone)]
+pub struct IOTHUB_CLIENT_CORE_LL_HANDLE_DATA_TAG {
+    _unused: [u8; 0],
+}
+pub type IOTHUB_DEVICE_CLIENT_LL_HANDLE = *mut IOTHUB_CLIENT_CORE_LL_HANDLE_DATA_TAG;
+
+#[repr(C)]
+#[derive(Debug, Copy, Clone)]
+pub struct IOTHUB_MESSAGE_HANDLE_DATA_TAG {
+    _unused: [u8; 0],
+}
+pub type IOTHUB_MESSAGE_HANDLE = *mut IOTHUB_MESSAGE_HANDLE_DATA_TAG;
+
+#[repr(C)]
+#[derive(Debug, Copy, Clone)]
+pub struct TRANSPORT_PROVIDER_TAG {
+    _unused: [u8; 0],
+}
+pub type TRANSPORT_PROVIDER = TRANSPORT_PROVIDER_TAG;
+pub type IOTHUB_CLIENT_TRANSPORT_PROVIDER =
+    Option<unsafe extern "C" fn() -> *const TRANSPORT_PROVIDER>;
+
+pub type IOTHUB_CLIENT_RESULT_TAG = u32;
+pub use self::IOTHUB_CLIENT_RESULT_TAG as IOTHUB_CLIENT_RESULT;
+pub const IOTHUB_CLIENT_RESULT_TAG_IOTHUB_CLIENT_OK: IOTHUB_CLIENT_RESULT_TAG = 0;
+pub const IOTHUB_CLIENT_RESULT_TAG_IOTHUB_CLIENT_INVALID_ARG: IOTHUB_CLIENT_RESULT_TAG = 1;
+pub const IOTHUB_CLIENT_RESULT_TAG_IOTHUB_CLIENT_ERROR: IOTHUB_CLIENT_RESULT_TAG = 2;
+pub const IOTHUB_CLIENT_RESULT_TAG_IOTHUB_CLIENT_INVALID_SIZE: IOTHUB_CLIENT_RESULT_TAG = 3;
+pub const IOTHUB_CLIENT_RESULT_TAG_IOTHUB_CLIENT_INDEFINITE_TIME: IOTHUB_CLIENT_RESULT_TAG = 4;
+
+pub type IOTHUB_CLIENT_CONFIRMATION_RESULT_TAG = u32;
+pub use self::IOTHUB_CLIENT_CONFIRMATION_RESULT_TAG as IOTHUB_CLIENT_CONFIRMATION_RESULT;
+pub const IOTHUB_CLIENT_CONFIRMATION_RESULT_TAG_IOTHUB_CLIENT_CONFIRMATION_OK:
+    IOTHUB_CLIENT_CONFIRMATION_RESULT_TAG = 0;
+
+pub type IOTHUB_CLIENT_CONNECTION_STATUS_TAG = u32;
+pub use self::IOTHUB_CLIENT_CONNECTION_STATUS_TAG as IOTHUB_CLIENT_CONNECTION_STATUS;
+pub const IOTHUB_CLIENT_CONNECTION_STATUS_TAG_IOTHUB_CLIENT_CONNECTION_AUTHENTICATED:
+    IOTHUB_CLIENT_CONNECTION_STATUS_TAG = 0;
+pub const IOTHUB_CLIENT_CONNECTION_STATUS_TAG_IOTHUB_CLIENT_CONNECTION_UNAUTHENTICATED:
+    IOTHUB_CLIENT_CONNECTION_STATUS_TAG = 1;
+
+pub type IOTHUB_CLIENT_CONNECTION_STATUS_REASON_TAG = u32;
+pub use self::IOTHUB_CLIENT_CONNECTION_STATUS_REASON_TAG as IOTHUB_CLIENT_CONNECTION_STATUS_REASON;
+pub const IOTHUB_CLIENT_CONNECTION_STATUS_REASON_TAG_IOTHUB_CLIENT_CONNECTION_OK:
+    IOTHUB_CLIENT_CONNECTION_STATUS_REASON_TAG = 6;
+
+pub type DEVICE_TWIN_UPDATE_STATE_TAG = u32;
+pub use self::DEVICE_TWIN_UPDATE_STATE_TAG as DEVICE_TWIN_UPDATE_STATE;
+pub const DEVICE_TWIN_UPDATE_STATE_TAG_DEVICE_TWIN_UPDATE_COMPLETE: DEVICE_TWIN_UPDATE_STATE_TAG =
+    0;
+pub const DEVICE_TWIN_UPDATE_STATE_TAG_DEVICE_TWIN_UPDATE_PARTIAL: DEVICE_TWIN_UPDATE_STATE_TAG = 1;
+
+pub type IOTHUB_SECURITY_TYPE_TAG = u32;
+pub use self::IOTHUB_SECURITY_TYPE_TAG as IOTHUB_SECURITY_TYPE;
+pub const IOTHUB_SECURITY_TYPE_TAG_IOTHUB_SECURITY_TYPE_UNKNOWN: IOTHUB_SECURITY_TYPE_TAG = 0;
+pub const IOTHUB_SECURITY_TYPE_TAG_IOTHUB_SECURITY_TYPE_SAS: IOTHUB_SECURITY_TYPE_TAG = 1;
+pub const IOTHUB_SECURITY_TYPE_TAG_IOTHUB_SECURITY_TYPE_X509: IOTHUB_SECURITY_TYPE_TAG = 2;
+
+pub type IOTHUB_CLIENT_EVENT_CONFIRMATION_CALLBACK = Option<
+    unsafe extern "C" fn(
+        result: IOTHUB_CLIENT_CONFIRMATION_RESULT,
+        userContextCallback: *mut c_void,
+    ),
+>;
+
+pub type IOTHUB_CLIENT_CONNECTION_STATUS_CALLBACK = Option<
+    unsafe extern "C" fn(
+        result: IOTHUB_CLIENT_CONNECTION_STATUS,
+        reason: IOTHUB_CLIENT_CONNECTION_STATUS_REASON,
+        userContextCallback: *mut c_void,
+    ),
+>;
+
+pub type IOTHUB_CLIENT_DEVICE_TWIN_CALLBACK = Option<
+    unsafe extern "C" fn(
+        update_state: DEVICE_TWIN_UPDATE_STATE,
+        payLoad: *const c_uchar,
+        size: usize,
+        userContextCallback: *mut c_void,
+    ),
+>;
+
+pub type IOTHUB_CLIENT_DEVICE_METHOD_CALLBACK_ASYNC = Option<
+    unsafe extern "C" fn(
+        method_name: *const c_char,
+        payload: *const c_uchar,
+        size: usize,
+        response: *mut *mut c_uchar,
+        response_size: *mut usize,
+        userContextCallback: *mut c_void,
+    ) -> c_int,
+>;
+
+pub type AZURE_SPHERE_PROV_RESULT = u32;
+pub const AZURE_SPHERE_PROV_RESULT_AZURE_SPHERE_PROV_RESULT_OK: AZURE_SPHERE_PROV_RESULT = 0;
+pub const AZURE_SPHERE_PROV_RESULT_AZURE_SPHERE_PROV_RESULT_INVALID_PARAM:
+    AZURE_SPHERE_PROV_RESULT = 1;
+pub const AZURE_SPHERE_PROV_RESULT_AZURE_SPHERE_PROV_RESULT_NETWORK_NOT_READY:
+    AZURE_SPHERE_PROV_RESULT = 2;
+pub const AZURE_SPHERE_PROV_RESULT_AZURE_SPHERE_PROV_RESULT_DEVICEAUTH_NOT_READY:
+    AZURE_SPHERE_PROV_RESULT = 3;
+pub const AZURE_SPHERE_PROV_RESULT_AZURE_SPHERE_PROV_RESULT_PROV_DEVICE_ERROR:
+    AZURE_SPHERE_PROV_RESULT = 4;
+pub const AZURE_SPHERE_PROV_RESULT_AZURE_SPHERE_PROV_RESULT_GENERIC_ERROR:
+    AZURE_SPHERE_PROV_RESULT = 5;
+
+pub type PROV_DEVICE_RESULT = u32;
+
+#[repr(C)]
+#[derive(Debug, Copy, Clone)]
+pub struct AZURE_SPHERE_PROV_RETURN_VALUE {
+    pub result: AZURE_SPHERE_PROV_RESULT,
+    pub prov_device_error: PROV_DEVICE_RESULT,
+    pub iothub_client_error: IOTHUB_CLIENT_RESULT,
+}